@@ -93,8 +93,14 @@ fn watch_with_duration(path: &Path, duration: Duration) -> impl Stream<Item = ()
         config,
     )
     .unwrap_or_else(|_| panic!("could not create watch on: {path:?}"));
+    // Directories are watched with their subdirectories, so nested files are reloaded too
+    let recursive_mode = if is_dir {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
     watcher
-        .watch(&path, RecursiveMode::NonRecursive)
+        .watch(&path, recursive_mode)
         .unwrap_or_else(|_| panic!("could not watch: {path:?}"));
     // Tell watchers once they should read the file once,
     // then listen to fs events.
@@ -25,6 +25,7 @@ pub struct ManifestOperation {
     /// The operation body.
     pub body: String,
     /// The client name associated with the operation. If None, can be any client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub client_name: Option<String>,
 }
 
@@ -0,0 +1,278 @@
+//! Audit log of tool calls
+//!
+//! When a sink is configured, every tool call is recorded with the client that made it, the
+//! subject of its validated token, the tool and its arguments, the outcome and the latency.
+//! Records are sent to each sink: a JSON Lines file rotated like the server log, and an OTLP
+//! log exporter. Secrets are redacted from records before they reach any sink.
+
+mod file;
+mod otlp;
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use rmcp::model::CallToolResult;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    errors::{McpError, ServerError},
+    introspection::tools::execute::EXECUTE_TOOL_NAME,
+    redaction::{Redactor, redactor},
+};
+
+pub use file::FileSinkConfig;
+pub use otlp::OtlpSinkConfig;
+
+/// Configuration for the audit log
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// Write records as JSON lines to a rotated file
+    pub file: Option<FileSinkConfig>,
+
+    /// Export records as OpenTelemetry logs over OTLP
+    pub otlp: Option<OtlpSinkConfig>,
+}
+
+impl AuditConfig {
+    /// Whether any sink is configured
+    pub fn is_enabled(&self) -> bool {
+        self.file.is_some() || self.otlp.is_some()
+    }
+}
+
+/// A destination for audit records
+pub trait AuditSink: Send + Sync {
+    /// Write a record. Sinks must not block the tool call for long, and report their own
+    /// failures since the tool call can't fail because of them.
+    fn record(&self, record: &AuditRecord);
+}
+
+/// The outcome of a tool call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    /// The tool returned a result
+    Success,
+
+    /// The tool returned a result flagged as an error, such as a response with only GraphQL
+    /// errors or a cancelled mutation
+    ToolError,
+
+    /// The tool call was rejected or failed
+    Error,
+}
+
+impl AuditOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditOutcome::Success => "success",
+            AuditOutcome::ToolError => "tool_error",
+            AuditOutcome::Error => "error",
+        }
+    }
+}
+
+/// The client that made a tool call
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AuditClient {
+    /// The client name sent when initializing the session
+    pub name: Option<String>,
+
+    /// The client version sent when initializing the session
+    pub version: Option<String>,
+
+    /// The subject of the client's validated token
+    pub subject: Option<String>,
+}
+
+/// A record of a tool call
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    pub client: AuditClient,
+    pub tool: String,
+
+    /// The arguments of the tool call, which are the variables of the operation for operation
+    /// tools
+    pub arguments: Value,
+
+    pub outcome: AuditOutcome,
+
+    /// The error message, if the tool call failed
+    pub error: Option<String>,
+
+    pub latency_ms: u64,
+}
+
+impl AuditRecord {
+    /// Record the result of a tool call
+    pub(crate) fn new(
+        client: AuditClient,
+        tool: String,
+        arguments: Value,
+        result: &Result<CallToolResult, McpError>,
+        latency: Duration,
+    ) -> Self {
+        let (outcome, error) = match result {
+            Ok(result) if result.is_error == Some(true) => (AuditOutcome::ToolError, None),
+            Ok(_) => (AuditOutcome::Success, None),
+            Err(e) => (AuditOutcome::Error, Some(e.message.to_string())),
+        };
+        Self {
+            timestamp: Utc::now(),
+            client,
+            tool,
+            arguments,
+            outcome,
+            error,
+            latency_ms: u64::try_from(latency.as_millis()).unwrap_or(u64::MAX),
+        }
+    }
+}
+
+/// The audit log, sending each record to every configured sink
+#[derive(Clone)]
+pub struct AuditLog {
+    sinks: Vec<Arc<dyn AuditSink>>,
+    redactor: Arc<Redactor>,
+}
+
+impl AuditLog {
+    /// Create the sinks of an audit log
+    #[allow(clippy::result_large_err)]
+    pub fn new(config: &AuditConfig) -> Result<Self, ServerError> {
+        let mut sinks: Vec<Arc<dyn AuditSink>> = Vec::new();
+        if let Some(file) = &config.file {
+            sinks.push(Arc::new(file.sink()?));
+        }
+        if let Some(otlp) = &config.otlp {
+            sinks.push(Arc::new(otlp.sink()?));
+        }
+        Ok(Self {
+            sinks,
+            redactor: Arc::new(redactor().clone()),
+        })
+    }
+
+    /// Redact a record and send it to every sink
+    pub(crate) fn record(&self, mut record: AuditRecord) {
+        if record.tool == EXECUTE_TOOL_NAME {
+            // The variables of the execute tool are an argument beside the operation
+            if let Some(variables) = record.arguments.get_mut("variables") {
+                self.redactor.redact_variables(variables);
+            }
+            self.redactor.redact_value(&mut record.arguments);
+        } else {
+            self.redactor.redact_variables(&mut record.arguments);
+        }
+        if let Some(error) = &mut record.error {
+            *error = self.redactor.redact_str(error).into_owned();
+        }
+
+        for sink in &self.sinks {
+            sink.record(&record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use rmcp::model::{Content, ErrorCode};
+    use serde_json::json;
+
+    use super::*;
+    use crate::redaction::{REDACTED, RedactionConfig};
+
+    #[derive(Default)]
+    struct MemorySink(Mutex<Vec<AuditRecord>>);
+
+    impl AuditSink for MemorySink {
+        fn record(&self, record: &AuditRecord) {
+            self.0.lock().unwrap().push(record.clone());
+        }
+    }
+
+    #[test]
+    fn it_redacts_variables() {
+        let sink = Arc::new(MemorySink::default());
+        let audit_log = AuditLog {
+            sinks: vec![sink.clone()],
+            redactor: Arc::new(
+                Redactor::new(&RedactionConfig {
+                    variables: vec!["input.password".parse().unwrap()],
+                    ..Default::default()
+                })
+                .unwrap(),
+            ),
+        };
+        let record = |tool: &str, arguments| {
+            AuditRecord::new(
+                AuditClient::default(),
+                String::from(tool),
+                arguments,
+                &Ok(CallToolResult::success(vec![])),
+                Duration::from_millis(12),
+            )
+        };
+
+        audit_log.record(record(
+            "CreateUser",
+            json!({ "input": { "name": "Ada", "password": "secret" } }),
+        ));
+        audit_log.record(record(
+            EXECUTE_TOOL_NAME,
+            json!({
+                "query": "mutation CreateUser($input: UserInput!) { createUser(input: $input) { id } }",
+                "variables": { "input": { "password": "secret" }, "token": "Bearer abc" },
+            }),
+        ));
+
+        let records = sink.0.lock().unwrap();
+        assert_eq!(
+            records[0].arguments,
+            json!({ "input": { "name": "Ada", "password": REDACTED } })
+        );
+        assert_eq!(
+            records[1].arguments["variables"],
+            json!({ "input": { "password": REDACTED }, "token": "Bearer [REDACTED]" })
+        );
+        assert_eq!(records[0].latency_ms, 12);
+    }
+
+    #[test]
+    fn it_records_the_outcome() {
+        let outcome = |result: Result<CallToolResult, McpError>| {
+            let record = AuditRecord::new(
+                AuditClient::default(),
+                String::from("GetUser"),
+                Value::Null,
+                &result,
+                Duration::ZERO,
+            );
+            (record.outcome, record.error)
+        };
+
+        assert_eq!(
+            outcome(Ok(CallToolResult::success(vec![]))),
+            (AuditOutcome::Success, None)
+        );
+        assert_eq!(
+            outcome(Ok(CallToolResult::error(vec![Content::text("failed")]))),
+            (AuditOutcome::ToolError, None)
+        );
+        assert_eq!(
+            outcome(Err(McpError::new(
+                ErrorCode::INVALID_PARAMS,
+                "Invalid input",
+                None
+            ))),
+            (AuditOutcome::Error, Some(String::from("Invalid input")))
+        );
+    }
+}
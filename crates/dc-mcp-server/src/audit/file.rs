@@ -0,0 +1,121 @@
+use std::{io::Write as _, path::PathBuf};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tracing::error;
+use tracing_appender::{
+    non_blocking::{NonBlocking, NonBlockingBuilder, WorkerGuard},
+    rolling::RollingFileAppender,
+};
+
+use super::{AuditRecord, AuditSink};
+use crate::{errors::ServerError, log_rotation_kind::LogRotationKind};
+
+/// Configuration for writing audit records to a file
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FileSinkConfig {
+    /// The directory to write audit files to
+    pub path: PathBuf,
+
+    /// Audit file rotation period [default: Hourly]
+    #[serde(default = "default_rotation")]
+    pub rotation: LogRotationKind,
+}
+
+fn default_rotation() -> LogRotationKind {
+    LogRotationKind::Hourly
+}
+
+impl FileSinkConfig {
+    #[allow(clippy::result_large_err)]
+    pub(super) fn sink(&self) -> Result<FileSink, ServerError> {
+        std::fs::create_dir_all(&self.path)?;
+        let appender = RollingFileAppender::builder()
+            .rotation(self.rotation.clone().into())
+            .filename_prefix("audit")
+            .filename_suffix("jsonl")
+            .build(&self.path)
+            .map_err(|e| ServerError::Audit(e.to_string()))?;
+
+        // Audit records must not be dropped when the writer falls behind
+        let (writer, guard) = NonBlockingBuilder::default().lossy(false).finish(appender);
+        Ok(FileSink {
+            writer,
+            _guard: guard,
+        })
+    }
+}
+
+/// Writes audit records as JSON lines, without blocking the tool call on the file system
+pub(super) struct FileSink {
+    writer: NonBlocking,
+
+    /// Flushes the remaining records when the sink is dropped
+    _guard: WorkerGuard,
+}
+
+impl AuditSink for FileSink {
+    fn record(&self, record: &AuditRecord) {
+        let mut line = match serde_json::to_vec(record) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Failed to serialize audit record: {e}");
+                return;
+            }
+        };
+        line.push(b'\n');
+        if let Err(e) = self.writer.clone().write_all(&line) {
+            error!("Failed to write audit record: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rmcp::model::CallToolResult;
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::audit::{AuditClient, AuditOutcome};
+
+    #[test]
+    fn it_writes_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let sink = FileSinkConfig {
+            path: dir.path().to_path_buf(),
+            rotation: LogRotationKind::Never,
+        }
+        .sink()
+        .unwrap();
+
+        let record = AuditRecord::new(
+            AuditClient {
+                name: Some(String::from("claude-ai")),
+                version: Some(String::from("0.1.0")),
+                subject: Some(String::from("user-1")),
+            },
+            String::from("GetUser"),
+            json!({ "id": "1" }),
+            &Ok(CallToolResult::success(vec![])),
+            Duration::from_millis(5),
+        );
+        sink.record(&record);
+        sink.record(&record);
+        drop(sink);
+
+        let contents = std::fs::read_to_string(dir.path().join("audit.jsonl")).unwrap();
+        let lines: Vec<Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["tool"], "GetUser");
+        assert_eq!(lines[0]["client"]["subject"], "user-1");
+        assert_eq!(lines[0]["arguments"], json!({ "id": "1" }));
+        assert_eq!(lines[0]["outcome"], AuditOutcome::Success.as_str());
+        assert_eq!(lines[0]["latency_ms"], 5);
+    }
+}
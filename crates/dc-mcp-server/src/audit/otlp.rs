@@ -0,0 +1,112 @@
+use opentelemetry::logs::{AnyValue, LogRecord as _, Logger as _, LoggerProvider as _, Severity};
+use opentelemetry_otlp::WithExportConfig as _;
+use opentelemetry_sdk::{
+    Resource,
+    logs::{SdkLogger, SdkLoggerProvider},
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tracing::error;
+
+use super::{AuditRecord, AuditSink};
+use crate::errors::ServerError;
+
+/// The event name of exported audit records
+const EVENT_NAME: &str = "dc.mcp.audit";
+
+/// Configuration for exporting audit records as OpenTelemetry logs
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct OtlpSinkConfig {
+    /// The OTLP endpoint to export records to
+    pub endpoint: String,
+
+    /// The OTLP protocol, either `grpc` or `http/protobuf`
+    pub protocol: String,
+}
+
+impl Default for OtlpSinkConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: "http://localhost:4317".into(),
+            protocol: "grpc".into(),
+        }
+    }
+}
+
+impl OtlpSinkConfig {
+    #[allow(clippy::result_large_err)]
+    pub(super) fn sink(&self) -> Result<OtlpSink, ServerError> {
+        let exporter = match self.protocol.as_str() {
+            "grpc" => opentelemetry_otlp::LogExporter::builder()
+                .with_tonic()
+                .with_endpoint(self.endpoint.clone())
+                .build(),
+            "http/protobuf" => opentelemetry_otlp::LogExporter::builder()
+                .with_http()
+                .with_endpoint(self.endpoint.clone())
+                .build(),
+            other => {
+                return Err(ServerError::Audit(format!(
+                    "Unsupported OTLP protocol: {other}. Supported protocols are: grpc, http/protobuf"
+                )));
+            }
+        }
+        .map_err(|e| ServerError::Audit(e.to_string()))?;
+
+        let provider = SdkLoggerProvider::builder()
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(env!("CARGO_PKG_NAME"))
+                    .build(),
+            )
+            .with_batch_exporter(exporter)
+            .build();
+        Ok(OtlpSink {
+            logger: provider.logger("dc-mcp-server-audit"),
+            _provider: provider,
+        })
+    }
+}
+
+/// Exports audit records as OpenTelemetry logs, with the JSON record as the body and its
+/// main fields as attributes
+pub(super) struct OtlpSink {
+    logger: SdkLogger,
+
+    /// Exports the remaining records when the sink is dropped
+    _provider: SdkLoggerProvider,
+}
+
+impl AuditSink for OtlpSink {
+    fn record(&self, record: &AuditRecord) {
+        let body = match serde_json::to_string(record) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("Failed to serialize audit record: {e}");
+                return;
+            }
+        };
+
+        let mut log_record = self.logger.create_log_record();
+        log_record.set_event_name(EVENT_NAME);
+        log_record.set_timestamp(record.timestamp.into());
+        log_record.set_severity_number(Severity::Info);
+        log_record.set_severity_text("INFO");
+        log_record.set_body(AnyValue::from(body));
+        log_record.add_attribute("tool", record.tool.clone());
+        log_record.add_attribute("outcome", record.outcome.as_str());
+        log_record.add_attribute("latency_ms", record.latency_ms as i64);
+        if let Some(name) = &record.client.name {
+            log_record.add_attribute("client.name", name.clone());
+        }
+        if let Some(version) = &record.client.version {
+            log_record.add_attribute("client.version", version.clone());
+        }
+        if let Some(subject) = &record.client.subject {
+            log_record.add_attribute("client.subject", subject.clone());
+        }
+        self.logger.emit(log_record);
+    }
+}
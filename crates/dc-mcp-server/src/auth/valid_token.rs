@@ -12,7 +12,14 @@ use url::Url;
 /// Note: This is used as a marker to ensure that we have validated this
 /// separately from just reading the header itself.
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) struct ValidToken(pub(super) Authorization<Bearer>);
+pub(crate) struct ValidToken(pub(super) Authorization<Bearer>, pub(super) String);
+
+impl ValidToken {
+    /// The user who owns this token, from its `sub` claim
+    pub(crate) fn subject(&self) -> &str {
+        &self.1
+    }
+}
 
 impl Deref for ValidToken {
     type Target = Authorization<Bearer>;
@@ -104,8 +111,8 @@ pub(super) trait ValidateToken {
             };
 
             match decode::<Claims>(jwt, &jwk.decoding_key, &validation) {
-                Ok(_) => {
-                    return Some(ValidToken(token));
+                Ok(data) => {
+                    return Some(ValidToken(token, data.claims.sub));
                 }
                 Err(e) => warn!("Token failed validation with error: {e}"),
             };
@@ -224,15 +231,9 @@ mod test {
         };
 
         let token = jwt.token().to_string();
-        assert_eq!(
-            test_validator
-                .validate(jwt)
-                .await
-                .expect("valid token")
-                .0
-                .token(),
-            token
-        );
+        let valid_token = test_validator.validate(jwt).await.expect("valid token");
+        assert_eq!(valid_token.0.token(), token);
+        assert_eq!(valid_token.subject(), "test user");
     }
 
     #[traced_test]
@@ -0,0 +1,193 @@
+//! Confirmation of mutations by the user
+//!
+//! When enabled, mutation tools and `execute` calls running a mutation are only run once the
+//! user approves them. The server sends an MCP elicitation request showing the operation and
+//! its variables, and returns a cancelled result to the model if the user declines, cancels,
+//! or doesn't answer in time, or if the client can't ask the user.
+
+use std::time::Duration;
+
+use rmcp::{
+    Peer, RoleServer,
+    model::{
+        CallToolResult, Content, CreateElicitationRequestParam, CreateElicitationResult,
+        ElicitationAction,
+    },
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::{debug, warn};
+
+use crate::{
+    errors::McpError,
+    graphql::{Executable, OperationDetails},
+};
+
+/// The field of the elicitation response with the user's decision
+const CONFIRM_FIELD: &str = "confirm";
+
+/// Configuration for confirming mutations with the user before they are run
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct MutationConfirmationConfig {
+    /// Ask the user to confirm each mutation before it is run
+    pub enabled: bool,
+
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// How long to wait for the user to answer before cancelling the mutation (default: 5m)
+    pub timeout: Duration,
+}
+
+impl Default for MutationConfirmationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+impl MutationConfirmationConfig {
+    /// Ask the user to confirm a mutation, returning the result to send instead of running it
+    /// if it isn't confirmed
+    pub(crate) async fn confirm(
+        &self,
+        peer: &Peer<RoleServer>,
+        tool_name: &str,
+        executable: &impl Executable,
+        input: &Value,
+    ) -> Result<Option<CallToolResult>, McpError> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        if !peer.supports_elicitation() {
+            return Ok(Some(cancelled(
+                tool_name,
+                "the client can't ask the user to confirm it",
+            )));
+        }
+
+        let operation = executable.operation(input.clone())?;
+        let variables = executable.variables(input.clone())?;
+        match peer
+            .create_elicitation_with_timeout(
+                request(tool_name, &operation, &variables),
+                Some(self.timeout),
+            )
+            .await
+        {
+            Ok(result) if is_approved(&result) => {
+                debug!("User confirmed mutation {tool_name}");
+                Ok(None)
+            }
+            Ok(_) => Ok(Some(cancelled(tool_name, "the user didn't confirm it"))),
+            Err(e) => {
+                warn!("Failed to get confirmation for mutation {tool_name}: {e}");
+                Ok(Some(cancelled(
+                    tool_name,
+                    "the user's confirmation couldn't be obtained",
+                )))
+            }
+        }
+    }
+}
+
+/// The elicitation request showing the user the mutation to confirm
+fn request(
+    tool_name: &str,
+    operation: &OperationDetails,
+    variables: &Value,
+) -> CreateElicitationRequestParam {
+    let mut message = format!(
+        "The {tool_name} tool wants to run this mutation:\n\n{}",
+        operation.query
+    );
+    if !variables.is_null() {
+        let variables =
+            serde_json::to_string_pretty(variables).unwrap_or_else(|_| variables.to_string());
+        message.push_str(&format!("\n\nVariables:\n{variables}"));
+    }
+
+    let requested_schema = json!({
+        "type": "object",
+        "properties": {
+            CONFIRM_FIELD: {
+                "type": "boolean",
+                "title": "Run the mutation",
+                "default": true,
+            },
+        },
+    });
+    CreateElicitationRequestParam {
+        message,
+        requested_schema: requested_schema.as_object().cloned().unwrap_or_default(),
+    }
+}
+
+/// Whether the user accepted the request without unchecking the confirmation
+fn is_approved(result: &CreateElicitationResult) -> bool {
+    result.action == ElicitationAction::Accept
+        && result
+            .content
+            .as_ref()
+            .and_then(|content| content.get(CONFIRM_FIELD))
+            .and_then(Value::as_bool)
+            .unwrap_or(true)
+}
+
+/// The result returned to the model for a mutation that wasn't run
+fn cancelled(tool_name: &str, reason: &str) -> CallToolResult {
+    CallToolResult::error(vec![Content::text(format!(
+        "The {tool_name} mutation was cancelled because {reason}. It was not run."
+    ))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_shows_the_operation_and_variables() {
+        let request = request(
+            "CreateUser",
+            &OperationDetails {
+                query: "mutation CreateUser($name: String!) { createUser(name: $name) { id } }"
+                    .to_string(),
+                operation_name: Some("CreateUser".to_string()),
+            },
+            &json!({ "name": "Ada" }),
+        );
+
+        insta::assert_snapshot!(request.message, @r#"
+        The CreateUser tool wants to run this mutation:
+
+        mutation CreateUser($name: String!) { createUser(name: $name) { id } }
+
+        Variables:
+        {
+          "name": "Ada"
+        }
+        "#);
+        assert_eq!(request.requested_schema["type"], "object");
+    }
+
+    #[test]
+    fn it_only_runs_mutations_the_user_accepts() {
+        let result = |action, content| CreateElicitationResult { action, content };
+
+        assert!(is_approved(&result(ElicitationAction::Accept, None)));
+        assert!(is_approved(&result(
+            ElicitationAction::Accept,
+            Some(json!({ "confirm": true }))
+        )));
+        assert!(!is_approved(&result(
+            ElicitationAction::Accept,
+            Some(json!({ "confirm": false }))
+        )));
+        assert!(!is_approved(&result(ElicitationAction::Decline, None)));
+        assert!(!is_approved(&result(ElicitationAction::Cancel, None)));
+    }
+}
@@ -0,0 +1,285 @@
+//! Contract schemas
+//!
+//! Like Apollo contracts, a contract exposes a variant of the schema made by filtering the
+//! elements of the supergraph by their `@tag` directives. Types are matched by their own tags,
+//! and fields, input fields and enum values by their own tags and those of their type.
+//!
+//! If any tags are included, only the elements tagged with one of them are kept, along with
+//! the types that have tagged members. Elements tagged with an excluded tag are then removed.
+//! Everything that can no longer be used without the removed elements is removed as well.
+
+use std::collections::{HashMap, HashSet};
+
+use apollo_compiler::{
+    Name, Schema,
+    ast::{Directive, DirectiveList},
+    schema::ExtendedType,
+    validation::Valid,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tracing::error;
+
+use crate::{
+    errors::ServerError,
+    operations::Operation,
+    schema_filter::{filter_schema, member_names},
+};
+
+/// The name of the directive that tags schema elements
+const TAG_DIRECTIVE: &str = "tag";
+
+/// Configuration for a contract variant of the schema
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct ContractConfig {
+    /// The tags of the elements to keep. If empty, all elements are kept.
+    pub include: Vec<String>,
+
+    /// The tags of the elements to remove
+    pub exclude: Vec<String>,
+}
+
+impl ContractConfig {
+    /// Whether the contract filters the schema
+    pub fn is_enabled(&self) -> bool {
+        !self.include.is_empty() || !self.exclude.is_empty()
+    }
+
+    /// Make the contract variant of an API schema, using the tags of the schema it was made from
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn apply(
+        &self,
+        tagged_schema: &Schema,
+        api_schema: &Valid<Schema>,
+    ) -> Result<Valid<Schema>, ServerError> {
+        if !self.is_enabled() {
+            return Ok(api_schema.clone());
+        }
+
+        let tags = tags(tagged_schema);
+        let has_tag = |coordinate: &str, wanted: &[String]| {
+            tags.get(coordinate)
+                .is_some_and(|tags| wanted.iter().any(|tag| tags.contains(tag)))
+        };
+        let is_included =
+            |coordinate: &str| self.include.is_empty() || has_tag(coordinate, &self.include);
+
+        filter_schema(
+            api_schema,
+            |name, ty| {
+                !has_tag(name, &self.exclude)
+                    && (is_included(name)
+                        || member_names(ty).any(|member| is_included(&format!("{name}.{member}"))))
+            },
+            |type_name, name| {
+                let coordinate = format!("{type_name}.{name}");
+                !has_tag(&coordinate, &self.exclude)
+                    && (is_included(type_name) || is_included(&coordinate))
+            },
+        )
+    }
+
+    /// Whether an operation can be used with the contract schema, logging why not. Operations
+    /// targeting another endpoint aren't affected by the contract.
+    pub(crate) fn allows(&self, operation: &Operation, schema: &Valid<Schema>) -> bool {
+        if !self.is_enabled() || operation.endpoint().is_some() {
+            return true;
+        }
+        match operation.validate(schema) {
+            Ok(()) => true,
+            Err(e) => {
+                error!(
+                    "Operation {} is not valid against the contract schema: {}",
+                    operation.operation_name(),
+                    e
+                );
+                false
+            }
+        }
+    }
+}
+
+/// The tags of the types of a schema, keyed by name, and of their members, keyed by
+/// `Type.member`
+fn tags(schema: &Schema) -> HashMap<String, HashSet<String>> {
+    let mut tags: HashMap<String, HashSet<String>> = HashMap::new();
+    for (name, ty) in schema.types.iter().filter(|(_, ty)| !ty.is_built_in()) {
+        let type_tags: HashSet<String> = ty
+            .directives()
+            .get_all(TAG_DIRECTIVE)
+            .filter_map(|directive| tag_name(directive))
+            .collect();
+        if !type_tags.is_empty() {
+            tags.insert(name.to_string(), type_tags);
+        }
+
+        let members: Vec<(&Name, &DirectiveList)> = match ty {
+            ExtendedType::Object(object) => object
+                .fields
+                .iter()
+                .map(|(member, field)| (member, &field.directives))
+                .collect(),
+            ExtendedType::Interface(interface) => interface
+                .fields
+                .iter()
+                .map(|(member, field)| (member, &field.directives))
+                .collect(),
+            ExtendedType::InputObject(input) => input
+                .fields
+                .iter()
+                .map(|(member, field)| (member, &field.directives))
+                .collect(),
+            ExtendedType::Enum(enum_type) => enum_type
+                .values
+                .iter()
+                .map(|(member, value)| (member, &value.directives))
+                .collect(),
+            ExtendedType::Scalar(_) | ExtendedType::Union(_) => Vec::new(),
+        };
+        for (member, directives) in members {
+            let member_tags: HashSet<String> = directives
+                .get_all(TAG_DIRECTIVE)
+                .filter_map(|directive| tag_name(directive))
+                .collect();
+            if !member_tags.is_empty() {
+                tags.insert(format!("{name}.{member}"), member_tags);
+            }
+        }
+    }
+    tags
+}
+
+/// The name of a `@tag` directive
+fn tag_name(directive: &Directive) -> Option<String> {
+    directive
+        .specified_argument_by_name("name")?
+        .as_str()
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_federation::{ApiSchemaOptions, Supergraph};
+
+    use super::*;
+    use crate::operations::{MutationMode, RawOperation};
+
+    const SUPERGRAPH: &str = r#"
+        schema
+        @link(url: "https://specs.apollo.dev/link/v1.0")
+        @link(url: "https://specs.apollo.dev/join/v0.3", for: EXECUTION)
+        @link(url: "https://specs.apollo.dev/tag/v0.3") {
+            query: Query
+        }
+
+        directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+        directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+        directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+        directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+        directive @tag(name: String!) repeatable on FIELD_DEFINITION | OBJECT | INTERFACE | UNION | ARGUMENT_DEFINITION | SCALAR | ENUM | ENUM_VALUE | INPUT_OBJECT | INPUT_FIELD_DEFINITION | SCHEMA
+
+        scalar join__FieldSet
+        scalar link__Import
+
+        enum join__Graph {
+            USERS @join__graph(name: "users", url: "http://localhost:4001")
+        }
+
+        enum link__Purpose {
+            SECURITY
+            EXECUTION
+        }
+
+        type Query @join__type(graph: USERS) {
+            me: User @tag(name: "public")
+            users: [User] @tag(name: "internal")
+            billing: Billing @tag(name: "public")
+        }
+
+        type User @join__type(graph: USERS) @tag(name: "public") {
+            id: ID!
+            name: String
+            email: String @tag(name: "internal")
+        }
+
+        type Billing @join__type(graph: USERS) @tag(name: "internal") {
+            balance: Int
+        }
+    "#;
+
+    fn contract(include: &[&str], exclude: &[&str]) -> Valid<Schema> {
+        let supergraph = Supergraph::new_with_router_specs(SUPERGRAPH).unwrap();
+        let api_schema = supergraph
+            .to_api_schema(ApiSchemaOptions::default())
+            .unwrap();
+        ContractConfig {
+            include: include.iter().map(ToString::to_string).collect(),
+            exclude: exclude.iter().map(ToString::to_string).collect(),
+        }
+        .apply(supergraph.schema.schema(), api_schema.schema())
+        .unwrap()
+    }
+
+    fn fields(schema: &Schema, type_name: &str) -> Vec<String> {
+        match schema.types.get(type_name) {
+            Some(ExtendedType::Object(object)) => {
+                object.fields.keys().map(ToString::to_string).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn it_removes_excluded_elements() {
+        let schema = contract(&[], &["internal"]);
+
+        assert_eq!(fields(&schema, "Query"), ["me"]);
+        assert_eq!(fields(&schema, "User"), ["id", "name"]);
+        assert!(!schema.types.contains_key("Billing"));
+    }
+
+    #[test]
+    fn it_keeps_only_included_elements() {
+        let schema = contract(&["public"], &[]);
+
+        // Billing isn't tagged as public, so the field returning it is removed
+        assert_eq!(fields(&schema, "Query"), ["me"]);
+        assert_eq!(fields(&schema, "User"), ["id", "name", "email"]);
+    }
+
+    #[test]
+    fn it_keeps_the_api_schema_without_tags() {
+        let schema = contract(&[], &[]);
+
+        assert_eq!(fields(&schema, "Query"), ["me", "users", "billing"]);
+    }
+
+    #[test]
+    fn it_rejects_operations_invalid_against_the_contract() {
+        let schema = contract(&[], &["internal"]);
+        let config = ContractConfig {
+            exclude: vec!["internal".to_string()],
+            ..Default::default()
+        };
+        let operation = |source_text: &str| {
+            RawOperation::from((source_text.to_string(), None))
+                .into_operation(
+                    &schema,
+                    None,
+                    MutationMode::None,
+                    false,
+                    false,
+                    false,
+                    &HashMap::new(),
+                    &HashMap::new(),
+                )
+                .unwrap()
+                .unwrap()
+        };
+
+        assert!(config.allows(&operation("query Me { me { id } }"), &schema));
+        assert!(!config.allows(&operation("query Users { users { id } }"), &schema));
+    }
+}
@@ -0,0 +1,431 @@
+//! Mapping of GraphQL errors into tool results
+//!
+//! Errors returned by a GraphQL endpoint are classified by their `extensions.code` into a small
+//! set of categories, so the model gets remediation text it can act on along with the path of
+//! each error. Errors without a code get one derived from the HTTP status of the response, and
+//! an endpoint that can't be reached is reported the same way instead of as an opaque failure.
+
+use std::collections::HashMap;
+
+use reqwest::StatusCode;
+use rmcp::model::Content;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+/// The error code given to requests that failed because the endpoint couldn't be reached
+pub(crate) const UNAVAILABLE_CODE: &str = "SERVICE_UNAVAILABLE";
+
+/// The error code of requests that weren't authenticated
+pub(crate) const UNAUTHENTICATED_CODE: &str = "UNAUTHENTICATED";
+
+/// Configuration for mapping GraphQL errors into tool results
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct ErrorMappingConfig {
+    /// How to return responses that have both data and errors
+    pub partial_data: PartialData,
+
+    /// Categories of custom error codes, which take precedence over the built-in codes
+    pub codes: HashMap<String, ErrorCategory>,
+}
+
+/// How to return responses that have both data and errors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PartialData {
+    /// Return the data as a successful result, with the errors alongside
+    #[default]
+    Allow,
+
+    /// Return the data, but flag the result as an error
+    Error,
+
+    /// Drop the data and return only the errors
+    Reject,
+}
+
+/// The category of a GraphQL error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorCategory {
+    /// The request wasn't authenticated, or isn't allowed to access the data
+    Auth,
+
+    /// The operation or its variables are invalid
+    Validation,
+
+    /// The requested data doesn't exist
+    NotFound,
+
+    /// Too many requests were made
+    RateLimited,
+
+    /// The endpoint, or a service behind it, is unavailable
+    UpstreamDown,
+
+    /// Any other error
+    Unknown,
+}
+
+impl ErrorCategory {
+    /// The category of a built-in error code
+    pub fn from_code(code: &str) -> Self {
+        match code.to_uppercase().as_str() {
+            "UNAUTHENTICATED" | "UNAUTHORIZED" | "FORBIDDEN" | "ACCESS_DENIED"
+            | "INVALID_TOKEN" | "TOKEN_EXPIRED" => Self::Auth,
+            "GRAPHQL_PARSE_FAILED"
+            | "GRAPHQL_VALIDATION_FAILED"
+            | "BAD_USER_INPUT"
+            | "BAD_REQUEST"
+            | "VALIDATION_FAILED"
+            | "INVALID_INPUT"
+            | "OPERATION_RESOLUTION_FAILURE" => Self::Validation,
+            "RATE_LIMITED" | "RATE_LIMIT_EXCEEDED" | "TOO_MANY_REQUESTS" | "THROTTLED" => {
+                Self::RateLimited
+            }
+            "SERVICE_UNAVAILABLE"
+            | "UPSTREAM_UNAVAILABLE"
+            | "GATEWAY_TIMEOUT"
+            | "SUBREQUEST_HTTP_ERROR" => Self::UpstreamDown,
+            code if code == "NOT_FOUND" || code.ends_with("_NOT_FOUND") => Self::NotFound,
+            _ => Self::Unknown,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::Auth => "auth",
+            ErrorCategory::Validation => "validation",
+            ErrorCategory::NotFound => "not-found",
+            ErrorCategory::RateLimited => "rate-limited",
+            ErrorCategory::UpstreamDown => "upstream-down",
+            ErrorCategory::Unknown => "unknown",
+        }
+    }
+
+    /// What the model can do about an error in this category
+    pub fn remediation(&self) -> &'static str {
+        match self {
+            ErrorCategory::Auth => {
+                "The request was not authorized. Retrying with the same credentials won't help; ask the user to check their access to this data."
+            }
+            ErrorCategory::Validation => {
+                "The request was invalid. Check the arguments against the tool's input schema, correct them and call the tool again."
+            }
+            ErrorCategory::NotFound => {
+                "The requested data doesn't exist. Check the IDs and other arguments identifying it, or look it up with another tool first."
+            }
+            ErrorCategory::RateLimited => {
+                "Too many requests were made. Wait before calling the tool again, and make fewer calls."
+            }
+            ErrorCategory::UpstreamDown => {
+                "The GraphQL endpoint is unavailable. The arguments aren't the problem; try again later."
+            }
+            ErrorCategory::Unknown => {
+                "The GraphQL endpoint returned an error. Use its message to decide whether to change the arguments or try again."
+            }
+        }
+    }
+}
+
+/// The error code of responses with an HTTP error status, for errors that don't have one
+pub(crate) fn status_code(status: StatusCode) -> Option<&'static str> {
+    match status {
+        StatusCode::UNAUTHORIZED => Some(UNAUTHENTICATED_CODE),
+        StatusCode::FORBIDDEN => Some("FORBIDDEN"),
+        StatusCode::NOT_FOUND => Some("NOT_FOUND"),
+        StatusCode::TOO_MANY_REQUESTS => Some("RATE_LIMITED"),
+        StatusCode::GATEWAY_TIMEOUT => Some("GATEWAY_TIMEOUT"),
+        status if status.is_client_error() => Some("BAD_REQUEST"),
+        status if status.is_server_error() => Some(UNAVAILABLE_CODE),
+        _ => None,
+    }
+}
+
+/// Whether the endpoint rejected a whole request because it wasn't authenticated, rather than
+/// denying access to some of its fields
+pub(crate) fn is_unauthenticated(response: &Value) -> bool {
+    response.get("data").is_none_or(Value::is_null)
+        && response
+            .get("errors")
+            .and_then(Value::as_array)
+            .is_some_and(|errors| {
+                errors.iter().any(|error| {
+                    error.pointer("/extensions/code").and_then(Value::as_str)
+                        == Some(UNAUTHENTICATED_CODE)
+                })
+            })
+}
+
+/// A GraphQL error, classified for the model
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MappedError {
+    pub category: ErrorCategory,
+
+    /// The `extensions.code` of the error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+
+    pub message: String,
+
+    /// The path of the response field that failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    pub remediation: &'static str,
+}
+
+impl MappedError {
+    fn new(error: &Value, config: &ErrorMappingConfig) -> Self {
+        let code = error
+            .pointer("/extensions/code")
+            .and_then(Value::as_str)
+            .map(ToString::to_string);
+        let category = code
+            .as_deref()
+            .map(|code| {
+                config
+                    .codes
+                    .get(code)
+                    .copied()
+                    .unwrap_or_else(|| ErrorCategory::from_code(code))
+            })
+            .unwrap_or(ErrorCategory::Unknown);
+        let message = match error {
+            Value::String(message) => message.clone(),
+            error => error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("Unknown error")
+                .to_string(),
+        };
+        Self {
+            category,
+            code,
+            message,
+            path: error.get("path").and_then(Value::as_array).map(|path| {
+                path.iter()
+                    .map(|segment| match segment {
+                        Value::String(field) => field.clone(),
+                        segment => segment.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(".")
+            }),
+            remediation: category.remediation(),
+        }
+    }
+}
+
+/// The errors of a GraphQL response, mapped according to the configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct MappedErrors {
+    pub errors: Vec<MappedError>,
+
+    /// Whether the response also has data
+    pub partial: bool,
+}
+
+impl MappedErrors {
+    /// Map the errors of a response, if it has any
+    pub fn from_response(response: &Value, config: &ErrorMappingConfig) -> Option<Self> {
+        let errors = match response.get("errors") {
+            Some(Value::Array(errors)) if !errors.is_empty() => errors,
+            _ => return None,
+        };
+        Some(Self {
+            errors: errors
+                .iter()
+                .map(|error| MappedError::new(error, config))
+                .collect(),
+            partial: response.get("data").is_some_and(|data| !data.is_null()),
+        })
+    }
+
+    /// Whether the tool result is an error, dropping the data of the response if partial data
+    /// is rejected
+    pub fn apply(&self, response: &mut Value, partial_data: PartialData) -> bool {
+        match (self.partial, partial_data) {
+            (true, PartialData::Allow) => false,
+            (true, PartialData::Error) => true,
+            (true, PartialData::Reject) => {
+                if let Some(data) = response.get_mut("data") {
+                    *data = Value::Null;
+                }
+                true
+            }
+            (false, _) => true,
+        }
+    }
+
+    /// A summary of the errors for the model
+    pub fn content(&self) -> Content {
+        let mut text = if self.partial {
+            format!(
+                "The GraphQL endpoint returned partial data with {} error(s):",
+                self.errors.len()
+            )
+        } else {
+            format!(
+                "The GraphQL endpoint returned {} error(s):",
+                self.errors.len()
+            )
+        };
+        for error in &self.errors {
+            text.push_str(&format!("\n- [{}]", error.category.as_str()));
+            if let Some(path) = &error.path {
+                text.push_str(&format!(" at {path}"));
+            }
+            text.push_str(&format!(": {}", error.message));
+            if let Some(code) = &error.code {
+                text.push_str(&format!(" ({code})"));
+            }
+        }
+
+        let mut categories: Vec<ErrorCategory> = Vec::new();
+        for error in &self.errors {
+            if !categories.contains(&error.category) {
+                categories.push(error.category);
+            }
+        }
+        for category in categories {
+            text.push_str(&format!("\n{}", category.remediation()));
+        }
+        Content::text(text)
+    }
+
+    /// The errors for the result metadata
+    pub fn meta(&self) -> Value {
+        json!(self.errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_maps_error_codes_to_categories() {
+        let config = ErrorMappingConfig {
+            codes: HashMap::from([(String::from("QUOTA_EXCEEDED"), ErrorCategory::RateLimited)]),
+            ..Default::default()
+        };
+        let response = json!({
+            "data": { "user": null },
+            "errors": [
+                { "message": "Not logged in", "extensions": { "code": "UNAUTHENTICATED" } },
+                { "message": "No such user", "path": ["user", 0, "name"], "extensions": { "code": "USER_NOT_FOUND" } },
+                { "message": "Slow down", "extensions": { "code": "QUOTA_EXCEEDED" } },
+                { "message": "Boom" },
+            ],
+        });
+
+        let errors = MappedErrors::from_response(&response, &config).unwrap();
+
+        assert!(errors.partial);
+        assert_eq!(
+            errors
+                .errors
+                .iter()
+                .map(|error| error.category)
+                .collect::<Vec<_>>(),
+            vec![
+                ErrorCategory::Auth,
+                ErrorCategory::NotFound,
+                ErrorCategory::RateLimited,
+                ErrorCategory::Unknown,
+            ]
+        );
+        assert_eq!(errors.errors[1].path.as_deref(), Some("user.0.name"));
+        insta::assert_snapshot!(errors.content().as_text().unwrap().text, @r"
+        The GraphQL endpoint returned partial data with 4 error(s):
+        - [auth]: Not logged in (UNAUTHENTICATED)
+        - [not-found] at user.0.name: No such user (USER_NOT_FOUND)
+        - [rate-limited]: Slow down (QUOTA_EXCEEDED)
+        - [unknown]: Boom
+        The request was not authorized. Retrying with the same credentials won't help; ask the user to check their access to this data.
+        The requested data doesn't exist. Check the IDs and other arguments identifying it, or look it up with another tool first.
+        Too many requests were made. Wait before calling the tool again, and make fewer calls.
+        The GraphQL endpoint returned an error. Use its message to decide whether to change the arguments or try again.
+        ");
+    }
+
+    #[test]
+    fn it_handles_partial_data() {
+        let response = json!({
+            "data": { "user": { "name": "Ada" } },
+            "errors": [{ "message": "Boom" }],
+        });
+        let errors = MappedErrors::from_response(&response, &Default::default()).unwrap();
+
+        let mut allowed = response.clone();
+        assert!(!errors.apply(&mut allowed, PartialData::Allow));
+        assert_eq!(allowed, response);
+
+        let mut flagged = response.clone();
+        assert!(errors.apply(&mut flagged, PartialData::Error));
+        assert_eq!(flagged, response);
+
+        let mut rejected = response.clone();
+        assert!(errors.apply(&mut rejected, PartialData::Reject));
+        assert_eq!(rejected["data"], Value::Null);
+    }
+
+    #[test]
+    fn it_detects_unauthenticated_requests() {
+        let error =
+            json!({ "message": "Token expired", "extensions": { "code": "UNAUTHENTICATED" } });
+
+        assert!(is_unauthenticated(
+            &json!({ "data": null, "errors": [error] })
+        ));
+        assert!(is_unauthenticated(&json!({ "errors": [error] })));
+        assert!(!is_unauthenticated(
+            &json!({ "data": { "user": null }, "errors": [error] })
+        ));
+        assert!(!is_unauthenticated(
+            &json!({ "data": null, "errors": [{ "message": "Forbidden", "extensions": { "code": "FORBIDDEN" } }] })
+        ));
+    }
+
+    #[test]
+    fn it_derives_codes_from_error_statuses() {
+        let category = |status| status_code(status).map(ErrorCategory::from_code);
+
+        assert_eq!(category(StatusCode::OK), None);
+        assert_eq!(
+            category(StatusCode::UNAUTHORIZED),
+            Some(ErrorCategory::Auth)
+        );
+        assert_eq!(
+            category(StatusCode::NOT_FOUND),
+            Some(ErrorCategory::NotFound)
+        );
+        assert_eq!(
+            category(StatusCode::UNPROCESSABLE_ENTITY),
+            Some(ErrorCategory::Validation)
+        );
+        for status in [
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::BAD_GATEWAY,
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::GATEWAY_TIMEOUT,
+        ] {
+            assert_eq!(category(status), Some(ErrorCategory::UpstreamDown));
+        }
+    }
+
+    #[test]
+    fn it_ignores_responses_without_errors() {
+        assert_eq!(
+            MappedErrors::from_response(&json!({ "data": {} }), &Default::default()),
+            None
+        );
+        assert_eq!(
+            MappedErrors::from_response(&json!({ "data": {}, "errors": [] }), &Default::default()),
+            None
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::introspection::tools::search::IndexingError;
-use apollo_compiler::{Schema, ast::Document, validation::WithErrors};
+use apollo_compiler::{ExecutableDocument, Schema, ast::Document, validation::WithErrors};
 use apollo_federation::error::FederationError;
 use apollo_mcp_registry::platform_api::operation_collections::error::CollectionError;
 use reqwest::header::{InvalidHeaderName, InvalidHeaderValue};
@@ -16,6 +16,9 @@ pub enum OperationError {
     #[error("Internal error: {0}")]
     Internal(String),
 
+    #[error("Invalid GraphQL operation: {0}")]
+    Validation(Box<WithErrors<ExecutableDocument>>),
+
     #[error("{0}Operation is missing its required name: {1}", .source_path.as_ref().map(|s| format!("{s}: ")).unwrap_or_default(), operation)]
     MissingName {
         source_path: Option<String>,
@@ -34,6 +37,18 @@ pub enum OperationError {
         count: usize,
     },
 
+    #[error("{0}Invalid response projection: {1}", .source_path.as_ref().map(|s| format!("{s}: ")).unwrap_or_default(), message)]
+    InvalidProjection {
+        source_path: Option<String>,
+        message: String,
+    },
+
+    #[error("{0}Unknown endpoint '{1}'", .source_path.as_ref().map(|s| format!("{s}: ")).unwrap_or_default(), endpoint)]
+    UnknownEndpoint {
+        source_path: Option<String>,
+        endpoint: String,
+    },
+
     #[error(transparent)]
     File(#[from] std::io::Error),
 
@@ -103,6 +118,15 @@ pub enum ServerError {
 
     #[error("CORS configuration error: {0}")]
     Cors(String),
+
+    #[error("Invalid upstream HTTP client configuration: {0}")]
+    UpstreamClient(String),
+
+    #[error("Failed to create upstream HTTP client: {0}")]
+    HttpClient(#[from] reqwest::Error),
+
+    #[error("Failed to set up the audit log: {0}")]
+    Audit(String),
 }
 
 /// An MCP tool error
@@ -1,13 +1,28 @@
 //! Execute GraphQL operations from an MCP tool
 
+use crate::error_mapping::{
+    ErrorMappingConfig, MappedError, MappedErrors, UNAVAILABLE_CODE, is_unauthenticated,
+    status_code,
+};
 use crate::errors::McpError;
 use crate::generated::telemetry::{TelemetryAttribute, TelemetryMetric};
 use crate::meter;
+use crate::operations::{Connection, PageLimits, ResponseProjection, Truncation};
+use crate::redaction::redactor;
+use crate::subscription::{SubscriptionCompletion, SubscriptionConfig};
+use crate::token_manager::{TokenManager, bearer};
+use crate::upstream::UpstreamClient;
+use futures::{StreamExt as _, TryFutureExt as _};
 use opentelemetry::KeyValue;
-use reqwest::header::{HeaderMap, HeaderValue};
-use rmcp::model::{CallToolResult, Content, ErrorCode};
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use rmcp::model::{
+    CallToolResult, Content, ErrorCode, Meta, ProgressNotificationParam, ProgressToken,
+};
+use rmcp::{Peer, RoleServer};
 use serde_json::{Map, Value};
-use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
 use url::Url;
 
 #[derive(Debug)]
@@ -15,6 +30,11 @@ pub struct Request<'a> {
     pub input: Value,
     pub endpoint: &'a Url,
     pub headers: HeaderMap,
+    pub client: &'a UpstreamClient,
+    pub error_mapping: &'a ErrorMappingConfig,
+
+    /// The manager of the access token in the headers, to refresh it if the endpoint rejects it
+    pub token_manager: Option<&'a Mutex<TokenManager>>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -37,176 +57,594 @@ pub trait Executable {
     /// Get the headers to execute the operation with
     fn headers(&self, default_headers: &HeaderMap<HeaderValue>) -> HeaderMap<HeaderValue>;
 
+    /// Get the projection to apply to the response, if any
+    fn projection(&self) -> Option<&ResponseProjection> {
+        None
+    }
+
+    /// Get the Relay connection returned by the operation, if the server can page through it
+    fn connection(&self) -> Option<&Connection> {
+        None
+    }
+
+    /// Whether the operation can be safely retried after a transient failure
+    fn idempotent(&self) -> bool {
+        false
+    }
+
     /// Execute as a GraphQL operation using the endpoint and headers
     async fn execute(&self, request: Request<'_>) -> Result<CallToolResult, McpError> {
-        let meter = &meter::METER;
         let start = std::time::Instant::now();
-        let mut op_id: Option<String> = None;
-        let client_metadata = serde_json::json!({
-            "name": "mcp",
-            "version": std::env!("CARGO_PKG_VERSION")
-        });
-
-        let mut request_body = Map::from_iter([(
-            String::from("variables"),
-            self.variables(request.input.clone())?,
-        )]);
+        let pagination = match self.connection() {
+            Some(connection) => {
+                PageLimits::from_input(&request.input)?.map(|limits| (connection, limits))
+            }
+            None => None,
+        };
+        let persisted_query_id = self.persisted_query_id();
+        let mut persisted_query = persisted_query_id.is_some();
+        let (mut body, mut op_id) = request_body(self, request.input.clone(), persisted_query_id)?;
+        let mut headers = self.headers(&request.headers);
 
-        if let Some(id) = self.persisted_query_id() {
-            request_body.insert(
-                String::from("extensions"),
-                serde_json::json!({
-                    "persistedQuery": {
-                        "version": 1,
-                        "sha256Hash": id,
-                    },
-                    "clientLibrary": client_metadata,
-                }),
+        let mut json = send_authenticated(&request, &mut headers, &body, self.idempotent()).await?;
+        if persisted_query && persisted_query_not_found(&json) {
+            debug!(
+                "Persisted query for {} not found, sending the full query",
+                op_id.as_deref().unwrap_or_default()
             );
-            op_id = Some(id.to_string());
-        } else {
-            let OperationDetails {
-                query,
-                operation_name,
-            } = self.operation(request.input)?;
-
-            request_body.insert(String::from("query"), Value::String(query));
-            request_body.insert(
-                String::from("extensions"),
+            (body, op_id) = request_body(self, request.input.clone(), None)?;
+            persisted_query = false;
+            json = send_authenticated(&request, &mut headers, &body, self.idempotent()).await?;
+        }
+        let mut meta = Map::new();
+
+        if let Some((connection, limits)) = pagination {
+            let mut pages = 1;
+            while limits.allows(pages, connection.item_count(&json))
+                && json.get("errors").is_none_or(Value::is_null)
+                && let Some(cursor) = connection.next_cursor(&json)
+            {
+                match body.get_mut("variables") {
+                    Some(Value::Object(variables)) => {
+                        variables.insert(connection.cursor_variable().to_string(), cursor);
+                    }
+                    _ => {
+                        body.insert(
+                            String::from("variables"),
+                            Value::Object(Map::from_iter([(
+                                connection.cursor_variable().to_string(),
+                                cursor,
+                            )])),
+                        );
+                    }
+                }
+
+                let page = match send_authenticated(
+                    &request,
+                    &mut headers,
+                    &body,
+                    self.idempotent(),
+                )
+                .await
+                {
+                    Ok(page) => page,
+                    Err(e) => {
+                        warn!("Stopped paginating after {pages} pages: {}", e.message);
+                        break;
+                    }
+                };
+                pages += 1;
+                if connection.merge(&mut json, page) == 0 {
+                    break;
+                }
+            }
+            if let Some(max_items) = limits.max_items {
+                connection.truncate(&mut json, max_items);
+            }
+            meta.insert(
+                String::from("pagination"),
                 serde_json::json!({
-                    "clientLibrary": client_metadata,
+                    "pages": pages,
+                    "items": connection.item_count(&json),
                 }),
             );
+        }
 
-            if let Some(op_name) = operation_name {
-                op_id = Some(op_name.clone());
-                request_body.insert(String::from("operationName"), Value::String(op_name));
-            }
+        let errors = MappedErrors::from_response(&json, request.error_mapping);
+        let truncations = self
+            .projection()
+            .map(|projection| projection.apply(&mut json))
+            .unwrap_or_default();
+        if !truncations.is_empty() {
+            meta.insert(String::from("projection"), Truncation::meta(&truncations));
         }
 
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(
-                std::env::var("REQWEST_TIMEOUT")
-                    .ok()
-                    .and_then(|s| s.parse().ok())
-                    .unwrap_or(30),
-            ))
-            .connect_timeout(Duration::from_secs(
-                std::env::var("REQWEST_CONNECT_TIMEOUT")
-                    .ok()
-                    .and_then(|s| s.parse().ok())
-                    .unwrap_or(10),
-            ))
-            .user_agent(
-                std::env::var("REQWEST_USER_AGENT").unwrap_or_else(|_| "curl/8.4.0".to_string()),
-            )
-            .danger_accept_invalid_certs(
-                std::env::var("REQWEST_SSL_VERIFY")
-                    .ok()
-                    .map(|s| s == "false")
-                    .unwrap_or(false),
-            )
-            .danger_accept_invalid_hostnames(
-                std::env::var("REQWEST_SSL_VERIFY_HOSTNAME")
-                    .ok()
-                    .map(|s| s == "false")
-                    .unwrap_or(false),
-            )
-            .build()
-            .map_err(|e| {
-                McpError::new(
-                    ErrorCode::INTERNAL_ERROR,
-                    format!("Failed to create HTTP client: {e}"),
-                    None,
-                )
-            })?;
-
-        let response = client
-            .post(request.endpoint.as_str())
-            .headers(self.headers(&request.headers))
-            .header("Content-Type", "application/json")
-            .body(Value::Object(request_body).to_string())
-            .send()
-            .await
-            .map_err(|reqwest_error| {
-                McpError::new(
-                    ErrorCode::INTERNAL_ERROR,
-                    format!("Failed to send GraphQL request: {reqwest_error}"),
-                    None,
-                )
-            })?;
-
-        let status = response.status();
-        let response_text = response.text().await.map_err(|reqwest_error| {
-            McpError::new(
-                ErrorCode::INTERNAL_ERROR,
-                format!("Failed to read response text: {reqwest_error}"),
-                None,
-            )
-        })?;
-
-        let json: Value = serde_json::from_str(&response_text).map_err(|reqwest_error| {
-            McpError::new(
-                ErrorCode::INTERNAL_ERROR,
-                format!(
-                    "Failed to parse JSON response (status: {}, body: {}): {reqwest_error}",
-                    status, response_text
-                ),
-                None,
-            )
-        })?;
-
-        let result = Ok(json).map(|json| CallToolResult {
-            content: vec![Content::json(&json).unwrap_or(Content::text(json.to_string()))],
-            is_error: Some(
-                json.get("errors")
-                    .filter(|value| !matches!(value, Value::Null))
-                    .is_some()
-                    && json
-                        .get("data")
-                        .filter(|value| !matches!(value, Value::Null))
-                        .is_none(),
-            ),
-            meta: None,
+        let is_error = errors
+            .as_ref()
+            .is_some_and(|errors| errors.apply(&mut json, request.error_mapping.partial_data));
+        let mut content = vec![Content::json(&json).unwrap_or(Content::text(json.to_string()))];
+        if let Some(errors) = errors {
+            content.push(errors.content());
+            meta.insert(String::from("errors"), errors.meta());
+        }
+
+        let result = Ok(CallToolResult {
+            content,
+            is_error: Some(is_error),
+            meta: (!meta.is_empty()).then(|| Meta(meta)),
             structured_content: Some(json),
         });
 
-        // Record response metrics
-        let attributes = vec![
-            KeyValue::new(
-                TelemetryAttribute::Success.to_key(),
-                result.as_ref().is_ok_and(|r| r.is_error != Some(true)),
-            ),
-            KeyValue::new(
-                TelemetryAttribute::OperationId.to_key(),
-                op_id.unwrap_or("".to_string()),
+        record_operation_metrics(
+            start,
+            result.as_ref().is_ok_and(|r| r.is_error != Some(true)),
+            op_id,
+            persisted_query,
+        );
+
+        result
+    }
+}
+
+/// Progress reporting for a tool call, as requested by the client
+pub struct Progress {
+    pub peer: Peer<RoleServer>,
+    pub token: ProgressToken,
+}
+
+/// Able to be executed as a GraphQL subscription, streaming events back as they arrive
+pub trait StreamingExecutable: Executable {
+    /// Subscribe using the endpoint and headers, collecting events until the configured
+    /// event count or timeout is reached, or the tool call is cancelled.
+    ///
+    /// Each event is sent to the client as a progress notification when a progress token
+    /// was supplied with the tool call.
+    async fn execute_stream(
+        &self,
+        request: Request<'_>,
+        config: &SubscriptionConfig,
+        progress: Option<Progress>,
+        cancellation_token: CancellationToken,
+    ) -> Result<CallToolResult, McpError> {
+        let start = std::time::Instant::now();
+        let mut headers = self.headers(&request.headers);
+        let persisted_query_id = self.persisted_query_id();
+        let mut persisted_query = persisted_query_id.is_some();
+        let (mut body, mut op_id) = request_body(self, request.input.clone(), persisted_query_id)?;
+
+        // Opening the subscription is part of the event stream, so an endpoint that never
+        // accepts it is subject to the timeout and cancellation like one that never sends events
+        let timeout = tokio::time::sleep(config.timeout);
+        tokio::pin!(timeout);
+        let subscribe = |headers: HeaderMap, body: &Map<String, Value>| {
+            config
+                .subscribe(
+                    request.client,
+                    request.endpoint,
+                    headers,
+                    Value::Object(body.clone()),
+                )
+                .try_flatten_stream()
+                .boxed()
+        };
+        let mut events = subscribe(headers.clone(), &body);
+
+        let mut collected = Vec::new();
+        let mut truncations = Vec::new();
+        let mut errors = Vec::new();
+        let mut partial = false;
+        let mut is_error = false;
+        let mut failure = None;
+        let mut reauthenticated = false;
+        let completion = loop {
+            // The timeout is polled first, since an event stream bounded by the same timeout
+            // fails as it elapses
+            let event = tokio::select! {
+                biased;
+                _ = &mut timeout => break SubscriptionCompletion::Timeout,
+                _ = cancellation_token.cancelled() => break SubscriptionCompletion::Cancelled,
+                event = events.next() => event,
+            };
+
+            // An endpoint that rejects the access token does so when opening the subscription,
+            // or in its first event, so the subscription is reopened once with a refreshed token
+            let event = match (event, request.token_manager) {
+                (Some(Ok(event)), Some(token_manager))
+                    if !reauthenticated && collected.is_empty() && is_unauthenticated(&event) =>
+                {
+                    reauthenticated = true;
+                    match refreshed_authorization(token_manager, &headers).await {
+                        Some(header) => {
+                            debug!("Resubscribing with the refreshed access token");
+                            headers.insert(AUTHORIZATION, header);
+                            events = subscribe(headers.clone(), &body);
+                            continue;
+                        }
+                        None => Some(Ok(event)),
+                    }
+                }
+                (event, _) => event,
+            };
+            match event {
+                // An endpoint that doesn't know the persisted query ID rejects it in the first event
+                Some(Ok(event))
+                    if persisted_query
+                        && collected.is_empty()
+                        && persisted_query_not_found(&event) =>
+                {
+                    debug!(
+                        "Persisted query for {} not found, subscribing with the full query",
+                        op_id.as_deref().unwrap_or_default()
+                    );
+                    (body, op_id) = request_body(self, request.input.clone(), None)?;
+                    persisted_query = false;
+                    events = subscribe(headers.clone(), &body);
+                }
+                Some(Ok(mut event)) => {
+                    let index = collected.len();
+                    let event_errors = MappedErrors::from_response(&event, request.error_mapping);
+                    if let Some(projection) = self.projection() {
+                        truncations.extend(projection.apply(&mut event).into_iter().map(
+                            |truncation| Truncation {
+                                path: format!("events[{index}].{}", truncation.path),
+                                ..truncation
+                            },
+                        ));
+                    }
+                    if let Some(event_errors) = event_errors {
+                        is_error |=
+                            event_errors.apply(&mut event, request.error_mapping.partial_data);
+                        partial |= event_errors.partial;
+                        errors.extend(event_errors.errors.into_iter().map(|error| MappedError {
+                            path: Some(match error.path {
+                                Some(path) => format!("events[{index}].{path}"),
+                                None => format!("events[{index}]"),
+                            }),
+                            ..error
+                        }));
+                    }
+                    if let Some(Progress { peer, token }) = progress.as_ref()
+                        && let Err(e) = peer
+                            .notify_progress(ProgressNotificationParam {
+                                progress_token: token.clone(),
+                                progress: (collected.len() + 1) as f64,
+                                total: Some(config.max_events as f64),
+                                message: Some(event.to_string()),
+                            })
+                            .await
+                    {
+                        warn!("Failed to send subscription event to client: {e}");
+                    }
+                    collected.push(event);
+                    if collected.len() >= config.max_events {
+                        break SubscriptionCompletion::MaxEvents;
+                    }
+                }
+                Some(Err(e)) if collected.is_empty() => {
+                    record_operation_metrics(start, false, op_id, persisted_query);
+                    return Err(e);
+                }
+                Some(Err(e)) => {
+                    warn!(
+                        "Subscription failed after {} events: {}",
+                        collected.len(),
+                        e.message
+                    );
+                    failure = Some(e.message);
+                    break SubscriptionCompletion::Error;
+                }
+                None => break SubscriptionCompletion::Complete,
+            }
+        };
+
+        let mut subscription = Map::from_iter([
+            (String::from("events"), Value::from(collected.len())),
+            (
+                String::from("completion"),
+                serde_json::to_value(completion).unwrap_or_default(),
             ),
-            KeyValue::new(
-                TelemetryAttribute::OperationSource.to_key(),
-                match self.persisted_query_id() {
-                    Some(_) => "persisted_query",
-                    None => "operation",
+        ]);
+        if let Some(failure) = failure {
+            subscription.insert(String::from("error"), Value::from(failure.into_owned()));
+        }
+        let mut meta =
+            Map::from_iter([(String::from("subscription"), Value::Object(subscription))]);
+        if !truncations.is_empty() {
+            meta.insert(String::from("projection"), Truncation::meta(&truncations));
+        }
+
+        // The events collected before a failure are still a result, so the failure is only
+        // flagged in the metadata, while errors in the events are mapped like those of a query
+        let json = serde_json::json!({ "events": collected });
+        let mut content = vec![Content::json(&json).unwrap_or(Content::text(json.to_string()))];
+        if !errors.is_empty() {
+            let errors = MappedErrors { errors, partial };
+            content.push(errors.content());
+            meta.insert(String::from("errors"), errors.meta());
+        }
+        let result = CallToolResult {
+            content,
+            is_error: Some(is_error),
+            meta: Some(Meta(meta)),
+            structured_content: Some(json),
+        };
+
+        record_operation_metrics(
+            start,
+            completion != SubscriptionCompletion::Error && !is_error,
+            op_id,
+            persisted_query,
+        );
+        Ok(result)
+    }
+}
+
+/// Build the GraphQL request body for an executable, along with the ID used to identify
+/// the operation in telemetry. Only the persisted query ID is sent when one is given.
+pub(crate) fn request_body<E: Executable + ?Sized>(
+    executable: &E,
+    input: Value,
+    persisted_query_id: Option<String>,
+) -> Result<(Map<String, Value>, Option<String>), McpError> {
+    let mut op_id: Option<String> = None;
+    let client_metadata = serde_json::json!({
+        "name": "mcp",
+        "version": std::env!("CARGO_PKG_VERSION")
+    });
+
+    let mut request_body = Map::from_iter([(
+        String::from("variables"),
+        executable.variables(input.clone())?,
+    )]);
+
+    if let Some(id) = persisted_query_id {
+        request_body.insert(
+            String::from("extensions"),
+            serde_json::json!({
+                "persistedQuery": {
+                    "version": 1,
+                    "sha256Hash": id,
                 },
-            ),
-        ];
-        meter
-            .f64_histogram(TelemetryMetric::OperationDuration.as_str())
-            .build()
-            .record(start.elapsed().as_millis() as f64, &attributes);
-        meter
-            .u64_counter(TelemetryMetric::OperationCount.as_str())
-            .build()
-            .add(1, &attributes);
+                "clientLibrary": client_metadata,
+            }),
+        );
+        op_id = Some(id.to_string());
+    } else {
+        let OperationDetails {
+            query,
+            operation_name,
+        } = executable.operation(input)?;
 
-        result
+        request_body.insert(String::from("query"), Value::String(query));
+        request_body.insert(
+            String::from("extensions"),
+            serde_json::json!({
+                "clientLibrary": client_metadata,
+            }),
+        );
+
+        if let Some(op_name) = operation_name {
+            op_id = Some(op_name.clone());
+            request_body.insert(String::from("operationName"), Value::String(op_name));
+        }
+    }
+
+    Ok((request_body, op_id))
+}
+
+/// Whether the endpoint rejected a request because it doesn't know its persisted query ID
+fn persisted_query_not_found(response: &Value) -> bool {
+    response
+        .get("errors")
+        .and_then(Value::as_array)
+        .is_some_and(|errors| {
+            errors.iter().any(|error| {
+                error.get("message").and_then(Value::as_str) == Some("PersistedQueryNotFound")
+                    || error
+                        .get("extensions")
+                        .and_then(|extensions| extensions.get("code"))
+                        .and_then(Value::as_str)
+                        == Some("PERSISTED_QUERY_NOT_FOUND")
+            })
+        })
+}
+
+/// Send a GraphQL request with the headers of a tool call. If the endpoint rejects the managed
+/// access token, it's refreshed and the request replayed once with the new token, which is also
+/// kept in the headers for the rest of the tool call.
+async fn send_authenticated(
+    request: &Request<'_>,
+    headers: &mut HeaderMap,
+    request_body: &Map<String, Value>,
+    idempotent: bool,
+) -> Result<Value, McpError> {
+    let json = send(
+        request.client,
+        request.endpoint,
+        headers,
+        request_body,
+        idempotent,
+    )
+    .await?;
+    let Some(token_manager) = request.token_manager.filter(|_| is_unauthenticated(&json)) else {
+        return Ok(json);
+    };
+    let Some(header) = refreshed_authorization(token_manager, headers).await else {
+        return Ok(json);
+    };
+    headers.insert(AUTHORIZATION, header);
+
+    debug!("Replaying the request with the refreshed access token");
+    send(
+        request.client,
+        request.endpoint,
+        headers,
+        request_body,
+        idempotent,
+    )
+    .await
+}
+
+/// Refresh the access token in the headers after the endpoint rejected it, returning the
+/// authorization header with the refreshed token
+async fn refreshed_authorization(
+    token_manager: &Mutex<TokenManager>,
+    headers: &HeaderMap,
+) -> Option<HeaderValue> {
+    let refreshed = token_manager
+        .lock()
+        .await
+        .refresh_rejected_token(headers.get(AUTHORIZATION))
+        .await;
+    match refreshed.map(|token| HeaderValue::from_str(&bearer(&token))) {
+        Ok(Ok(header)) => Some(header),
+        Ok(Err(e)) => {
+            warn!("Failed to use the refreshed access token: {e}");
+            None
+        }
+        Err(e) => {
+            warn!("Failed to refresh the rejected access token: {}", e.message);
+            None
+        }
+    }
+}
+
+/// Send a GraphQL request, returning the parsed JSON response.
+///
+/// Failing to reach the endpoint and HTTP error statuses are returned as GraphQL errors, so they
+/// are mapped into the tool result like the errors of the endpoint. Errors in a response with an
+/// HTTP error status get a code derived from the status if they don't have one.
+async fn send(
+    client: &UpstreamClient,
+    endpoint: &Url,
+    headers: &HeaderMap,
+    request_body: &Map<String, Value>,
+    idempotent: bool,
+) -> Result<Value, McpError> {
+    let response = match client
+        .post(
+            endpoint,
+            headers,
+            Value::Object(request_body.clone()).to_string(),
+            idempotent,
+        )
+        .await
+    {
+        Ok(response) => response,
+        Err(upstream_error) => {
+            return Ok(error_response(
+                redactor()
+                    .redact_str(&upstream_error.to_string())
+                    .into_owned(),
+                UNAVAILABLE_CODE,
+            ));
+        }
+    };
+
+    let status = response.status();
+    let response_text = response.text().await.map_err(|reqwest_error| {
+        McpError::new(
+            ErrorCode::INTERNAL_ERROR,
+            format!("Failed to read response text: {reqwest_error}"),
+            None,
+        )
+    })?;
+
+    match serde_json::from_str::<Value>(&response_text) {
+        Ok(mut json) if json.get("errors").is_some_and(Value::is_array) => {
+            if let Some(code) = status_code(status) {
+                add_error_code(&mut json, code);
+            }
+            Ok(json)
+        }
+        Ok(json) if status.is_success() => Ok(json),
+        Err(reqwest_error) if status.is_success() => Err(McpError::new(
+            ErrorCode::INTERNAL_ERROR,
+            format!("Failed to parse JSON response (status: {status}): {reqwest_error}"),
+            None,
+        )),
+        _ => {
+            debug!(
+                "Unexpected response from {endpoint} (status: {status}): {}",
+                redactor().redact_str(&response_text)
+            );
+            Ok(error_response(
+                format!("The GraphQL endpoint responded with HTTP status {status}"),
+                status_code(status).unwrap_or(UNAVAILABLE_CODE),
+            ))
+        }
+    }
+}
+
+/// A GraphQL response with a single error
+fn error_response(message: String, code: &str) -> Value {
+    serde_json::json!({
+        "data": null,
+        "errors": [{ "message": message, "extensions": { "code": code } }],
+    })
+}
+
+/// Give a code to the errors of a response that don't have one
+fn add_error_code(response: &mut Value, code: &str) {
+    let Some(Value::Array(errors)) = response.get_mut("errors") else {
+        return;
+    };
+    for error in errors.iter_mut().filter_map(Value::as_object_mut) {
+        let extensions = error
+            .entry("extensions")
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(extensions) = extensions {
+            extensions
+                .entry("code")
+                .or_insert_with(|| Value::String(code.to_string()));
+        }
     }
 }
 
+/// Record the duration and count of an executed operation
+fn record_operation_metrics(
+    start: std::time::Instant,
+    success: bool,
+    op_id: Option<String>,
+    persisted_query: bool,
+) {
+    let meter = &meter::METER;
+    let attributes = vec![
+        KeyValue::new(TelemetryAttribute::Success.to_key(), success),
+        KeyValue::new(
+            TelemetryAttribute::OperationId.to_key(),
+            op_id.unwrap_or("".to_string()),
+        ),
+        KeyValue::new(
+            TelemetryAttribute::OperationSource.to_key(),
+            if persisted_query {
+                "persisted_query"
+            } else {
+                "operation"
+            },
+        ),
+    ];
+    meter
+        .f64_histogram(TelemetryMetric::OperationDuration.as_str())
+        .build()
+        .record(start.elapsed().as_millis() as f64, &attributes);
+    meter
+        .u64_counter(TelemetryMetric::OperationCount.as_str())
+        .build()
+        .add(1, &attributes);
+}
+
 #[cfg(test)]
 mod test {
+    use crate::error_mapping::{ErrorMappingConfig, PartialData};
     use crate::errors::McpError;
     use crate::generated::telemetry::TelemetryMetric;
-    use crate::graphql::{Executable, OperationDetails, Request};
+    use crate::graphql::{Executable, OperationDetails, Request, StreamingExecutable};
+    use crate::operations::ResponseProjection;
+    use crate::subscription::{SubscriptionConfig, SubscriptionProtocol};
+    use crate::token_manager::TokenManager;
+    use crate::token_provider::{DoControlConfig, TokenProviderConfig};
+    use crate::upstream::UpstreamClient;
     use http::{HeaderMap, HeaderValue};
     use opentelemetry::global;
     use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData};
@@ -214,6 +652,9 @@ mod test {
         InMemoryMetricExporter, MeterProviderBuilder, PeriodicReader,
     };
     use serde_json::{Map, Value, json};
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+    use tokio_util::sync::CancellationToken;
     use url::Url;
 
     struct TestExecutableWithoutPersistedQueryId;
@@ -277,6 +718,9 @@ mod test {
             input: json!({}),
             endpoint: &url,
             headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
         };
         let expected_request_body = json!({
             "variables": { "arg1": "foobar" },
@@ -320,6 +764,9 @@ mod test {
             input: json!({}),
             endpoint: &url,
             headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
         };
         let expected_request_body = json!({
             "variables": "mock_variables",
@@ -357,39 +804,295 @@ mod test {
     }
 
     #[tokio::test]
-    async fn results_in_mcp_error_when_gql_server_cannot_be_reached() {
+    async fn falls_back_to_full_query_when_persisted_query_not_found() {
+        // given
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
+        };
+
+        let persisted_query_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "extensions": { "persistedQuery": { "version": 1 } },
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({ "errors": [{
+                    "message": "PersistedQueryNotFound",
+                    "extensions": { "code": "PERSISTED_QUERY_NOT_FOUND" },
+                }] })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let full_query_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "query": "query MockOp { mockOp { id } }",
+                "operationName": "mock_operation",
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "data": { "mockOp": { "id": 1 } } }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        // when
+        let test_executable = TestExecutableWithPersistedQueryId {};
+        let result = test_executable.execute(mock_request).await.unwrap();
+
+        // then
+        persisted_query_mock.assert_async().await;
+        full_query_mock.assert_async().await;
+        assert_eq!(
+            result.structured_content,
+            Some(json!({ "data": { "mockOp": { "id": 1 } } }))
+        );
+        assert!(!result.is_error.unwrap());
+    }
+
+    #[tokio::test]
+    async fn results_in_upstream_down_error_when_gql_server_cannot_be_reached() {
         // given
         let url = Url::parse("http://localhost/no-server").unwrap();
         let mock_request = Request {
             input: json!({}),
             endpoint: &url,
             headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
         };
 
         // when
         let test_executable = TestExecutableWithPersistedQueryId {};
-        let result = test_executable.execute(mock_request).await;
+        let result = test_executable.execute(mock_request).await.unwrap();
 
         // then
-        match result {
-            Err(e) => {
-                let error_msg = e.message.to_string();
-                // On Windows, error messages might be formatted slightly differently
-                // Check for either the expected message or common network error patterns
-                assert!(
-                    error_msg.starts_with("Failed to send GraphQL request")
-                        || error_msg.contains("Failed to send GraphQL")
-                        || error_msg.contains("connection")
-                        || error_msg.contains("Connection")
-                        || error_msg.contains("network"),
-                    "Expected network error, got: {}",
-                    error_msg
-                );
-            }
-            _ => {
-                panic!("Expected MCP error");
-            }
+        assert_eq!(result.is_error, Some(true));
+        let errors = &result.meta.unwrap().0["errors"];
+        assert_eq!(errors[0]["category"], "upstream-down");
+        assert_eq!(errors[0]["code"], "SERVICE_UNAVAILABLE");
+        let error_msg = errors[0]["message"].as_str().unwrap();
+        // On Windows, error messages might be formatted slightly differently
+        // Check for either the expected message or common network error patterns
+        assert!(
+            error_msg.starts_with("Failed to send GraphQL request")
+                || error_msg.contains("Failed to send GraphQL")
+                || error_msg.contains("connection")
+                || error_msg.contains("Connection")
+                || error_msg.contains("network"),
+            "Expected network error, got: {}",
+            error_msg
+        );
+    }
+
+    #[tokio::test]
+    async fn maps_http_error_statuses_into_call_tool_result() {
+        // given
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        server
+            .mock("POST", "/")
+            .with_status(503)
+            .with_header("content-type", "text/html")
+            .with_body("<html>Service Unavailable</html>")
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/")
+            .with_status(401)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "errors": [{ "message": "Token expired" }] }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+        let execute = || async {
+            TestExecutableWithoutPersistedQueryId
+                .execute(Request {
+                    input: json!({}),
+                    endpoint: &url,
+                    headers: HeaderMap::new(),
+                    client: &UpstreamClient::default(),
+                    error_mapping: &ErrorMappingConfig::default(),
+                    token_manager: None,
+                })
+                .await
+                .unwrap()
+        };
+
+        // when
+        let unavailable = execute().await;
+        let unauthenticated = execute().await;
+
+        // then
+        assert_eq!(unavailable.is_error, Some(true));
+        assert_eq!(
+            unavailable.meta.unwrap().0["errors"],
+            json!([{
+                "category": "upstream-down",
+                "code": "SERVICE_UNAVAILABLE",
+                "message": "The GraphQL endpoint responded with HTTP status 503 Service Unavailable",
+                "remediation": "The GraphQL endpoint is unavailable. The arguments aren't the problem; try again later.",
+            }])
+        );
+        assert_eq!(unauthenticated.is_error, Some(true));
+        assert_eq!(
+            unauthenticated.structured_content.unwrap()["errors"][0]["extensions"]["code"],
+            "UNAUTHENTICATED"
+        );
+        assert_eq!(
+            unauthenticated.meta.unwrap().0["errors"][0]["category"],
+            "auth"
+        );
+    }
+
+    #[tokio::test]
+    async fn replays_request_once_with_refreshed_token_when_unauthenticated() {
+        // given
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        server
+            .mock("POST", "/")
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_status(401)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "errors": [{ "message": "Token expired" }] }).to_string())
+            .expect(2)
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/")
+            .match_header("authorization", "Bearer new_token")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "data": { "mockOp": { "id": 1 } } }).to_string())
+            .expect(2)
+            .create_async()
+            .await;
+        let refresh = server
+            .mock("POST", "/refresh")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "accessToken": "new_token", "expiresIn": 300 }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+        let token_manager = Mutex::new(TokenManager::new(
+            TokenProviderConfig::DoControl(DoControlConfig {
+                refresh_url: format!("{}/refresh", server.url()).parse().unwrap(),
+                refresh_token: String::from("refresh_token"),
+            })
+            .provider(None)
+            .unwrap(),
+        ));
+        let client = UpstreamClient::default();
+        let error_mapping = ErrorMappingConfig::default();
+        let execute = || {
+            TestExecutableWithoutPersistedQueryId.execute(Request {
+                input: json!({}),
+                endpoint: &url,
+                headers: HeaderMap::new(),
+                client: &client,
+                error_mapping: &error_mapping,
+                token_manager: Some(&token_manager),
+            })
+        };
+
+        // when
+        let (first, second) = tokio::join!(execute(), execute());
+
+        // then
+        for result in [first.unwrap(), second.unwrap()] {
+            assert_eq!(result.is_error, Some(false));
+            assert_eq!(
+                result.structured_content,
+                Some(json!({ "data": { "mockOp": { "id": 1 } } }))
+            );
         }
+        refresh.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn partial_data_is_handled_as_configured() {
+        // given
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "data": { "user": { "name": "Ada", "email": null } },
+                    "errors": [{
+                        "message": "Not allowed",
+                        "path": ["user", "email"],
+                        "extensions": { "code": "FORBIDDEN" },
+                    }],
+                })
+                .to_string(),
+            )
+            .expect(3)
+            .create_async()
+            .await;
+        let execute = |partial_data| {
+            let url = url.clone();
+            async move {
+                TestExecutableWithoutPersistedQueryId
+                    .execute(Request {
+                        input: json!({}),
+                        endpoint: &url,
+                        headers: HeaderMap::new(),
+                        client: &UpstreamClient::default(),
+                        error_mapping: &ErrorMappingConfig {
+                            partial_data,
+                            ..Default::default()
+                        },
+                        token_manager: None,
+                    })
+                    .await
+                    .unwrap()
+            }
+        };
+
+        // when
+        let allowed = execute(PartialData::Allow).await;
+        let flagged = execute(PartialData::Error).await;
+        let rejected = execute(PartialData::Reject).await;
+
+        // then
+        assert_eq!(allowed.is_error, Some(false));
+        assert_eq!(
+            allowed.structured_content.unwrap()["data"]["user"]["name"],
+            "Ada"
+        );
+        assert_eq!(allowed.content.len(), 2);
+        assert!(
+            allowed.content[1]
+                .as_text()
+                .unwrap()
+                .text
+                .contains("[auth] at user.email: Not allowed (FORBIDDEN)")
+        );
+        assert_eq!(flagged.is_error, Some(true));
+        assert_eq!(
+            flagged.structured_content.unwrap()["data"]["user"]["name"],
+            "Ada"
+        );
+        assert_eq!(rejected.is_error, Some(true));
+        assert_eq!(rejected.structured_content.unwrap()["data"], Value::Null);
     }
 
     #[tokio::test]
@@ -401,6 +1104,9 @@ mod test {
             input: json!({}),
             endpoint: &url,
             headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
         };
 
         server
@@ -440,6 +1146,9 @@ mod test {
             input: json!({}),
             endpoint: &url,
             headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
         };
 
         server
@@ -460,6 +1169,679 @@ mod test {
         assert!(result.is_error.unwrap());
     }
 
+    struct TestExecutableWithProjection(ResponseProjection);
+
+    impl Executable for TestExecutableWithProjection {
+        fn persisted_query_id(&self) -> Option<String> {
+            None
+        }
+
+        fn operation(&self, _input: Value) -> Result<OperationDetails, McpError> {
+            Ok(OperationDetails {
+                query: "query MockOp { mockOp { id name } }".to_string(),
+                operation_name: Some("mock_operation".to_string()),
+            })
+        }
+
+        fn variables(&self, _input: Value) -> Result<Value, McpError> {
+            Ok(Value::Null)
+        }
+
+        fn headers(&self, _default_headers: &HeaderMap<HeaderValue>) -> HeaderMap<HeaderValue> {
+            HeaderMap::new()
+        }
+
+        fn projection(&self) -> Option<&ResponseProjection> {
+            Some(&self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn projection_is_applied_to_response_and_truncation_noted_in_meta() {
+        // given
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
+        };
+
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({ "data": { "mockOp": [
+                    { "id": 1, "name": "one" },
+                    { "id": 2, "name": "two" },
+                    { "id": 3, "name": "three" },
+                ] } })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        // when
+        let test_executable = TestExecutableWithProjection(ResponseProjection {
+            paths: vec!["mockOp[*].id".parse().unwrap()],
+            max_array_length: Some(2),
+        });
+        let result = test_executable.execute(mock_request).await.unwrap();
+
+        // then
+        assert_eq!(
+            result.structured_content,
+            Some(json!({ "data": { "mockOp": [{ "id": 1 }, { "id": 2 }] } }))
+        );
+        assert_eq!(
+            result.meta.unwrap().get("projection"),
+            Some(&json!({ "truncated": [{ "path": "data.mockOp", "length": 3 }] }))
+        );
+    }
+
+    impl StreamingExecutable for TestExecutableWithoutPersistedQueryId {}
+
+    #[tokio::test]
+    async fn collects_subscription_events_up_to_max_events() {
+        // given
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
+        };
+        let config = SubscriptionConfig {
+            protocol: SubscriptionProtocol::GraphqlSse,
+            max_events: 2,
+            ..Default::default()
+        };
+
+        let mock = server
+            .mock("POST", "/")
+            .match_header("accept", "text/event-stream")
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(
+                "event: next\ndata: {\"data\":{\"id\":1}}\n\n\
+                 event: next\ndata: {\"data\":{\"id\":2}}\n\n\
+                 event: next\ndata: {\"data\":{\"id\":3}}\n\n",
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        // when
+        let test_executable = TestExecutableWithoutPersistedQueryId {};
+        let result = test_executable
+            .execute_stream(mock_request, &config, None, CancellationToken::new())
+            .await
+            .unwrap();
+
+        // then
+        mock.assert();
+        assert_eq!(result.is_error, Some(false));
+        assert_eq!(
+            result.structured_content,
+            Some(json!({ "events": [{ "data": { "id": 1 } }, { "data": { "id": 2 } }] }))
+        );
+        assert_eq!(
+            result.meta.unwrap().get("subscription"),
+            Some(&json!({ "events": 2, "completion": "max_events" }))
+        );
+    }
+
+    #[tokio::test]
+    async fn completes_subscription_when_server_completes() {
+        // given
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
+        };
+        let config = SubscriptionConfig {
+            protocol: SubscriptionProtocol::GraphqlSse,
+            ..Default::default()
+        };
+
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body("event: next\ndata: {\"data\":{}}\n\nevent: complete\ndata:\n\n")
+            .create_async()
+            .await;
+
+        // when
+        let test_executable = TestExecutableWithoutPersistedQueryId {};
+        let result = test_executable
+            .execute_stream(mock_request, &config, None, CancellationToken::new())
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(
+            result.meta.unwrap().get("subscription"),
+            Some(&json!({ "events": 1, "completion": "complete" }))
+        );
+    }
+
+    #[tokio::test]
+    async fn times_out_when_subscription_is_never_accepted() {
+        // given
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let server = tokio::spawn(async move {
+            // Accept the connection, but never respond
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            drop(stream);
+        });
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
+        };
+        let config = SubscriptionConfig {
+            protocol: SubscriptionProtocol::GraphqlSse,
+            timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+
+        // when
+        let result = TestExecutableWithoutPersistedQueryId
+            .execute_stream(mock_request, &config, None, CancellationToken::new())
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(result.structured_content, Some(json!({ "events": [] })));
+        assert_eq!(
+            result.meta.unwrap().get("subscription"),
+            Some(&json!({ "events": 0, "completion": "timeout" }))
+        );
+        server.abort();
+    }
+
+    impl StreamingExecutable for TestExecutableWithPersistedQueryId {}
+
+    #[tokio::test]
+    async fn subscribes_with_full_query_when_persisted_query_not_found() {
+        // given
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
+        };
+        let config = SubscriptionConfig {
+            protocol: SubscriptionProtocol::GraphqlSse,
+            ..Default::default()
+        };
+
+        let persisted_query_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "extensions": { "persistedQuery": { "version": 1 } },
+            })))
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(format!(
+                "event: next\ndata: {}\n\nevent: complete\ndata:\n\n",
+                json!({ "errors": [{
+                    "message": "PersistedQueryNotFound",
+                    "extensions": { "code": "PERSISTED_QUERY_NOT_FOUND" },
+                }] })
+            ))
+            .expect(1)
+            .create_async()
+            .await;
+        let full_query_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "query": "query MockOp { mockOp { id } }",
+            })))
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body("event: next\ndata: {\"data\":{\"id\":1}}\n\nevent: complete\ndata:\n\n")
+            .expect(1)
+            .create_async()
+            .await;
+
+        // when
+        let test_executable = TestExecutableWithPersistedQueryId {};
+        let result = test_executable
+            .execute_stream(mock_request, &config, None, CancellationToken::new())
+            .await
+            .unwrap();
+
+        // then
+        persisted_query_mock.assert_async().await;
+        full_query_mock.assert_async().await;
+        assert_eq!(
+            result.structured_content,
+            Some(json!({ "events": [{ "data": { "id": 1 } }] }))
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_collected_events_when_subscription_fails() {
+        // given
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
+        };
+        let config = SubscriptionConfig {
+            protocol: SubscriptionProtocol::GraphqlSse,
+            ..Default::default()
+        };
+
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body("event: next\ndata: {\"data\":{\"id\":1}}\n\nevent: next\ndata: oops\n\n")
+            .create_async()
+            .await;
+
+        // when
+        let test_executable = TestExecutableWithoutPersistedQueryId {};
+        let result = test_executable
+            .execute_stream(mock_request, &config, None, CancellationToken::new())
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(result.is_error, Some(false));
+        assert_eq!(
+            result.structured_content,
+            Some(json!({ "events": [{ "data": { "id": 1 } }] }))
+        );
+        let subscription = result.meta.unwrap().get("subscription").cloned().unwrap();
+        assert_eq!(subscription["events"], json!(1));
+        assert_eq!(subscription["completion"], json!("error"));
+        assert!(
+            subscription["error"]
+                .as_str()
+                .unwrap()
+                .starts_with("Failed to subscribe: invalid event from server")
+        );
+    }
+
+    #[tokio::test]
+    async fn maps_errors_in_subscription_events() {
+        // given
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig {
+                partial_data: PartialData::Reject,
+                ..Default::default()
+            },
+            token_manager: None,
+        };
+        let config = SubscriptionConfig {
+            protocol: SubscriptionProtocol::GraphqlSse,
+            ..Default::default()
+        };
+
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(
+                "event: next\ndata: {\"data\":{\"id\":1},\"errors\":[{\"message\":\"Boom\",\"path\":[\"id\"],\"extensions\":{\"code\":\"SERVICE_UNAVAILABLE\"}}]}\n\n\
+                 event: next\ndata: {\"data\":{\"id\":2}}\n\n\
+                 event: complete\ndata:\n\n",
+            )
+            .create_async()
+            .await;
+
+        // when
+        let test_executable = TestExecutableWithoutPersistedQueryId {};
+        let result = test_executable
+            .execute_stream(mock_request, &config, None, CancellationToken::new())
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(
+            result.structured_content.unwrap()["events"],
+            json!([
+                {
+                    "data": null,
+                    "errors": [{ "message": "Boom", "path": ["id"], "extensions": { "code": "SERVICE_UNAVAILABLE" } }]
+                },
+                { "data": { "id": 2 } }
+            ])
+        );
+        let errors = &result.meta.unwrap().0["errors"];
+        assert_eq!(errors[0]["category"], json!("upstream-down"));
+        assert_eq!(errors[0]["path"], json!("events[0].id"));
+    }
+
+    #[tokio::test]
+    async fn resubscribes_once_with_refreshed_token_when_unauthenticated() {
+        // given
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        let rejected = server
+            .mock("POST", "/")
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_status(401)
+            .expect(1)
+            .create_async()
+            .await;
+        let subscribed = server
+            .mock("POST", "/")
+            .match_header("authorization", "Bearer new_token")
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body("event: next\ndata: {\"data\":{\"id\":1}}\n\nevent: complete\ndata:\n\n")
+            .expect(1)
+            .create_async()
+            .await;
+        let refresh = server
+            .mock("POST", "/refresh")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "accessToken": "new_token", "expiresIn": 300 }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+        let token_manager = Mutex::new(TokenManager::new(
+            TokenProviderConfig::DoControl(DoControlConfig {
+                refresh_url: format!("{}/refresh", server.url()).parse().unwrap(),
+                refresh_token: String::from("refresh_token"),
+            })
+            .provider(None)
+            .unwrap(),
+        ));
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: Some(&token_manager),
+        };
+        let config = SubscriptionConfig {
+            protocol: SubscriptionProtocol::GraphqlSse,
+            ..Default::default()
+        };
+
+        // when
+        let result = TestExecutableWithoutPersistedQueryId
+            .execute_stream(mock_request, &config, None, CancellationToken::new())
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(result.is_error, Some(false));
+        assert_eq!(
+            result.structured_content,
+            Some(json!({ "events": [{ "data": { "id": 1 } }] }))
+        );
+        rejected.assert_async().await;
+        subscribed.assert_async().await;
+        refresh.assert_async().await;
+    }
+
+    /// Serve one graphql-transport-ws connection per script, answering its subscription with
+    /// the script's messages, and return the authorization and subscribe payload of each
+    async fn graphql_ws_server(
+        scripts: Vec<Vec<Value>>,
+    ) -> (Url, tokio::task::JoinHandle<Vec<(Option<String>, Value)>>) {
+        use futures::{SinkExt as _, StreamExt as _};
+        use tokio_tungstenite::tungstenite::Message;
+        use tokio_tungstenite::tungstenite::handshake::server::{
+            Request as HandshakeRequest, Response as HandshakeResponse,
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let server = tokio::spawn(async move {
+            let mut subscriptions = Vec::new();
+            for messages in scripts {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut authorization = None;
+                let mut socket = tokio_tungstenite::accept_hdr_async(
+                    stream,
+                    |request: &HandshakeRequest, mut response: HandshakeResponse| {
+                        authorization = request
+                            .headers()
+                            .get("authorization")
+                            .map(|value| value.to_str().unwrap().to_string());
+                        response.headers_mut().insert(
+                            "sec-websocket-protocol",
+                            HeaderValue::from_static("graphql-transport-ws"),
+                        );
+                        Ok(response)
+                    },
+                )
+                .await
+                .unwrap();
+
+                socket.next().await.unwrap().unwrap();
+                socket
+                    .send(Message::text(r#"{"type":"connection_ack"}"#))
+                    .await
+                    .unwrap();
+                let subscribe: Value =
+                    serde_json::from_str(socket.next().await.unwrap().unwrap().to_text().unwrap())
+                        .unwrap();
+                subscriptions.push((authorization, subscribe["payload"].clone()));
+
+                for message in messages {
+                    socket
+                        .send(Message::text(message.to_string()))
+                        .await
+                        .unwrap();
+                }
+                // Wait for the client to close the connection
+                while let Some(Ok(_)) = socket.next().await {}
+            }
+            subscriptions
+        });
+        (url, server)
+    }
+
+    #[tokio::test]
+    async fn resubscribes_over_graphql_ws_with_refreshed_token_when_unauthenticated() {
+        // given
+        let (url, server) = graphql_ws_server(vec![
+            vec![json!({ "id": "1", "type": "error", "payload": [{
+                "message": "Token expired",
+                "extensions": { "code": "UNAUTHENTICATED" },
+            }] })],
+            vec![
+                json!({ "id": "1", "type": "next", "payload": { "data": { "id": 1 } } }),
+                json!({ "id": "1", "type": "complete" }),
+            ],
+        ])
+        .await;
+        let mut refresh_server = mockito::Server::new_async().await;
+        let refresh = refresh_server
+            .mock("POST", "/refresh")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "accessToken": "new_token", "expiresIn": 300 }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+        let token_manager = Mutex::new(TokenManager::new(
+            TokenProviderConfig::DoControl(DoControlConfig {
+                refresh_url: format!("{}/refresh", refresh_server.url()).parse().unwrap(),
+                refresh_token: String::from("refresh_token"),
+            })
+            .provider(None)
+            .unwrap(),
+        ));
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: Some(&token_manager),
+        };
+        let config = SubscriptionConfig {
+            protocol: SubscriptionProtocol::GraphqlWs,
+            ..Default::default()
+        };
+
+        // when
+        let result = TestExecutableWithoutPersistedQueryId
+            .execute_stream(mock_request, &config, None, CancellationToken::new())
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(result.is_error, Some(false));
+        assert_eq!(
+            result.structured_content,
+            Some(json!({ "events": [{ "data": { "id": 1 } }] }))
+        );
+        let authorizations: Vec<_> = server
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(authorization, _)| authorization)
+            .collect();
+        assert_eq!(
+            authorizations,
+            vec![None, Some(String::from("Bearer new_token"))]
+        );
+        refresh.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn subscribes_over_graphql_ws_with_full_query_when_persisted_query_not_found() {
+        // given
+        let (url, server) = graphql_ws_server(vec![
+            vec![json!({ "id": "1", "type": "error", "payload": [{
+                "message": "PersistedQueryNotFound",
+                "extensions": { "code": "PERSISTED_QUERY_NOT_FOUND" },
+            }] })],
+            vec![
+                json!({ "id": "1", "type": "next", "payload": { "data": { "id": 1 } } }),
+                json!({ "id": "1", "type": "complete" }),
+            ],
+        ])
+        .await;
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
+        };
+        let config = SubscriptionConfig {
+            protocol: SubscriptionProtocol::GraphqlWs,
+            ..Default::default()
+        };
+
+        // when
+        let result = TestExecutableWithPersistedQueryId
+            .execute_stream(mock_request, &config, None, CancellationToken::new())
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(result.is_error, Some(false));
+        assert_eq!(
+            result.structured_content,
+            Some(json!({ "events": [{ "data": { "id": 1 } }] }))
+        );
+        let subscriptions = server.await.unwrap();
+        let (_, persisted_query) = subscriptions.first().unwrap();
+        assert_eq!(
+            persisted_query["extensions"]["persistedQuery"]["version"],
+            json!(1)
+        );
+        assert_eq!(persisted_query.get("query"), None);
+        let (_, full_query) = subscriptions.get(1).unwrap();
+        assert_eq!(full_query["query"], json!("query MockOp { mockOp { id } }"));
+    }
+
+    #[tokio::test]
+    async fn maps_errors_in_graphql_ws_error_messages() {
+        // given
+        let (url, server) = graphql_ws_server(vec![vec![json!({
+            "id": "1",
+            "type": "error",
+            "payload": [{ "message": "Boom", "extensions": { "code": "SERVICE_UNAVAILABLE" } }],
+        })]])
+        .await;
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
+        };
+        let config = SubscriptionConfig {
+            protocol: SubscriptionProtocol::GraphqlWs,
+            ..Default::default()
+        };
+
+        // when
+        let result = TestExecutableWithoutPersistedQueryId
+            .execute_stream(mock_request, &config, None, CancellationToken::new())
+            .await
+            .unwrap();
+
+        // then
+        server.await.unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(
+            result.structured_content,
+            Some(json!({ "events": [{
+                "errors": [{ "message": "Boom", "extensions": { "code": "SERVICE_UNAVAILABLE" } }]
+            }] }))
+        );
+        let meta = result.meta.unwrap();
+        assert_eq!(
+            meta.get("subscription"),
+            Some(&json!({ "events": 1, "completion": "complete" }))
+        );
+        assert_eq!(meta.0["errors"][0]["category"], json!("upstream-down"));
+        assert_eq!(meta.0["errors"][0]["path"], json!("events[0]"));
+    }
+
     #[tokio::test]
     async fn validate_metric_attributes_success_false() {
         // given
@@ -475,6 +1857,9 @@ mod test {
             input: json!({}),
             endpoint: &url,
             headers: HeaderMap::new(),
+            client: &UpstreamClient::default(),
+            error_mapping: &ErrorMappingConfig::default(),
+            token_manager: None,
         };
 
         server
@@ -18,6 +18,8 @@ use serde::{Deserialize, Serialize};
 use tokio::time::Instant;
 use tracing::debug;
 
+use crate::upstream::circuit_breaker::CircuitBreaker;
+
 /// Health status enumeration
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "UPPERCASE")]
@@ -114,6 +116,7 @@ pub struct HealthCheck {
     ready: Arc<AtomicBool>,
     rejected: Arc<AtomicUsize>,
     ticker: Arc<tokio::task::JoinHandle<()>>,
+    circuit_breaker: Option<CircuitBreaker>,
 }
 
 impl HealthCheck {
@@ -158,9 +161,16 @@ impl HealthCheck {
             ready,
             rejected,
             ticker: Arc::new(ticker),
+            circuit_breaker: None,
         }
     }
 
+    /// Report unready while the circuit of an upstream endpoint is open
+    pub fn with_circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
     pub fn record_rejection(&self) {
         self.rejected.fetch_add(1, Ordering::Relaxed);
     }
@@ -176,7 +186,12 @@ impl HealthCheck {
             let query_upper = query.to_ascii_uppercase();
 
             if query_upper.starts_with("READY") {
-                let status = if self.ready.load(Ordering::SeqCst) {
+                let status = if self.ready.load(Ordering::SeqCst)
+                    && !self
+                        .circuit_breaker
+                        .as_ref()
+                        .is_some_and(CircuitBreaker::is_open)
+                {
                     HealthStatus::Up
                 } else {
                     status_code = StatusCode::SERVICE_UNAVAILABLE;
@@ -252,4 +267,32 @@ mod tests {
         assert!(health_check.live.load(Ordering::SeqCst));
         assert!(!health_check.ready.load(Ordering::SeqCst));
     }
+
+    #[tokio::test]
+    async fn test_health_check_reports_open_circuits_as_unready() {
+        use crate::upstream::circuit_breaker::CircuitBreakerConfig;
+
+        let circuit_breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            enabled: true,
+            failure_threshold: 1,
+            ..Default::default()
+        });
+        let health_check = HealthCheck::new(HealthCheckConfig::default())
+            .with_circuit_breaker(circuit_breaker.clone());
+        assert_eq!(
+            health_check.get_health_state(Some("ready")).1,
+            StatusCode::OK
+        );
+
+        circuit_breaker.record(&url::Url::parse("http://localhost:4000").unwrap(), false);
+
+        assert_eq!(
+            health_check.get_health_state(Some("ready")).1,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            health_check.get_health_state(Some("live")).1,
+            StatusCode::OK
+        );
+    }
 }
@@ -1,9 +1,15 @@
+use std::sync::{Arc, RwLock};
+
 use crate::errors::McpError;
 use crate::operations::{MutationMode, operation_defs, operation_name};
+use crate::query_limits::QueryLimitsConfig;
 use crate::{
     graphql::{self, OperationDetails},
     schema_from_type,
 };
+use apollo_compiler::ast::OperationType;
+use apollo_compiler::parser::Parser;
+use apollo_compiler::{Schema, validation::Valid};
 use reqwest::header::{HeaderMap, HeaderValue};
 use rmcp::model::{ErrorCode, Tool};
 use rmcp::schemars::JsonSchema;
@@ -18,6 +24,12 @@ pub const EXECUTE_TOOL_NAME: &str = "execute";
 pub struct Execute {
     pub tool: Tool,
     mutation_mode: MutationMode,
+    limits: Option<Arc<QueryLimitsConfig>>,
+    validate: bool,
+
+    /// The schema to check operations with, kept behind a synchronous lock since operations
+    /// are checked in [`graphql::Executable::operation`]
+    schema: Option<Arc<RwLock<Valid<Schema>>>>,
 }
 
 /// Input for the execute tool.
@@ -40,6 +52,52 @@ impl Execute {
                 "Execute a GraphQL operation. Use the `introspect` tool to get information about the GraphQL schema. Always use the schema to create operations - do not try arbitrary operations. If available, first use the `validate` tool to validate operations. DO NOT try to execute introspection queries.",
                 schema_from_type!(Input),
             ),
+            limits: None,
+            validate: false,
+            schema: None,
+        }
+    }
+
+    /// Reject operations that exceed the limits before they are sent
+    pub fn with_limits(self, config: QueryLimitsConfig, schema: Valid<Schema>) -> Self {
+        if !config.is_enabled() {
+            return self;
+        }
+        Self {
+            limits: Some(Arc::new(config)),
+            schema: Some(Arc::new(RwLock::new(schema))),
+            ..self
+        }
+    }
+
+    /// Reject operations that aren't valid against the schema before they are sent, so that
+    /// only what is in the schema can be executed
+    pub fn with_validation(self, schema: Valid<Schema>) -> Self {
+        Self {
+            validate: true,
+            schema: Some(Arc::new(RwLock::new(schema))),
+            ..self
+        }
+    }
+
+    /// Whether the input runs a mutation
+    pub fn is_mutation(&self, input: &Value) -> bool {
+        serde_json::from_value::<Input>(input.clone())
+            .ok()
+            .and_then(|input| operation_defs(&input.query, true, None).ok().flatten())
+            .is_some_and(|(_, operation_def, _)| {
+                operation_def.operation_type == OperationType::Mutation
+            })
+    }
+
+    /// Update the schema operations are checked with
+    pub fn set_schema(&self, schema: Valid<Schema>) {
+        if let Some(mut current) = self
+            .schema
+            .as_ref()
+            .and_then(|current| current.write().ok())
+        {
+            *current = schema;
         }
     }
 }
@@ -57,6 +115,10 @@ impl graphql::Executable for Execute {
         let (_, operation_def, source_path) =
             operation_defs(&input.query, self.mutation_mode == MutationMode::All, None)
                 .map_err(|e| McpError::new(ErrorCode::INVALID_PARAMS, e.to_string(), None))?
+                // Subscriptions can only be run by their own operation tools
+                .filter(|(_, operation_def, _)| {
+                    operation_def.operation_type != OperationType::Subscription
+                })
                 .ok_or_else(|| {
                     McpError::new(
                         ErrorCode::INVALID_PARAMS,
@@ -65,9 +127,24 @@ impl graphql::Executable for Execute {
                     )
                 })?;
 
+        let operation_name = operation_name(&operation_def, source_path).ok();
+        if let Some(schema) = self.schema.as_ref().and_then(|schema| schema.read().ok()) {
+            if self.validate {
+                Parser::new()
+                    .parse_executable(&schema, input.query.as_str(), "operation.graphql")
+                    .map_err(|e| McpError::new(ErrorCode::INVALID_PARAMS, e.to_string(), None))?
+                    .validate(&schema)
+                    .map_err(|e| McpError::new(ErrorCode::INVALID_PARAMS, e.to_string(), None))?;
+            }
+            if let Some(limits) = &self.limits {
+                let variables = parse_variables(input.variables)?;
+                limits.check(&schema, &input.query, operation_name.as_deref(), &variables)?;
+            }
+        }
+
         Ok(OperationDetails {
             query: input.query,
-            operation_name: operation_name(&operation_def, source_path).ok(),
+            operation_name,
         })
     }
 
@@ -75,19 +152,7 @@ impl graphql::Executable for Execute {
         let input = serde_json::from_value::<Input>(input).map_err(|_| {
             McpError::new(ErrorCode::INVALID_PARAMS, "Invalid input".to_string(), None)
         })?;
-        match input.variables {
-            None => Ok(Value::Null),
-            Some(Value::Null) => Ok(Value::Null),
-            Some(Value::String(s)) => serde_json::from_str(&s).map_err(|_| {
-                McpError::new(ErrorCode::INVALID_PARAMS, "Invalid input".to_string(), None)
-            }),
-            Some(obj) if obj.is_object() => Ok(obj),
-            _ => Err(McpError::new(
-                ErrorCode::INVALID_PARAMS,
-                "Invalid input".to_string(),
-                None,
-            )),
-        }
+        parse_variables(input.variables)
     }
 
     fn headers(&self, default_headers: &HeaderMap<HeaderValue>) -> HeaderMap<HeaderValue> {
@@ -95,12 +160,30 @@ impl graphql::Executable for Execute {
     }
 }
 
+/// Parse the variables of the input, which may be sent as a JSON string
+fn parse_variables(variables: Option<Value>) -> Result<Value, McpError> {
+    match variables {
+        None => Ok(Value::Null),
+        Some(Value::Null) => Ok(Value::Null),
+        Some(Value::String(s)) => serde_json::from_str(&s).map_err(|_| {
+            McpError::new(ErrorCode::INVALID_PARAMS, "Invalid input".to_string(), None)
+        }),
+        Some(obj) if obj.is_object() => Ok(obj),
+        _ => Err(McpError::new(
+            ErrorCode::INVALID_PARAMS,
+            "Invalid input".to_string(),
+            None,
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::errors::McpError;
     use crate::graphql::{Executable, OperationDetails};
     use crate::introspection::tools::execute::Execute;
     use crate::operations::MutationMode;
+    use crate::query_limits::QueryLimitsConfig;
     use rmcp::model::ErrorCode;
     use rmcp::serde_json::{Value, json};
 
@@ -291,4 +374,64 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn execute_query_over_limits() {
+        let schema = apollo_compiler::Schema::parse_and_validate(
+            "type Query { user(id: ID!): User } type User { id: ID friend: User }",
+            "schema.graphql",
+        )
+        .unwrap();
+        let execute = Execute::new(MutationMode::None).with_limits(
+            QueryLimitsConfig {
+                max_depth: Some(2),
+                ..Default::default()
+            },
+            schema,
+        );
+
+        let operation = |query: &str| {
+            Executable::operation(
+                &execute,
+                json!({ "query": query, "variables": { "id": "1" } }),
+            )
+        };
+
+        assert!(operation("query GetUser($id: ID!) { user(id: $id) { id } }").is_ok());
+        assert_eq!(
+            operation("query GetUser($id: ID!) { user(id: $id) { friend { id } } }")
+                .unwrap_err()
+                .data,
+            Some(json!({ "limit": "depth", "value": 3, "max": 2 }))
+        );
+    }
+
+    #[test]
+    fn execute_query_not_in_schema() {
+        let schema = apollo_compiler::Schema::parse_and_validate(
+            "type Query { user(id: ID!): User } type User { id: ID }",
+            "schema.graphql",
+        )
+        .unwrap();
+        let execute = Execute::new(MutationMode::None).with_validation(schema);
+
+        let operation = |query: &str| Executable::operation(&execute, json!({ "query": query }));
+
+        assert!(operation("query GetUser { user(id: \"1\") { id } }").is_ok());
+        assert_eq!(
+            operation("query GetUser { user(id: \"1\") { salary } }")
+                .unwrap_err()
+                .code,
+            ErrorCode::INVALID_PARAMS
+        );
+    }
+
+    #[test]
+    fn execute_query_is_mutation() {
+        let execute = Execute::new(MutationMode::All);
+
+        assert!(execute.is_mutation(&json!({ "query": "mutation CreateUser { id }" })));
+        assert!(!execute.is_mutation(&json!({ "query": "query GetUser { id }" })));
+        assert!(!execute.is_mutation(&json!({ "nonsense": "whatever" })));
+    }
 }
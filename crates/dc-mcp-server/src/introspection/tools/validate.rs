@@ -2,6 +2,7 @@ use crate::errors::McpError;
 use crate::operations::operation_defs;
 use crate::schema_from_type;
 use apollo_compiler::Schema;
+use apollo_compiler::ast::OperationType;
 use apollo_compiler::parser::Parser;
 use apollo_compiler::validation::Valid;
 use rmcp::model::CallToolResult;
@@ -54,6 +55,9 @@ impl Validate {
 
         operation_defs(&input.operation, true, None)
             .map_err(|e| McpError::new(ErrorCode::INVALID_PARAMS, e.to_string(), None))?
+            .filter(|(_, operation_def, _)| {
+                operation_def.operation_type != OperationType::Subscription
+            })
             .ok_or_else(|| {
                 McpError::new(
                     ErrorCode::INVALID_PARAMS,
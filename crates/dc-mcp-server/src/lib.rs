@@ -1,9 +1,12 @@
 #![cfg_attr(coverage_nightly, feature(coverage_attribute))]
 
+pub mod audit;
 pub mod auth;
-pub mod config_manager;
+pub mod confirmation;
+pub mod contract;
 pub mod cors;
 pub mod custom_scalar_map;
+pub mod error_mapping;
 pub mod errors;
 pub mod event;
 mod explorer;
@@ -11,14 +14,24 @@ mod graphql;
 pub mod health;
 mod introspection;
 pub mod json_schema;
+pub mod log_rotation_kind;
 pub(crate) mod meter;
 pub mod operations;
+pub mod query_limits;
+pub mod rate_limit;
+pub mod redaction;
+pub mod response_cache;
 pub mod sanitize;
+pub mod schema_filter;
 pub(crate) mod schema_tree_shake;
 pub mod server;
 pub mod startup;
+pub mod subscription;
 pub mod telemetry_attributes;
 pub mod token_manager;
+pub mod token_provider;
+pub mod token_store;
+pub mod upstream;
 
 /// These values are generated at build time by build.rs using telemetry.toml as input.
 pub mod generated {
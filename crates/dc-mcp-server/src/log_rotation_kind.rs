@@ -1,7 +1,10 @@
+//! Log file rotation periods
+
 use schemars::JsonSchema;
 use serde::Deserialize;
 use tracing_appender::rolling::Rotation;
 
+/// How often a log file is rotated
 #[derive(Debug, Deserialize, JsonSchema, Clone)]
 pub enum LogRotationKind {
     #[serde(alias = "minutely", alias = "MINUTELY")]
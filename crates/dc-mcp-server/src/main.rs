@@ -1,17 +1,24 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use apollo_compiler::Schema;
 use apollo_mcp_registry::platform_api::operation_collections::collection_poller::CollectionSource;
 use apollo_mcp_registry::uplink::persisted_queries::ManifestSource;
 use apollo_mcp_registry::uplink::schema::SchemaSource;
-use clap::Parser;
 use clap::builder::Styles;
 use clap::builder::styling::{AnsiColor, Effects};
+use clap::{Parser, Subcommand};
 use dc_mcp_server::custom_scalar_map::CustomScalarMap;
 use dc_mcp_server::errors::ServerError;
-use dc_mcp_server::operations::OperationSource;
+use dc_mcp_server::operations::{
+    FileOptions, OperationSource, TargetEndpoint, generate_manifest as build_manifest,
+};
+use dc_mcp_server::redaction::{self, Redactor};
+use dc_mcp_server::schema_filter::SchemaFilter;
 use dc_mcp_server::server::Server;
 use dc_mcp_server::startup;
+use dc_mcp_server::upstream::UpstreamClient;
 use runtime::IdOrDefault;
 use tokio::sync::{Mutex, RwLock};
 use tracing::{info, warn};
@@ -35,6 +42,31 @@ const STYLES: Styles = Styles::styled()
 struct Args {
     /// Path to the config file
     config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generate a persisted query manifest from local GraphQL operation files
+    GenerateManifest {
+        /// Path to the GraphQL schema to validate operations against
+        #[arg(long)]
+        schema: PathBuf,
+
+        /// GraphQL operation files, or directories of them
+        #[arg(required = true)]
+        operations: Vec<PathBuf>,
+
+        /// The client name to associate with every operation in the manifest
+        #[arg(long)]
+        client_name: Option<String>,
+
+        /// Write the manifest to this file instead of standard output
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
 }
 
 #[tokio::main]
@@ -49,6 +81,16 @@ async fn main() -> anyhow::Result<()> {
     }
 
     let args = Args::parse();
+    if let Some(Command::GenerateManifest {
+        schema,
+        operations,
+        client_name,
+        output,
+    }) = args.command
+    {
+        return generate_manifest(schema, operations, client_name, output);
+    }
+
     // Use config path as-is (already absolute from mcp.json args)
     // Don't canonicalize to avoid hanging on slow filesystems
     let config_path = args.config.clone();
@@ -82,6 +124,9 @@ async fn main() -> anyhow::Result<()> {
         None => runtime::read_config_from_env().unwrap_or_default(),
     };
 
+    // Secrets must be redacted from the very first log line
+    redaction::install(Redactor::new(&config.redaction)?)
+        .map_err(|_| anyhow::anyhow!("The redactor was already installed"))?;
     let _guard = runtime::telemetry::init_tracing_subscriber(&config)?;
 
     info!(
@@ -89,54 +134,40 @@ async fn main() -> anyhow::Result<()> {
         env!("CARGO_PKG_VERSION")
     );
 
+    // Build the upstream HTTP client once, so connections are pooled across requests
+    let http_client = UpstreamClient::new(&config.upstream)?;
+
     // Create shared headers that can be updated by token refresh
     let shared_headers = Arc::new(RwLock::new(config.headers.clone()));
 
-    // Check if token refresh is enabled
-    let token_manager = if startup::is_token_refresh_enabled() {
-        if let (Some(refresh_token), Some(refresh_url), Some(config_file)) = (
-            startup::get_refresh_token(),
-            startup::get_refresh_url(),
-            config_path.as_ref(),
-        ) {
-            // Get GraphQL endpoint from env or config
-            let graphql_endpoint =
-                startup::get_graphql_endpoint().or_else(|| Some(config.endpoint.to_string()));
-
-            if let Some(endpoint) = graphql_endpoint {
-                info!("Token refresh enabled, initializing...");
-                match startup::create_token_manager(
-                    config_file.to_string_lossy().to_string(),
-                    refresh_token,
-                    refresh_url,
-                    endpoint,
-                    Arc::clone(&shared_headers),
-                )
-                .await
-                {
-                    Ok(tm) => {
-                        info!("✅ Token refresh initialization complete");
-                        Some(Arc::new(Mutex::new(tm)))
-                    }
-                    Err(e) => {
-                        warn!("Token refresh initialization failed: {}", e);
-                        None
-                    }
+    // Token refresh is configured in the config file, or with DC_* environment variables
+    let token_refresh = match config.token_refresh.clone() {
+        Some(token_refresh) => Some(token_refresh),
+        None => startup::token_refresh_from_env()
+            .map_err(|e| anyhow::anyhow!("Invalid token refresh configuration: {}", e.message))?,
+    };
+    let token_manager = match token_refresh {
+        Some(token_refresh) => {
+            info!("Token refresh enabled, initializing...");
+            match startup::create_token_manager(
+                &token_refresh.provider,
+                token_refresh.store.store(config_path.as_deref()),
+                Arc::clone(&shared_headers),
+                http_client.http().clone(),
+            )
+            .await
+            {
+                Ok(tm) => {
+                    info!("✅ Token refresh initialization complete");
+                    Some(Arc::new(Mutex::new(tm)))
+                }
+                Err(e) => {
+                    warn!("Token refresh initialization failed: {}", e);
+                    None
                 }
-            } else {
-                warn!(
-                    "Token refresh enabled but no GraphQL endpoint found (set DC_GRAPHQL_ENDPOINT or endpoint in config)"
-                );
-                None
             }
-        } else {
-            warn!(
-                "Token refresh enabled but missing required environment variables (DC_REFRESH_TOKEN, DC_REFRESH_URL)"
-            );
-            None
         }
-    } else {
-        None
+        None => None,
     };
 
     let schema_source = match config.schema {
@@ -160,9 +191,16 @@ async fn main() -> anyhow::Result<()> {
             config.graphos.platform_api_config()?,
         )),
         runtime::OperationSource::Introspect => OperationSource::None,
-        runtime::OperationSource::Local { paths } if !paths.is_empty() => {
-            OperationSource::from(paths)
-        }
+        runtime::OperationSource::Local {
+            paths,
+            include,
+            exclude,
+            prefix_tool_names,
+            endpoint,
+        } if !paths.is_empty() => OperationSource::Files(
+            paths,
+            FileOptions::new(&include, &exclude, prefix_tool_names)?.with_endpoint(endpoint),
+        ),
         runtime::OperationSource::Manifest { path } => {
             OperationSource::from(ManifestSource::LocalHotReload(vec![path]))
         }
@@ -199,6 +237,30 @@ async fn main() -> anyhow::Result<()> {
 
     let transport = config.transport.clone();
 
+    let endpoints = config
+        .endpoints
+        .into_iter()
+        .map(|(name, endpoint)| {
+            let schema = endpoint
+                .schema
+                .map(|path| {
+                    let sdl = std::fs::read_to_string(&path)?;
+                    Schema::parse_and_validate(sdl, &path).map_err(|e| {
+                        anyhow::anyhow!("Invalid schema for endpoint {name}: {}", e.errors)
+                    })
+                })
+                .transpose()?;
+            Ok((
+                name,
+                TargetEndpoint {
+                    url: endpoint.url,
+                    headers: endpoint.headers,
+                    schema,
+                },
+            ))
+        })
+        .collect::<anyhow::Result<HashMap<_, _>>>()?;
+
     // Read current headers from shared state
     let current_headers = shared_headers.read().await.clone();
 
@@ -209,14 +271,20 @@ async fn main() -> anyhow::Result<()> {
         .endpoint(config.endpoint.into_inner())
         .maybe_explorer_graph_ref(explorer_graph_ref)
         .headers(current_headers)
+        .http_client(http_client)
         .maybe_shared_headers(Some(shared_headers))
         .execute_introspection(config.introspection.execute.enabled)
+        .execute_limits(config.introspection.execute.limits)
+        .schema_filter(SchemaFilter::new(&config.introspection.filter)?)
+        .contract(config.contract)
         .validate_introspection(config.introspection.validate.enabled)
         .introspect_introspection(config.introspection.introspect.enabled)
         .introspect_minify(config.introspection.introspect.minify)
         .search_minify(config.introspection.search.minify)
         .search_introspection(config.introspection.search.enabled)
         .mutation_mode(config.overrides.mutation_mode)
+        .persisted_queries(config.overrides.persisted_queries)
+        .maybe_tool_page_size(config.overrides.tool_page_size)
         .disable_type_description(config.overrides.disable_type_description)
         .disable_schema_description(config.overrides.disable_schema_description)
         .disable_auth_token_passthrough(match transport {
@@ -238,8 +306,42 @@ async fn main() -> anyhow::Result<()> {
         .index_memory_bytes(config.introspection.search.index_memory_bytes)
         .health_check(config.health_check)
         .cors(config.cors)
+        .projections(config.projections)
+        .endpoints(endpoints)
+        .subscriptions(config.subscriptions)
+        .response_cache(config.response_cache)
+        .rate_limits(config.rate_limits)
+        .mutation_confirmation(config.mutation_confirmation)
+        .audit(config.audit)
+        .error_mapping(config.error_mapping)
         .maybe_token_manager(token_manager)
         .build()
         .start()
         .await?)
 }
+
+/// Validate local operations against a schema and write them out as a persisted query manifest
+fn generate_manifest(
+    schema: PathBuf,
+    operations: Vec<PathBuf>,
+    client_name: Option<String>,
+    output: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let sdl = std::fs::read_to_string(&schema)?;
+    let schema = Schema::parse_and_validate(sdl, &schema)
+        .map_err(|e| anyhow::anyhow!("Invalid schema: {}", e.errors))?;
+
+    let manifest = build_manifest(&operations, &schema, client_name).map_err(|errors| {
+        for error in &errors {
+            eprintln!("{error}");
+        }
+        anyhow::anyhow!("{} invalid operations", errors.len())
+    })?;
+
+    let manifest = serde_json::to_string_pretty(&manifest)?;
+    match output {
+        Some(path) => std::fs::write(path, manifest + "\n")?,
+        None => println!("{manifest}"),
+    }
+    Ok(())
+}
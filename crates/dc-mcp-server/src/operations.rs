@@ -3,13 +3,23 @@
 //! This module includes transformation utilities that convert GraphQL operations
 //! into MCP tools.
 
+mod connection;
+mod dry_run;
+mod endpoint;
+mod fragments;
+mod manifest;
 mod mutation_mode;
 mod operation;
 mod operation_source;
+mod projection;
 mod raw_operation;
 mod schema_walker;
 
+pub use connection::{Connection, PageLimits};
+pub use endpoint::TargetEndpoint;
+pub use manifest::generate_manifest;
 pub use mutation_mode::MutationMode;
 pub use operation::{Operation, operation_defs, operation_name};
-pub use operation_source::OperationSource;
+pub use operation_source::{DEFAULT_INCLUDE, FileOptions, OperationSource};
+pub use projection::{JsonPath, ResponseProjection, Truncation};
 pub use raw_operation::RawOperation;
@@ -0,0 +1,434 @@
+//! Relay connection pagination
+//!
+//! An operation whose root field returns a Relay-style connection, selects its
+//! `pageInfo { hasNextPage endCursor }` and passes a variable as its `after` argument can
+//! be paged through by the server. Its tool gets optional `maxPages` and `maxItems`
+//! inputs, and when either is set the server follows `endCursor` and merges the `edges`
+//! of every page into a single response.
+
+use apollo_compiler::{
+    Node, Schema as GraphqlSchema,
+    ast::{Field, OperationDefinition, Selection, Value as GraphqlValue},
+};
+use rmcp::model::ErrorCode;
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use tracing::warn;
+
+use crate::errors::McpError;
+
+/// The tool input limiting the number of pages fetched
+const MAX_PAGES_INPUT: &str = "maxPages";
+
+/// The tool input limiting the number of edges fetched
+const MAX_ITEMS_INPUT: &str = "maxItems";
+
+/// A Relay connection returned by the root field of an operation, described by the
+/// response keys of the fields needed to page through it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Connection {
+    /// The root field returning the connection
+    field: String,
+
+    /// The variable passed as the `after` argument of the root field
+    cursor_variable: String,
+
+    edges: String,
+
+    /// The `cursor` of each edge, if selected
+    edge_cursor: Option<String>,
+
+    page_info: String,
+    has_next_page: String,
+    end_cursor: String,
+}
+
+/// The limits requested for a paginated tool call
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageLimits {
+    pub max_pages: Option<usize>,
+    pub max_items: Option<usize>,
+}
+
+impl Connection {
+    /// Detect whether the root field of an operation is a connection the server can page through
+    pub(crate) fn detect(
+        operation: &Node<OperationDefinition>,
+        graphql_schema: &GraphqlSchema,
+    ) -> Option<Self> {
+        let mut root_fields = operation.selection_set.iter().filter_map(field);
+        let (Some(root_field), None) = (root_fields.next(), root_fields.next()) else {
+            return None;
+        };
+
+        let cursor_variable =
+            root_field
+                .arguments
+                .iter()
+                .find_map(|argument| match argument.value.as_ref() {
+                    GraphqlValue::Variable(name) if argument.name == "after" => {
+                        Some(name.to_string())
+                    }
+                    _ => None,
+                })?;
+
+        let root_type = graphql_schema.root_operation(operation.operation_type)?;
+        let connection_type = graphql_schema
+            .type_field(root_type, &root_field.name)
+            .ok()?
+            .ty
+            .inner_named_type();
+        if graphql_schema.type_field(connection_type, "edges").is_err()
+            || graphql_schema
+                .type_field(connection_type, "pageInfo")
+                .is_err()
+        {
+            return None;
+        }
+
+        let edges = find_field(&root_field.selection_set, "edges")?;
+        let page_info = find_field(&root_field.selection_set, "pageInfo")?;
+        let has_next_page = find_field(&page_info.selection_set, "hasNextPage")?;
+        let end_cursor = find_field(&page_info.selection_set, "endCursor")?;
+
+        if operation
+            .variables
+            .iter()
+            .any(|variable| variable.name == MAX_PAGES_INPUT || variable.name == MAX_ITEMS_INPUT)
+        {
+            warn!(
+                "Not paginating connection {} since the operation already has a {MAX_PAGES_INPUT} or {MAX_ITEMS_INPUT} variable",
+                root_field.name
+            );
+            return None;
+        }
+
+        Some(Self {
+            field: response_key(root_field),
+            cursor_variable,
+            edges: response_key(edges),
+            edge_cursor: find_field(&edges.selection_set, "cursor").map(response_key),
+            page_info: response_key(page_info),
+            has_next_page: response_key(has_next_page),
+            end_cursor: response_key(end_cursor),
+        })
+    }
+
+    /// Add the pagination inputs to the input schema of a tool
+    pub(crate) fn add_inputs(&self, properties: &mut Map<String, Value>) {
+        properties.insert(
+            MAX_PAGES_INPUT.to_string(),
+            json!({
+                "type": "integer",
+                "minimum": 1,
+                "description": format!("Follow `{}.{}` to fetch up to this many pages, merging their `{}` into a single result", self.page_info, self.end_cursor, self.edges),
+            }),
+        );
+        properties.insert(
+            MAX_ITEMS_INPUT.to_string(),
+            json!({
+                "type": "integer",
+                "minimum": 1,
+                "description": format!("Follow `{}.{}` until this many `{}` have been fetched, returning at most this many", self.page_info, self.end_cursor, self.edges),
+            }),
+        );
+    }
+
+    /// The variable to set to the end cursor of the previous page
+    pub(crate) fn cursor_variable(&self) -> &str {
+        &self.cursor_variable
+    }
+
+    /// The cursor to fetch the next page with, if the connection has one
+    pub(crate) fn next_cursor(&self, response: &Value) -> Option<Value> {
+        let page_info = self.connection(response)?.get(&self.page_info)?;
+        if page_info.get(&self.has_next_page) != Some(&Value::Bool(true)) {
+            return None;
+        }
+        page_info
+            .get(&self.end_cursor)
+            .filter(|cursor| !cursor.is_null())
+            .cloned()
+    }
+
+    /// The number of edges in a response
+    pub(crate) fn item_count(&self, response: &Value) -> usize {
+        self.edges(response).map(Vec::len).unwrap_or_default()
+    }
+
+    /// Append the edges of the next page to a response, taking the page info of the new page.
+    /// Returns the number of edges added.
+    pub(crate) fn merge(&self, response: &mut Value, page: Value) -> usize {
+        let Value::Object(mut page) = page else {
+            return 0;
+        };
+
+        if let Some(Value::Array(errors)) = page.remove("errors") {
+            match response.get_mut("errors") {
+                Some(Value::Array(existing)) => existing.extend(errors),
+                _ => {
+                    if let Some(response) = response.as_object_mut() {
+                        response.insert("errors".to_string(), Value::Array(errors));
+                    }
+                }
+            }
+        }
+
+        let Some(Value::Object(mut connection)) = page
+            .remove("data")
+            .and_then(|mut data| data.get_mut(&self.field).map(Value::take))
+        else {
+            return 0;
+        };
+        let Some(Value::Array(edges)) = connection.remove(&self.edges) else {
+            return 0;
+        };
+        let added = edges.len();
+
+        if let Some(Value::Object(existing)) = response
+            .get_mut("data")
+            .and_then(|data| data.get_mut(&self.field))
+        {
+            if let Some(Value::Array(existing)) = existing.get_mut(&self.edges) {
+                existing.extend(edges);
+            }
+            if let Some(page_info) = connection.remove(&self.page_info) {
+                existing.insert(self.page_info.clone(), page_info);
+            }
+        }
+        added
+    }
+
+    /// Drop edges beyond the maximum, pointing the page info at the last edge kept
+    pub(crate) fn truncate(&self, response: &mut Value, max_items: usize) {
+        let Some(Value::Object(connection)) = response
+            .get_mut("data")
+            .and_then(|data| data.get_mut(&self.field))
+        else {
+            return;
+        };
+        let Some(Value::Array(edges)) = connection.get_mut(&self.edges) else {
+            return;
+        };
+        if edges.len() <= max_items {
+            return;
+        }
+        edges.truncate(max_items);
+
+        let end_cursor = self
+            .edge_cursor
+            .as_ref()
+            .and_then(|cursor| edges.last()?.get(cursor))
+            .cloned();
+        if let Some(Value::Object(page_info)) = connection.get_mut(&self.page_info) {
+            page_info.insert(self.has_next_page.clone(), Value::Bool(true));
+            match end_cursor {
+                Some(end_cursor) => {
+                    page_info.insert(self.end_cursor.clone(), end_cursor);
+                }
+                // Without the edge cursors, the end cursor of the last page would skip items
+                None => {
+                    page_info.insert(self.end_cursor.clone(), Value::Null);
+                }
+            }
+        }
+    }
+
+    fn connection<'a>(&self, response: &'a Value) -> Option<&'a Value> {
+        response.get("data")?.get(&self.field)
+    }
+
+    fn edges<'a>(&self, response: &'a Value) -> Option<&'a Vec<Value>> {
+        self.connection(response)?.get(&self.edges)?.as_array()
+    }
+}
+
+impl PageLimits {
+    /// Read the pagination inputs of a tool call, if any were set
+    pub(crate) fn from_input(input: &Value) -> Result<Option<Self>, McpError> {
+        let limits = Self {
+            max_pages: limit(input, MAX_PAGES_INPUT)?,
+            max_items: limit(input, MAX_ITEMS_INPUT)?,
+        };
+        Ok((limits.max_pages.is_some() || limits.max_items.is_some()).then_some(limits))
+    }
+
+    /// Remove the pagination inputs from the input of a tool call, leaving the variables
+    pub(crate) fn strip(input: Value) -> Value {
+        match input {
+            Value::Object(mut input) => {
+                input.remove(MAX_PAGES_INPUT);
+                input.remove(MAX_ITEMS_INPUT);
+                Value::Object(input)
+            }
+            input => input,
+        }
+    }
+
+    /// Whether another page should be fetched after the given pages and items
+    pub(crate) fn allows(&self, pages: usize, items: usize) -> bool {
+        self.max_pages.is_none_or(|max_pages| pages < max_pages)
+            && self.max_items.is_none_or(|max_items| items < max_items)
+    }
+}
+
+fn limit(input: &Value, name: &str) -> Result<Option<usize>, McpError> {
+    match input.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => value
+            .as_u64()
+            .filter(|limit| *limit > 0)
+            .and_then(|limit| usize::try_from(limit).ok())
+            .map(Some)
+            .ok_or_else(|| {
+                McpError::new(
+                    ErrorCode::INVALID_PARAMS,
+                    format!("{name} must be a positive integer"),
+                    None,
+                )
+            }),
+    }
+}
+
+fn field(selection: &Selection) -> Option<&Node<Field>> {
+    match selection {
+        Selection::Field(field) => Some(field),
+        _ => None,
+    }
+}
+
+fn find_field<'a>(selection_set: &'a [Selection], name: &str) -> Option<&'a Node<Field>> {
+    selection_set
+        .iter()
+        .filter_map(field)
+        .find(|field| field.name == name)
+}
+
+fn response_key(field: &Node<Field>) -> String {
+    field.alias.as_ref().unwrap_or(&field.name).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use apollo_compiler::{ast::Definition, parser::Parser, validation::Valid};
+    use std::sync::LazyLock;
+
+    static SCHEMA: LazyLock<Valid<GraphqlSchema>> = LazyLock::new(|| {
+        GraphqlSchema::parse(
+            r#"
+                type Query {
+                    documents(first: Int, after: String): DocumentConnection
+                    document(id: ID!): Document
+                }
+                type DocumentConnection { edges: [DocumentEdge] pageInfo: PageInfo! }
+                type DocumentEdge { cursor: String! node: Document }
+                type PageInfo { hasNextPage: Boolean! endCursor: String }
+                type Document { id: ID! }
+            "#,
+            "schema.graphql",
+        )
+        .unwrap()
+        .validate()
+        .unwrap()
+    });
+
+    fn detect(source: &str) -> Option<Connection> {
+        let document = Parser::new()
+            .parse_ast(source, "operation.graphql")
+            .unwrap();
+        let operation = document
+            .definitions
+            .iter()
+            .find_map(|definition| match definition {
+                Definition::OperationDefinition(operation) => Some(operation.clone()),
+                _ => None,
+            })
+            .unwrap();
+        Connection::detect(&operation, &SCHEMA)
+    }
+
+    fn connection() -> Connection {
+        detect(
+            "query Documents($after: String) { documents(first: 2, after: $after) { edges { cursor node { id } } pageInfo { hasNextPage endCursor } } }",
+        )
+        .unwrap()
+    }
+
+    fn page(ids: &[&str], has_next_page: bool) -> Value {
+        json!({ "data": { "documents": {
+            "edges": ids.iter().map(|id| json!({ "cursor": id, "node": { "id": id } })).collect::<Vec<_>>(),
+            "pageInfo": { "hasNextPage": has_next_page, "endCursor": ids.last() },
+        } } })
+    }
+
+    #[test]
+    fn it_detects_connections() {
+        assert_eq!(
+            detect(
+                "query Documents($cursor: String) { docs: documents(after: $cursor) { items: edges { node { id } } pageInfo { more: hasNextPage endCursor } } }"
+            ),
+            Some(Connection {
+                field: "docs".to_string(),
+                cursor_variable: "cursor".to_string(),
+                edges: "items".to_string(),
+                edge_cursor: None,
+                page_info: "pageInfo".to_string(),
+                has_next_page: "more".to_string(),
+                end_cursor: "endCursor".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn it_ignores_operations_that_cannot_be_paged() {
+        // No cursor variable
+        assert!(detect("query Documents { documents(after: \"a\") { edges { cursor } pageInfo { hasNextPage endCursor } } }").is_none());
+        // No end cursor selected
+        assert!(detect("query Documents($after: String) { documents(after: $after) { edges { cursor } pageInfo { hasNextPage } } }").is_none());
+        // Not a connection
+        assert!(detect("query Document($after: ID!) { document(id: $after) { id } }").is_none());
+        // More than one root field
+        assert!(detect("query Documents($after: String) { documents(after: $after) { edges { cursor } pageInfo { hasNextPage endCursor } } document(id: 1) { id } }").is_none());
+    }
+
+    #[test]
+    fn it_merges_pages() {
+        let connection = connection();
+        let mut response = page(&["a", "b"], true);
+
+        assert_eq!(connection.next_cursor(&response), Some(json!("b")));
+        assert_eq!(connection.merge(&mut response, page(&["c"], false)), 1);
+
+        assert_eq!(connection.item_count(&response), 3);
+        assert_eq!(connection.next_cursor(&response), None);
+        assert_eq!(response, page(&["a", "b", "c"], false));
+    }
+
+    #[test]
+    fn it_truncates_to_max_items() {
+        let connection = connection();
+        let mut response = page(&["a", "b", "c"], false);
+
+        connection.truncate(&mut response, 2);
+
+        assert_eq!(response, page(&["a", "b"], true));
+    }
+
+    #[test]
+    fn it_reads_page_limits() {
+        assert_eq!(PageLimits::from_input(&json!({ "id": 1 })).unwrap(), None);
+        assert_eq!(
+            PageLimits::from_input(&json!({ "maxPages": 3 })).unwrap(),
+            Some(PageLimits {
+                max_pages: Some(3),
+                max_items: None,
+            })
+        );
+        assert!(PageLimits::from_input(&json!({ "maxItems": 0 })).is_err());
+        assert_eq!(
+            PageLimits::strip(json!({ "after": "a", "maxPages": 3, "maxItems": 10 })),
+            json!({ "after": "a" })
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Dry runs of mutation tools
+//!
+//! Mutation tools get an optional `dryRun` input. When it is set, the server validates the
+//! variables against the tool's input schema and the operation against the current schema,
+//! then returns the request body it would send instead of sending it.
+
+use rmcp::model::{CallToolResult, Content, ErrorCode};
+use serde_json::{Map, Value, json};
+
+use crate::errors::McpError;
+
+/// The tool input requesting a dry run
+const DRY_RUN_INPUT: &str = "dryRun";
+
+/// Add the dry run input to the properties of a tool's input schema, unless the operation
+/// already has a variable with its name
+pub(crate) fn add_input(properties: &mut Map<String, Value>) -> bool {
+    if properties.contains_key(DRY_RUN_INPUT) {
+        return false;
+    }
+    properties.insert(
+        DRY_RUN_INPUT.to_string(),
+        json!({
+            "type": "boolean",
+            "description": "Validate the input and return the request that would be sent, without running the mutation",
+        }),
+    );
+    true
+}
+
+/// Whether the input of a tool call requests a dry run
+pub(crate) fn is_requested(input: &Value) -> Result<bool, McpError> {
+    match input.get(DRY_RUN_INPUT) {
+        None | Some(Value::Null) => Ok(false),
+        Some(Value::Bool(dry_run)) => Ok(*dry_run),
+        Some(_) => Err(McpError::new(
+            ErrorCode::INVALID_PARAMS,
+            format!("{DRY_RUN_INPUT} must be a boolean"),
+            None,
+        )),
+    }
+}
+
+/// Remove the dry run input from the input of a tool call, leaving the variables
+pub(crate) fn strip(input: Value) -> Value {
+    match input {
+        Value::Object(mut input) => {
+            input.remove(DRY_RUN_INPUT);
+            Value::Object(input)
+        }
+        input => input,
+    }
+}
+
+/// Validate the input of a tool call against the tool's input schema
+pub(crate) fn validate_input(
+    input_schema: &Map<String, Value>,
+    input: &Value,
+) -> Result<(), McpError> {
+    let validator = jsonschema::validator_for(&Value::Object(input_schema.clone()))
+        .map_err(|e| McpError::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
+    let input = if input.is_null() {
+        Value::Object(Map::new())
+    } else {
+        input.clone()
+    };
+    let errors: Vec<String> = validator
+        .iter_errors(&input)
+        .map(|error| match error.instance_path.to_string() {
+            path if path.is_empty() => error.to_string(),
+            path => format!("{path}: {error}"),
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(McpError::new(
+            ErrorCode::INVALID_PARAMS,
+            format!("Invalid input: {}", errors.join("; ")),
+            None,
+        ))
+    }
+}
+
+/// The result returned for a dry run, containing the request body that would have been sent
+pub(crate) fn result(body: Map<String, Value>) -> CallToolResult {
+    let body = Value::Object(body);
+    CallToolResult {
+        content: vec![Content::json(&body).unwrap_or(Content::text(body.to_string()))],
+        is_error: None,
+        meta: None,
+        structured_content: Some(body),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_the_dry_run_input() {
+        assert!(!is_requested(&json!({ "id": 1 })).unwrap());
+        assert!(!is_requested(&json!({ "dryRun": false })).unwrap());
+        assert!(is_requested(&json!({ "dryRun": true })).unwrap());
+        assert!(is_requested(&json!({ "dryRun": "yes" })).is_err());
+        assert_eq!(
+            strip(json!({ "id": 1, "dryRun": true })),
+            json!({ "id": 1 })
+        );
+    }
+
+    #[test]
+    fn it_validates_the_input_against_the_schema() {
+        let mut properties = Map::from_iter([(String::from("id"), json!({ "type": "string" }))]);
+        assert!(add_input(&mut properties));
+        assert!(!add_input(&mut properties));
+        let schema = Map::from_iter([
+            (String::from("type"), json!("object")),
+            (String::from("properties"), Value::Object(properties)),
+            (String::from("required"), json!(["id"])),
+        ]);
+
+        assert!(validate_input(&schema, &json!({ "id": "1", "dryRun": true })).is_ok());
+        let error = validate_input(&schema, &json!({ "id": 1, "dryRun": true })).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Invalid input: /id: 1 is not of type \"string\""
+        );
+        assert!(validate_input(&schema, &json!({ "dryRun": true })).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Target endpoints
+//!
+//! Operations are sent to the default GraphQL endpoint unless they target one of the named
+//! endpoints in the config. The target is set for every operation of a source, or for a
+//! single operation with a comment directive in the operation file:
+//!
+//! ```graphql
+//! # List the tenants of the admin API
+//! # @endpoint admin
+//! query ListTenants { ... }
+//! ```
+
+use apollo_compiler::{Schema, ast::Definition, parser::Parser, validation::Valid};
+use reqwest::header::HeaderMap;
+use url::Url;
+
+use super::operation::with_leading_comments;
+
+const ENDPOINT_DIRECTIVE: &str = "@endpoint";
+
+/// A named GraphQL endpoint that operations can target
+#[derive(Debug, Clone)]
+pub struct TargetEndpoint {
+    /// The URL of the GraphQL endpoint
+    pub url: Url,
+
+    /// The headers to include in requests to the endpoint
+    pub headers: HeaderMap,
+
+    /// The schema to validate operations targeting the endpoint against. The default schema
+    /// is used when unset.
+    pub schema: Option<Valid<Schema>>,
+}
+
+/// The endpoint named by an `@endpoint` comment directive in the source text of an operation.
+/// Only the comments leading the operation are read, which are the ones its tool description is
+/// made of, so comments in its selection set or in fragments don't change its endpoint.
+pub(super) fn endpoint_directive(source_text: &str) -> Option<&str> {
+    let document = Parser::new()
+        .parse_ast(source_text, "operation.graphql")
+        .ok()?;
+    with_leading_comments(&document, source_text)
+        .into_iter()
+        .find_map(|(definition, comments)| {
+            matches!(definition, Definition::OperationDefinition(_)).then_some(comments)
+        })??
+        .lines()
+        .find_map(directive_name)
+}
+
+/// Remove endpoint directives from the comments on an operation
+pub(super) fn strip_endpoint_directives(comments: Option<String>) -> Option<String> {
+    comments.map(|comments| {
+        comments
+            .lines()
+            .filter(|line| directive_name(line).is_none())
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+fn directive_name(line: &str) -> Option<&str> {
+    line.trim_start_matches(|c: char| c.is_whitespace() || c == ',')
+        .strip_prefix('#')?
+        .trim()
+        .strip_prefix(ENDPOINT_DIRECTIVE)
+        .filter(|name| name.starts_with(char::is_whitespace))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::errors::OperationError;
+    use crate::operations::{MutationMode, RawOperation};
+
+    use super::*;
+
+    fn schema(sdl: &str) -> Valid<Schema> {
+        Schema::parse_and_validate(sdl, "schema.graphql").unwrap()
+    }
+
+    fn into_operation(
+        source_text: &str,
+        endpoints: &HashMap<String, TargetEndpoint>,
+    ) -> Result<Option<crate::operations::Operation>, OperationError> {
+        RawOperation::from((source_text.to_string(), Some("op.graphql".to_string())))
+            .into_operation(
+                &schema("type Query { id: ID }"),
+                None,
+                MutationMode::None,
+                false,
+                false,
+                false,
+                &HashMap::new(),
+                endpoints,
+            )
+    }
+
+    #[test]
+    fn it_reads_the_endpoint_directive() {
+        let source_text = "# List tenants\n  # @endpoint admin\nquery ListTenants { id }";
+
+        assert_eq!(endpoint_directive(source_text), Some("admin"));
+        assert_eq!(
+            strip_endpoint_directives(Some("# List tenants\n  # @endpoint admin".to_string())),
+            Some("# List tenants".to_string())
+        );
+    }
+
+    #[test]
+    fn it_ignores_other_comments() {
+        assert_eq!(
+            endpoint_directive("# @endpoints admin\n# @endpoint\nquery Q { id }"),
+            None
+        );
+    }
+
+    #[test]
+    fn it_only_reads_the_comments_leading_the_operation() {
+        assert_eq!(
+            endpoint_directive(
+                "# Get a folder\nquery Folder {\n  # @endpoint admin\n  folder { ...FolderFields }\n}\n\n# @endpoint admin\nfragment FolderFields on Folder { id }"
+            ),
+            None
+        );
+        assert_eq!(
+            endpoint_directive(
+                "fragment FolderFields on Folder { id }\n\n# @endpoint admin\nquery Folder { folder { ...FolderFields } }"
+            ),
+            Some("admin")
+        );
+    }
+
+    #[test]
+    fn it_validates_operations_against_the_endpoint_schema() {
+        let endpoints = HashMap::from([(
+            "admin".to_string(),
+            TargetEndpoint {
+                url: Url::parse("http://admin.example.com/graphql").unwrap(),
+                headers: HeaderMap::new(),
+                schema: Some(schema("type Query { tenants: [String] }")),
+            },
+        )]);
+
+        let operation = into_operation("# @endpoint admin\nquery Tenants { tenants }", &endpoints)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(operation.endpoint(), Some("admin"));
+        assert_eq!(
+            operation.as_ref().description.as_deref(),
+            Some("The returned value is optional and is an array of type `String`")
+        );
+    }
+
+    #[test]
+    fn it_rejects_unknown_endpoints() {
+        let error =
+            into_operation("# @endpoint admin\nquery Id { id }", &HashMap::new()).unwrap_err();
+
+        assert_eq!(error.to_string(), "op.graphql: Unknown endpoint 'admin'");
+    }
+}
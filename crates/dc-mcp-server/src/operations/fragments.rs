@@ -0,0 +1,226 @@
+//! Shared fragments
+//!
+//! A GraphQL document file that defines only fragments is a fragment file. Its fragments can
+//! be spread by any operation loaded from files, and the definitions each operation uses are
+//! appended to its document, so they are sent with the operation and shape its tool.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use apollo_compiler::{
+    Name,
+    ast::{Definition, Document, Selection},
+    parser::Parser,
+};
+use tracing::warn;
+
+use super::RawOperation;
+
+/// A fragment defined in a fragment file
+struct SharedFragment {
+    /// The source text of the fragment definition
+    source_text: String,
+
+    /// The fragments spread within the fragment
+    spreads: BTreeSet<Name>,
+}
+
+/// Append the definitions of the shared fragments used by each operation to its document,
+/// removing fragment files from the operations.
+///
+/// Operations are left as they are when their document can't be parsed, so the error is
+/// reported when they are loaded.
+pub(super) fn resolve_shared_fragments(raw_operations: Vec<RawOperation>) -> Vec<RawOperation> {
+    let documents: Vec<_> = raw_operations
+        .into_iter()
+        .map(|raw_operation| {
+            let document = Parser::new()
+                .parse_ast(
+                    &raw_operation.source_text,
+                    raw_operation
+                        .source_path
+                        .clone()
+                        .unwrap_or_else(|| "operation.graphql".to_string()),
+                )
+                .ok();
+            (raw_operation, document)
+        })
+        .collect();
+
+    let mut fragments = HashMap::new();
+    for (raw_operation, document) in &documents {
+        let Some(document) = document.as_ref().filter(|d| is_fragment_file(d)) else {
+            continue;
+        };
+        for definition in &document.definitions {
+            let Definition::FragmentDefinition(fragment) = definition else {
+                continue;
+            };
+            let Some(source_text) = definition.location().and_then(|span| {
+                raw_operation
+                    .source_text
+                    .get(span.offset()..span.end_offset())
+            }) else {
+                continue;
+            };
+            if fragments.contains_key(&fragment.name) {
+                warn!(
+                    name = %fragment.name,
+                    path = raw_operation.source_path.as_deref().unwrap_or_default(),
+                    "Ignoring duplicate shared fragment"
+                );
+                continue;
+            }
+            let mut spreads = BTreeSet::new();
+            collect_spreads(&fragment.selection_set, &mut spreads);
+            fragments.insert(
+                fragment.name.clone(),
+                SharedFragment {
+                    source_text: source_text.to_string(),
+                    spreads,
+                },
+            );
+        }
+    }
+
+    documents
+        .into_iter()
+        .filter_map(|(mut raw_operation, document)| {
+            let Some(document) = document else {
+                return Some(raw_operation);
+            };
+            if is_fragment_file(&document) {
+                return None;
+            }
+
+            for name in used_shared_fragments(&document, &fragments) {
+                if let Some(fragment) = fragments.get(&name) {
+                    raw_operation.source_text.push_str("\n\n");
+                    raw_operation.source_text.push_str(&fragment.source_text);
+                }
+            }
+            Some(raw_operation)
+        })
+        .collect()
+}
+
+/// Whether a document defines fragments, but no operations
+fn is_fragment_file(document: &Document) -> bool {
+    document
+        .definitions
+        .iter()
+        .all(|definition| matches!(definition, Definition::FragmentDefinition(_)))
+        && !document.definitions.is_empty()
+}
+
+/// The shared fragments an operation document spreads, directly or through other
+/// fragments, without defining them itself
+fn used_shared_fragments(
+    document: &Document,
+    fragments: &HashMap<Name, SharedFragment>,
+) -> BTreeSet<Name> {
+    let mut defined = HashSet::new();
+    let mut pending = BTreeSet::new();
+    for definition in &document.definitions {
+        match definition {
+            Definition::OperationDefinition(operation) => {
+                collect_spreads(&operation.selection_set, &mut pending)
+            }
+            Definition::FragmentDefinition(fragment) => {
+                defined.insert(fragment.name.clone());
+                collect_spreads(&fragment.selection_set, &mut pending);
+            }
+            _ => {}
+        }
+    }
+
+    let mut used = BTreeSet::new();
+    while let Some(name) = pending.pop_first() {
+        if defined.contains(&name) || used.contains(&name) {
+            continue;
+        }
+        if let Some(fragment) = fragments.get(&name) {
+            pending.extend(fragment.spreads.iter().cloned());
+            used.insert(name);
+        }
+    }
+    used
+}
+
+fn collect_spreads(selection_set: &[Selection], spreads: &mut BTreeSet<Name>) {
+    for selection in selection_set {
+        match selection {
+            Selection::Field(field) => collect_spreads(&field.selection_set, spreads),
+            Selection::InlineFragment(fragment) => {
+                collect_spreads(&fragment.selection_set, spreads)
+            }
+            Selection::FragmentSpread(spread) => {
+                spreads.insert(spread.fragment_name.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(source_text: &str, source_path: &str) -> RawOperation {
+        RawOperation::from((source_text.to_string(), Some(source_path.to_string())))
+    }
+
+    #[test]
+    fn it_appends_used_fragments_transitively() {
+        let operations = resolve_shared_fragments(vec![
+            raw(
+                "query GetFolder { folder { ...FolderFields } }",
+                "operations/get_folder.graphql",
+            ),
+            raw(
+                "fragment FolderFields on Folder { id owner { ...UserFields } }\n\nfragment Unused on Folder { id }",
+                "fragments/folder.graphql",
+            ),
+            raw(
+                "fragment UserFields on User { id name }",
+                "fragments/user.graphql",
+            ),
+        ]);
+
+        assert_eq!(operations.len(), 1);
+        insta::assert_snapshot!(operations.first().unwrap().source_text, @"
+        query GetFolder { folder { ...FolderFields } }
+
+        fragment FolderFields on Folder { id owner { ...UserFields } }
+
+        fragment UserFields on User { id name }
+        ");
+    }
+
+    #[test]
+    fn it_prefers_fragments_defined_in_the_operation() {
+        let source_text =
+            "query GetUser { user { ...UserFields } }\n\nfragment UserFields on User { id }";
+        let operations = resolve_shared_fragments(vec![
+            raw(source_text, "get_user.graphql"),
+            raw(
+                "fragment UserFields on User { id name }",
+                "fragments/user.graphql",
+            ),
+        ]);
+
+        assert_eq!(operations.first().unwrap().source_text, source_text);
+    }
+
+    #[test]
+    fn it_leaves_unparseable_operations_unchanged() {
+        let operations = resolve_shared_fragments(vec![
+            raw("query Broken {", "broken.graphql"),
+            raw(
+                "fragment UserFields on User { id }",
+                "fragments/user.graphql",
+            ),
+        ]);
+
+        assert_eq!(operations.len(), 1);
+        assert_eq!(operations.first().unwrap().source_text, "query Broken {");
+    }
+}
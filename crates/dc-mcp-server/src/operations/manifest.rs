@@ -0,0 +1,166 @@
+//! Persisted query manifest generation
+//!
+//! Builds an `apollo-persisted-query-manifest` from local GraphQL operation files, so the
+//! manifest loaded with `OperationSource::Manifest` doesn't have to be maintained by hand.
+
+use std::path::PathBuf;
+
+use apollo_compiler::{ExecutableDocument, Schema, validation::Valid};
+use apollo_mcp_registry::uplink::persisted_queries::{ManifestOperation, SignedUrlChunk};
+use sha2::{Digest as _, Sha256};
+
+use crate::errors::OperationError;
+
+use super::{
+    RawOperation,
+    fragments::resolve_shared_fragments,
+    operation_defs, operation_name,
+    operation_source::{FileOptions, read_operations},
+};
+
+const MANIFEST_FORMAT: &str = "apollo-persisted-query-manifest";
+const MANIFEST_VERSION: u64 = 1;
+
+/// Generate a persisted query manifest from GraphQL operation files, or directories of them.
+///
+/// Every operation is validated against the schema, and identified by the SHA-256 hash of
+/// its document. All invalid operations are returned as errors.
+pub fn generate_manifest(
+    paths: &[PathBuf],
+    schema: &Valid<Schema>,
+    client_name: Option<String>,
+) -> Result<SignedUrlChunk, Vec<OperationError>> {
+    let mut raw_operations = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        match read_operations(path, &FileOptions::default()) {
+            Ok(path_operations) => raw_operations.extend(path_operations),
+            Err(e) => errors.push(OperationError::File(e)),
+        }
+    }
+
+    let mut operations: Vec<ManifestOperation> = Vec::new();
+    for raw_operation in resolve_shared_fragments(raw_operations) {
+        match manifest_operation(raw_operation, schema, client_name.clone()) {
+            Ok(operation) if operations.iter().any(|o| o.id == operation.id) => {}
+            Ok(operation) => operations.push(operation),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(SignedUrlChunk {
+        format: MANIFEST_FORMAT.to_string(),
+        version: MANIFEST_VERSION,
+        operations,
+    })
+}
+
+fn manifest_operation(
+    raw_operation: RawOperation,
+    schema: &Valid<Schema>,
+    client_name: Option<String>,
+) -> Result<ManifestOperation, OperationError> {
+    let RawOperation {
+        source_text,
+        source_path,
+        ..
+    } = raw_operation;
+
+    // Apply the same rules as when operations are loaded as tools
+    if let Some((_, operation, _)) = operation_defs(&source_text, true, source_path.clone())? {
+        operation_name(&operation, source_path.clone())?;
+    }
+
+    ExecutableDocument::parse_and_validate(
+        schema,
+        &source_text,
+        source_path.unwrap_or_else(|| "operation.graphql".to_string()),
+    )
+    .map_err(|e| OperationError::Validation(Box::new(e)))?;
+
+    Ok(ManifestOperation {
+        id: format!("{:x}", Sha256::digest(source_text.as_bytes())),
+        body: source_text,
+        client_name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn schema() -> Valid<Schema> {
+        Schema::parse_and_validate("type Query { id: String, name: String }", "schema.graphql")
+            .unwrap()
+    }
+
+    #[test]
+    fn it_generates_a_valid_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("b.graphql"), "query GetName { name }").unwrap();
+        fs::write(dir.path().join("a.graphql"), "query GetId { id }").unwrap();
+        fs::write(dir.path().join("notes.txt"), "not an operation").unwrap();
+
+        let manifest = generate_manifest(
+            &[dir.path().to_path_buf()],
+            &schema(),
+            Some("mcp".to_string()),
+        )
+        .unwrap();
+
+        let manifest =
+            SignedUrlChunk::parse_and_validate(&serde_json::to_string(&manifest).unwrap()).unwrap();
+        let operations: Vec<_> = manifest
+            .operations
+            .iter()
+            .map(|operation| (operation.id.as_str(), operation.body.as_str()))
+            .collect();
+        assert_eq!(
+            operations,
+            vec![
+                (
+                    "3e0e4e5f99f1fd56bf938d640f5ab8e363925dc848cb41079a5493b4d2a9d28c",
+                    "query GetId { id }"
+                ),
+                (
+                    "e7317ba06b1b348c272df635dc1e3d06c76a03f0d8b99103596ef4af351f63d6",
+                    "query GetName { name }"
+                ),
+            ]
+        );
+        assert_eq!(
+            manifest.operations.first().unwrap().client_name.as_deref(),
+            Some("mcp")
+        );
+    }
+
+    #[test]
+    fn it_reports_every_invalid_operation() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("unknown.graphql"),
+            "query Unknown { missing }",
+        )
+        .unwrap();
+        fs::write(dir.path().join("unnamed.graphql"), "query { id }").unwrap();
+        fs::write(dir.path().join("valid.graphql"), "query Valid { id }").unwrap();
+
+        let errors = generate_manifest(&[dir.path().to_path_buf()], &schema(), None).unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors.first(),
+            Some(OperationError::Validation(_))
+        ));
+        assert!(matches!(
+            errors.last(),
+            Some(OperationError::MissingName { .. })
+        ));
+    }
+}
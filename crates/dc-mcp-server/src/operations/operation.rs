@@ -1,18 +1,20 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use apollo_compiler::{
-    Node, Schema as GraphqlSchema,
+    ExecutableDocument, Node, Schema as GraphqlSchema,
     ast::{Definition, Document, OperationDefinition, OperationType, Selection, Type},
     parser::Parser,
     schema::ExtendedType,
+    validation::Valid,
 };
 use http::{HeaderMap, HeaderValue};
 use regex::Regex;
-use rmcp::model::{ErrorCode, Tool, ToolAnnotations};
+use rmcp::model::{CallToolResult, ErrorCode, Tool, ToolAnnotations};
 use schemars::{Schema, json_schema};
 use serde::Serialize;
 use serde_json::{Map, Value};
-use tracing::{debug, info, warn};
+use tracing::{info, warn};
 
 use crate::{
     custom_scalar_map::CustomScalarMap,
@@ -21,7 +23,10 @@ use crate::{
     schema_tree_shake::{DepthLimit, SchemaTreeShaker},
 };
 
-use super::{MutationMode, RawOperation, schema_walker};
+use super::{
+    Connection, MutationMode, PageLimits, RawOperation, ResponseProjection, dry_run,
+    endpoint::strip_endpoint_directives, schema_walker,
+};
 
 /// A valid GraphQL operation
 #[derive(Debug, Clone, Serialize)]
@@ -29,6 +34,16 @@ pub struct Operation {
     tool: Tool,
     inner: RawOperation,
     operation_name: String,
+    #[serde(skip)]
+    operation_type: OperationType,
+    #[serde(skip)]
+    endpoint: Option<String>,
+    projection: Option<ResponseProjection>,
+    connection: Option<Connection>,
+    #[serde(skip)]
+    dry_run: bool,
+    #[serde(skip)]
+    persisted_queries: bool,
 }
 
 impl AsRef<Tool> for Operation {
@@ -48,6 +63,73 @@ impl Operation {
         self.inner
     }
 
+    /// The name of the GraphQL operation, which is also the tool name unless it is prefixed
+    pub(crate) fn operation_name(&self) -> &str {
+        &self.operation_name
+    }
+
+    /// The name of the endpoint the operation targets, if it doesn't use the default endpoint
+    pub(crate) fn endpoint(&self) -> Option<&str> {
+        self.endpoint.as_deref()
+    }
+
+    /// The type of the operation, used to decide how the operation is executed
+    pub fn operation_type(&self) -> OperationType {
+        self.operation_type
+    }
+
+    /// Validate the operation against a schema
+    pub(crate) fn validate(&self, schema: &Valid<GraphqlSchema>) -> Result<(), OperationError> {
+        ExecutableDocument::parse_and_validate(
+            schema,
+            self.inner.source_text.as_str(),
+            self.inner
+                .source_path
+                .as_deref()
+                .unwrap_or("operation.graphql"),
+        )
+        .map(|_| ())
+        .map_err(|e| OperationError::Validation(Box::new(e)))
+    }
+
+    /// Whether a tool call asks for a dry run of the operation
+    pub(crate) fn is_dry_run(&self, input: &Value) -> Result<bool, McpError> {
+        if self.dry_run {
+            dry_run::is_requested(input)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Validate a tool call and return the request body it would send, without sending it.
+    /// Operations targeting another endpoint are only validated against their input schema.
+    pub(crate) fn dry_run(
+        &self,
+        input: Value,
+        schema: &Valid<GraphqlSchema>,
+    ) -> Result<CallToolResult, McpError> {
+        dry_run::validate_input(&self.tool.input_schema, &input)?;
+        if self.endpoint().is_none() {
+            self.validate(schema)
+                .map_err(|e| McpError::new(ErrorCode::INVALID_PARAMS, e.to_string(), None))?;
+        }
+        let (body, _) =
+            graphql::request_body(self, input, graphql::Executable::persisted_query_id(self))?;
+        Ok(dry_run::result(body))
+    }
+
+    /// Execute the operation by its persisted query ID, if it has one
+    pub(crate) fn with_persisted_queries(mut self, enabled: bool) -> Self {
+        self.persisted_queries = enabled;
+        self
+    }
+
+    /// Replace the response projection declared in the operation's comments
+    pub(crate) fn with_projection(mut self, projection: ResponseProjection) -> Self {
+        self.projection = Some(projection);
+        self
+    }
+
     #[tracing::instrument(skip_all, name = "load_tool")]
     pub fn from_document(
         raw_operation: RawOperation,
@@ -78,6 +160,13 @@ impl Operation {
                 }
                 Err(e) => return Err(e),
             };
+            let (comments, projection) = ResponseProjection::from_comments(
+                strip_endpoint_directives(comments),
+            )
+            .map_err(|message| OperationError::InvalidProjection {
+                source_path: raw_operation.source_path.clone(),
+                message,
+            })?;
             let variable_description_overrides =
                 variable_description_overrides(&raw_operation.source_text, &operation);
             let mut tree_shaker = SchemaTreeShaker::new(graphql_schema);
@@ -105,33 +194,67 @@ impl Operation {
             // configured to skip empty maps (in the case where there are no input args)
             ensure_properties_exists(&mut object);
 
-            let Value::Object(schema) = object else {
+            let Value::Object(mut schema) = object else {
+                return Err(OperationError::Internal(
+                    "Schemars should have returned an object".to_string(),
+                ));
+            };
+
+            let connection = Connection::detect(&operation, graphql_schema);
+            if let Some(connection) = connection.as_ref()
+                && let Some(Value::Object(properties)) = schema.get_mut("properties")
+            {
+                connection.add_inputs(properties);
+            }
+
+            let dry_run = operation.operation_type == OperationType::Mutation
+                && schema
+                    .get_mut("properties")
+                    .and_then(Value::as_object_mut)
+                    .is_some_and(dry_run::add_input);
+
+            let Value::Object(output_schema) = serde_json::to_value(get_output_schema(
+                &operation,
+                &document,
+                graphql_schema,
+                custom_scalar_map,
+            ))?
+            else {
                 return Err(OperationError::Internal(
                     "Schemars should have returned an object".to_string(),
                 ));
             };
 
-            let tool: Tool = Tool::new(operation_name.clone(), description, schema).annotate(
+            let tool_name = match raw_operation.tool_name_prefix.as_ref() {
+                Some(prefix) => format!("{prefix}_{operation_name}"),
+                None => operation_name.clone(),
+            };
+            let mut tool: Tool = Tool::new(tool_name.clone(), description, schema).annotate(
                 ToolAnnotations::new()
                     .read_only(operation.operation_type != OperationType::Mutation),
             );
+            tool.output_schema = Some(Arc::new(output_schema));
             let character_count = tool_character_length(&tool);
             match character_count {
                 Ok(length) => info!(
                     "Tool {} loaded with a character count of {}. Estimated tokens: {}",
-                    operation_name,
+                    tool_name,
                     length,
                     length / 4 // We don't know the tokenization algorithm, so we just use 4 characters per token as a rough estimate. https://docs.anthropic.com/en/docs/resources/glossary#tokens
                 ),
-                Err(_) => info!(
-                    "Tool {} loaded with an unknown character count",
-                    operation_name
-                ),
+                Err(_) => info!("Tool {} loaded with an unknown character count", tool_name),
             }
             Ok(Some(Operation {
                 tool,
+                // Resolved once, since it's needed for every tool call
+                endpoint: raw_operation.endpoint().map(ToString::to_string),
                 inner: raw_operation,
                 operation_name,
+                operation_type: operation.operation_type,
+                projection,
+                connection,
+                dry_run,
+                persisted_queries: false,
             }))
         } else {
             Ok(None)
@@ -271,10 +394,14 @@ impl Operation {
     }
 }
 
+impl graphql::StreamingExecutable for Operation {}
+
 impl graphql::Executable for Operation {
     fn persisted_query_id(&self) -> Option<String> {
-        // TODO: id was being overridden, should we be returning? Should this be behind a flag? self.inner.persisted_query_id.clone()
-        None
+        self.inner
+            .persisted_query_id
+            .clone()
+            .filter(|_| self.persisted_queries)
     }
 
     fn operation(&self, _input: Value) -> Result<OperationDetails, McpError> {
@@ -285,6 +412,17 @@ impl graphql::Executable for Operation {
     }
 
     fn variables(&self, input_variables: Value) -> Result<Value, McpError> {
+        let input_variables = if self.connection.is_some() {
+            PageLimits::strip(input_variables)
+        } else {
+            input_variables
+        };
+        let input_variables = if self.dry_run {
+            dry_run::strip(input_variables)
+        } else {
+            input_variables
+        };
+
         if let Some(raw_variables) = self.inner.variables.as_ref() {
             let mut variables = match input_variables {
                 Value::Null => Ok(serde_json::Map::new()),
@@ -334,6 +472,18 @@ impl graphql::Executable for Operation {
             }
         }
     }
+
+    fn projection(&self) -> Option<&ResponseProjection> {
+        self.projection.as_ref()
+    }
+
+    fn idempotent(&self) -> bool {
+        self.operation_type == OperationType::Query
+    }
+
+    fn connection(&self) -> Option<&Connection> {
+        self.connection.as_ref()
+    }
 }
 
 #[allow(clippy::type_complexity)]
@@ -350,21 +500,9 @@ pub fn operation_defs(
             source_path_clone.unwrap_or_else(|| "operation.graphql".to_string()),
         )
         .map_err(|e| OperationError::GraphQLDocument(Box::new(e)))?;
-    let mut last_offset: Option<usize> = Some(0);
-    let mut operation_defs = document.definitions.clone().into_iter().filter_map(|def| {
-            let description = match def.location() {
-                Some(source_span) => {
-                    let description = last_offset
-                        .map(|start_offset| &source_text[start_offset..source_span.offset()]);
-                    last_offset = Some(source_span.end_offset());
-                    description
-                }
-                None => {
-                    last_offset = None;
-                    None
-                }
-            };
-
+    let mut operation_defs = with_leading_comments(&document, source_text)
+        .into_iter()
+        .filter_map(|(def, description)| {
             match def {
                 Definition::OperationDefinition(operation_def) => {
                     Some((operation_def, description))
@@ -390,29 +528,46 @@ pub fn operation_defs(
         (Some(op), None) => op,
     };
 
-    match operation.operation_type {
-        OperationType::Subscription => {
-            debug!(
-                "Skipping subscription operation {}",
-                operation_name(&operation, source_path)?
-            );
-            return Ok(None);
-        }
-        OperationType::Mutation => {
-            if !allow_mutations {
-                warn!(
-                    "Skipping mutation operation {}",
-                    operation_name(&operation, source_path)?
-                );
-                return Ok(None);
-            }
-        }
-        OperationType::Query => {}
+    if operation.operation_type == OperationType::Mutation && !allow_mutations {
+        warn!(
+            "Skipping mutation operation {}",
+            operation_name(&operation, source_path)?
+        );
+        return Ok(None);
     }
 
     Ok(Some((document, operation, comments.map(|c| c.to_string()))))
 }
 
+/// Pair each definition of a document with the comments leading it: the text between the end
+/// of the previous definition, or the start of the document, and the definition
+pub(super) fn with_leading_comments<'a>(
+    document: &Document,
+    source_text: &'a str,
+) -> Vec<(Definition, Option<&'a str>)> {
+    let mut last_offset: Option<usize> = Some(0);
+    document
+        .definitions
+        .iter()
+        .map(|def| {
+            let comments = match def.location() {
+                Some(source_span) => {
+                    let comments = last_offset.and_then(|start_offset| {
+                        source_text.get(start_offset..source_span.offset())
+                    });
+                    last_offset = Some(source_span.end_offset());
+                    comments
+                }
+                None => {
+                    last_offset = None;
+                    None
+                }
+            };
+            (def.clone(), comments)
+        })
+        .collect()
+}
+
 pub fn operation_name(
     operation: &Node<OperationDefinition>,
     source_path: Option<String>,
@@ -586,19 +741,74 @@ fn get_json_schema(
     schema
 }
 
+/// Build the schema of the structured content returned by an operation's tool, which is
+/// the GraphQL response (or, for subscriptions, the list of responses received)
+#[tracing::instrument(skip_all)]
+fn get_output_schema(
+    operation: &Node<OperationDefinition>,
+    document: &Document,
+    graphql_schema: &GraphqlSchema,
+    custom_scalar_map: Option<&CustomScalarMap>,
+) -> Schema {
+    let mut definitions = Map::new();
+    let data = match graphql_schema.root_operation(operation.operation_type) {
+        Some(root_type) => schema_walker::selection_set_to_schema(
+            &operation.selection_set,
+            root_type,
+            document,
+            graphql_schema,
+            &mut definitions,
+            custom_scalar_map,
+        ),
+        None => json_schema!({"type": "object"}),
+    };
+
+    let response = json_schema!({
+        "type": "object",
+        "properties": {
+            "data": {"oneOf": [data, {"type": "null"}]},
+            "errors": {"type": "array", "items": {"type": "object"}},
+            "extensions": {"type": "object"},
+        },
+    });
+    let mut schema = if operation.operation_type == OperationType::Subscription {
+        json_schema!({
+            "type": "object",
+            "properties": {
+                "events": {"type": "array", "items": response},
+            },
+        })
+    } else {
+        response
+    };
+
+    // Add the definitions to the overall schema if needed
+    if !definitions.is_empty() {
+        schema
+            .ensure_object()
+            .insert("definitions".to_string(), definitions.into());
+    }
+
+    schema
+}
+
 #[cfg(test)]
 mod tests {
     use std::{collections::HashMap, str::FromStr as _, sync::LazyLock};
 
-    use apollo_compiler::{Schema, parser::Parser, validation::Valid};
+    use apollo_compiler::{Schema, ast::OperationType, parser::Parser, validation::Valid};
+    use http::HeaderMap;
+    use mockito::Matcher;
     use rmcp::model::Tool;
-    use serde_json::Value;
+    use serde_json::{Value, json};
     use tracing_test::traced_test;
+    use url::Url;
 
     use crate::{
         custom_scalar_map::CustomScalarMap,
-        graphql::Executable as _,
-        operations::{MutationMode, Operation, RawOperation},
+        graphql::{Executable as _, Request},
+        operations::{MutationMode, Operation, RawOperation, ResponseProjection},
+        upstream::UpstreamClient,
     };
 
     // Example schema for tests
@@ -612,6 +822,7 @@ mod tests {
                     testOp: OpResponse
                 }
                 type Mutation {id: String }
+                type Subscription {id: String }
 
                 """
                 RealCustomScalar exists
@@ -681,6 +892,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -692,7 +905,7 @@ mod tests {
         .unwrap();
         let tool = Tool::from(operation);
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r#"
         Tool {
             name: "QueryName",
             title: None,
@@ -707,7 +920,44 @@ mod tests {
                     },
                 },
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -721,7 +971,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "#);
 
         let json = to_sorted_json!(tool.input_schema);
         insta::assert_snapshot!(serde_json::to_string_pretty(&json).unwrap(), @r#"
@@ -745,6 +995,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -756,7 +1008,7 @@ mod tests {
         .unwrap();
         let tool = Tool::from(operation);
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r#"
         Tool {
             name: "QueryName",
             title: None,
@@ -774,7 +1026,44 @@ mod tests {
                     String("id"),
                 ],
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -788,7 +1077,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "#);
         insta::assert_snapshot!(serde_json::to_string_pretty(&serde_json::json!(tool.input_schema)).unwrap(), @r###"
         {
           "type": "object",
@@ -813,6 +1102,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -824,7 +1115,7 @@ mod tests {
         .unwrap();
         let tool = Tool::from(operation);
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r#"
         Tool {
             name: "QueryName",
             title: None,
@@ -852,7 +1143,44 @@ mod tests {
                     String("id"),
                 ],
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -866,7 +1194,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "#);
         insta::assert_snapshot!(serde_json::to_string_pretty(&serde_json::json!(tool.input_schema)).unwrap(), @r###"
         {
           "type": "object",
@@ -901,6 +1229,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -912,7 +1242,7 @@ mod tests {
         .unwrap();
         let tool = Tool::from(operation);
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r#"
         Tool {
             name: "QueryName",
             title: None,
@@ -933,7 +1263,44 @@ mod tests {
                     String("id"),
                 ],
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -947,7 +1314,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "#);
         insta::assert_snapshot!(serde_json::to_string_pretty(&serde_json::json!(tool.input_schema)).unwrap(), @r###"
         {
           "type": "object",
@@ -975,6 +1342,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -986,7 +1355,7 @@ mod tests {
         .unwrap();
         let tool = Tool::from(operation);
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r#"
         Tool {
             name: "QueryName",
             title: None,
@@ -1011,7 +1380,44 @@ mod tests {
                     },
                 },
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -1025,7 +1431,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "#);
         insta::assert_snapshot!(serde_json::to_string_pretty(&serde_json::json!(tool.input_schema)).unwrap(), @r#"
         {
           "type": "object",
@@ -1057,6 +1463,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -1068,7 +1476,7 @@ mod tests {
         .unwrap();
         let tool = Tool::from(operation);
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r#"
         Tool {
             name: "QueryName",
             title: None,
@@ -1086,7 +1494,44 @@ mod tests {
                     },
                 },
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -1100,7 +1545,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "#);
         insta::assert_snapshot!(serde_json::to_string_pretty(&serde_json::json!(tool.input_schema)).unwrap(), @r#"
         {
           "type": "object",
@@ -1125,6 +1570,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -1136,7 +1583,7 @@ mod tests {
         .unwrap();
         let tool = Tool::from(operation);
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r#"
         Tool {
             name: "QueryName",
             title: None,
@@ -1171,7 +1618,44 @@ mod tests {
                     },
                 },
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -1185,7 +1669,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "#);
         insta::assert_snapshot!(serde_json::to_string_pretty(&serde_json::json!(tool.input_schema)).unwrap(), @r#"
         {
           "type": "object",
@@ -1227,6 +1711,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -1238,7 +1724,7 @@ mod tests {
         .unwrap();
         let tool = Tool::from(operation);
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r##"
         Tool {
             name: "QueryName",
             title: None,
@@ -1271,7 +1757,44 @@ mod tests {
                     },
                 },
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -1285,7 +1808,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "##);
     }
 
     #[test]
@@ -1297,6 +1820,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -1308,7 +1833,7 @@ mod tests {
         .unwrap();
         let tool = Tool::from(operation);
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r##"
         Tool {
             name: "QueryName",
             title: None,
@@ -1336,12 +1861,49 @@ mod tests {
                     },
                 },
             },
-            output_schema: None,
-            annotations: Some(
-                ToolAnnotations {
-                    title: None,
-                    read_only_hint: Some(
-                        true,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
+            annotations: Some(
+                ToolAnnotations {
+                    title: None,
+                    read_only_hint: Some(
+                        true,
                     ),
                     destructive_hint: None,
                     idempotent_hint: None,
@@ -1350,7 +1912,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "##);
     }
 
     #[test]
@@ -1362,6 +1924,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: Some("operation.graphql".to_string()),
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -1381,6 +1945,31 @@ mod tests {
         "#);
     }
 
+    #[test]
+    fn tool_name_prefix_is_applied() {
+        let operation = Operation::from_document(
+            RawOperation {
+                source_text: "query QueryName { id }".to_string(),
+                persisted_query_id: None,
+                headers: None,
+                variables: None,
+                source_path: Some("billing/invoices/query.graphql".to_string()),
+                tool_name_prefix: Some("billing_invoices".to_string()),
+                endpoint: None,
+            },
+            &SCHEMA,
+            None,
+            MutationMode::None,
+            false,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(operation.as_ref().name, "billing_invoices_QueryName");
+        assert_eq!(operation.operation_name(), "QueryName");
+    }
+
     #[test]
     #[traced_test]
     fn unnamed_operations_should_be_skipped() {
@@ -1391,6 +1980,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: Some("operation.graphql".to_string()),
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -1421,6 +2012,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: Some("operation.graphql".to_string()),
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -1448,6 +2041,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -1474,6 +2069,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -1495,7 +2092,7 @@ mod tests {
                 .ok_or("Expected warning about unknown type in logs".to_string())
         });
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r#"
         Tool {
             name: "QueryName",
             title: None,
@@ -1508,7 +2105,44 @@ mod tests {
                     "id": Object {},
                 },
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -1522,7 +2156,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "#);
     }
 
     #[test]
@@ -1535,6 +2169,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -1556,7 +2192,7 @@ mod tests {
                 .ok_or("Expected warning about custom scalar without map in logs".to_string())
         });
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r##"
         Tool {
             name: "QueryName",
             title: None,
@@ -1576,7 +2212,44 @@ mod tests {
                     },
                 },
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -1590,7 +2263,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "##);
     }
 
     #[test]
@@ -1603,6 +2276,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             Some(&CustomScalarMap::from_str("{}").unwrap()),
@@ -1628,7 +2303,7 @@ mod tests {
                 .ok_or("Expected warning about custom scalar missing in logs".to_string())
         });
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r##"
         Tool {
             name: "QueryName",
             title: None,
@@ -1648,7 +2323,44 @@ mod tests {
                     },
                 },
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -1662,7 +2374,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "##);
     }
 
     #[test]
@@ -1677,6 +2389,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             custom_scalar_map.ok().as_ref(),
@@ -1688,7 +2402,7 @@ mod tests {
         .unwrap();
         let tool = Tool::from(operation);
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r##"
         Tool {
             name: "QueryName",
             title: None,
@@ -1709,7 +2423,44 @@ mod tests {
                     },
                 },
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -1723,7 +2474,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "##);
     }
 
     #[test]
@@ -1862,6 +2613,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &schema,
             None,
@@ -1930,6 +2683,313 @@ mod tests {
         );
     }
 
+    #[test]
+    fn output_schema_from_selection_set() {
+        const SCHEMA: &str = r#"
+        type Query {
+          folder(id: ID!): Folder
+          search: [Result!]
+        }
+
+        type Folder {
+          id: ID!
+          "The name of the folder"
+          name: String
+          documents: [Document]!
+          status: Status!
+        }
+
+        type Document {
+          id: ID!
+          title: String
+        }
+
+        union Result = Folder | Document
+
+        enum Status {
+          OPEN
+          CLOSED
+        }
+        "#;
+        let schema = Schema::parse(SCHEMA, "schema.graphql")
+            .unwrap()
+            .validate()
+            .unwrap();
+
+        let operation = Operation::from_document(
+            RawOperation {
+                source_text: r###"
+            query GetFolder {
+              folder(id: "1") {
+                ...FolderFields
+                docs: documents { id }
+                docs: documents { title }
+                status
+              }
+              search {
+                __typename
+                ... on Document { title }
+              }
+            }
+
+            fragment FolderFields on Folder {
+              id
+              name
+            }
+            "###
+                .to_string(),
+                persisted_query_id: None,
+                headers: None,
+                variables: None,
+                source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
+            },
+            &schema,
+            None,
+            MutationMode::None,
+            false,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_snapshot!(serde_json::to_string_pretty(&operation.tool.output_schema).unwrap(), @r##"
+        {
+          "type": "object",
+          "properties": {
+            "data": {
+              "oneOf": [
+                {
+                  "type": "object",
+                  "properties": {
+                    "folder": {
+                      "oneOf": [
+                        {
+                          "type": "object",
+                          "properties": {
+                            "id": {
+                              "type": "string"
+                            },
+                            "name": {
+                              "oneOf": [
+                                {
+                                  "description": "The name of the folder",
+                                  "type": "string"
+                                },
+                                {
+                                  "type": "null"
+                                }
+                              ]
+                            },
+                            "docs": {
+                              "type": "array",
+                              "items": {
+                                "oneOf": [
+                                  {
+                                    "type": "object",
+                                    "properties": {
+                                      "id": {
+                                        "type": "string"
+                                      },
+                                      "title": {
+                                        "oneOf": [
+                                          {
+                                            "type": "string"
+                                          },
+                                          {
+                                            "type": "null"
+                                          }
+                                        ]
+                                      }
+                                    }
+                                  },
+                                  {
+                                    "type": "null"
+                                  }
+                                ]
+                              }
+                            },
+                            "status": {
+                              "$ref": "#/definitions/Status"
+                            }
+                          }
+                        },
+                        {
+                          "type": "null"
+                        }
+                      ]
+                    },
+                    "search": {
+                      "oneOf": [
+                        {
+                          "type": "array",
+                          "items": {
+                            "type": "object",
+                            "properties": {
+                              "__typename": {
+                                "type": "string"
+                              },
+                              "title": {
+                                "oneOf": [
+                                  {
+                                    "type": "string"
+                                  },
+                                  {
+                                    "type": "null"
+                                  }
+                                ]
+                              }
+                            }
+                          }
+                        },
+                        {
+                          "type": "null"
+                        }
+                      ]
+                    }
+                  }
+                },
+                {
+                  "type": "null"
+                }
+              ]
+            },
+            "errors": {
+              "type": "array",
+              "items": {
+                "type": "object"
+              }
+            },
+            "extensions": {
+              "type": "object"
+            }
+          },
+          "definitions": {
+            "Status": {
+              "description": "\n\nValues:\nOPEN: \nCLOSED: ",
+              "type": "string",
+              "enum": [
+                "OPEN",
+                "CLOSED"
+              ]
+            }
+          }
+        }
+        "##);
+    }
+
+    #[tokio::test]
+    async fn connection_pages_are_followed_and_merged() {
+        const SCHEMA: &str = r#"
+        type Query {
+          documents(first: Int, after: String): DocumentConnection!
+        }
+
+        type DocumentConnection {
+          edges: [DocumentEdge!]!
+          pageInfo: PageInfo!
+        }
+
+        type DocumentEdge {
+          cursor: String!
+          node: Document!
+        }
+
+        type PageInfo {
+          hasNextPage: Boolean!
+          endCursor: String
+        }
+
+        type Document {
+          id: ID!
+        }
+        "#;
+        let schema = Schema::parse(SCHEMA, "schema.graphql")
+            .unwrap()
+            .validate()
+            .unwrap();
+
+        let operation = Operation::from_document(
+            RawOperation {
+                source_text: "query Documents($after: String) { documents(first: 2, after: $after) { edges { cursor node { id } } pageInfo { hasNextPage endCursor } } }".to_string(),
+                persisted_query_id: None,
+                headers: None,
+                variables: None,
+                source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
+            },
+            &schema,
+            None,
+            MutationMode::None,
+            false,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+
+        let properties = operation.tool.input_schema.get("properties").unwrap();
+        assert!(properties.get("maxPages").is_some());
+        assert!(properties.get("maxItems").is_some());
+
+        let page = |ids: &[&str], has_next_page: bool| {
+            json!({ "data": { "documents": {
+                "edges": ids.iter().map(|id| json!({ "cursor": id, "node": { "id": id } })).collect::<Vec<_>>(),
+                "pageInfo": { "hasNextPage": has_next_page, "endCursor": ids.last() },
+            } } })
+            .to_string()
+        };
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("POST", "/")
+            .match_body(Matcher::Regex(r#""variables":\{\}"#.to_string()))
+            .with_body(page(&["a", "b"], true))
+            .expect(1)
+            .create_async()
+            .await;
+        let second = server
+            .mock("POST", "/")
+            .match_body(Matcher::PartialJson(
+                json!({ "variables": { "after": "b" } }),
+            ))
+            .with_body(page(&["c", "d"], true))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let endpoint = Url::parse(&server.url()).unwrap();
+        let result = operation
+            .execute(Request {
+                input: json!({ "maxItems": 3 }),
+                endpoint: &endpoint,
+                headers: HeaderMap::new(),
+                client: &UpstreamClient::default(),
+                error_mapping: &Default::default(),
+                token_manager: None,
+            })
+            .await
+            .unwrap();
+
+        first.assert_async().await;
+        second.assert_async().await;
+        assert_eq!(
+            result.structured_content.unwrap()["data"]["documents"],
+            json!({
+                "edges": [
+                    { "cursor": "a", "node": { "id": "a" } },
+                    { "cursor": "b", "node": { "id": "b" } },
+                    { "cursor": "c", "node": { "id": "c" } },
+                ],
+                "pageInfo": { "hasNextPage": true, "endCursor": "c" },
+            })
+        );
+        assert_eq!(
+            result.meta.unwrap().get("pagination"),
+            Some(&json!({ "pages": 2, "items": 3 }))
+        );
+    }
+
     #[test]
     fn tool_comment_description() {
         let operation = Operation::from_document(
@@ -1949,6 +3009,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -1965,6 +3027,83 @@ mod tests {
         );
     }
 
+    #[test]
+    fn tool_comment_projection_directives() {
+        let operation = Operation::from_document(
+            RawOperation {
+                source_text: r###"
+            # Overridden tool description
+            # @projection b.d.f
+            # @maxArrayLength 10
+            query GetABZ($state: String!) {
+              b {
+                d {
+                  f
+                }
+              }
+            }
+            "###
+                .to_string(),
+                persisted_query_id: None,
+                headers: None,
+                variables: None,
+                source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
+            },
+            &SCHEMA,
+            None,
+            MutationMode::None,
+            false,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_snapshot!(
+            operation.tool.description.as_deref().unwrap(),
+            @"Overridden tool description"
+        );
+        assert_eq!(
+            operation.projection,
+            Some(ResponseProjection {
+                paths: vec!["b.d.f".parse().unwrap()],
+                max_array_length: Some(10),
+            })
+        );
+    }
+
+    #[test]
+    fn tool_comment_invalid_projection_directive() {
+        let result = Operation::from_document(
+            RawOperation {
+                source_text: r###"
+            # @projection b[0]
+            query GetABZ($state: String!) {
+              id
+            }
+            "###
+                .to_string(),
+                persisted_query_id: None,
+                headers: None,
+                variables: None,
+                source_path: Some("get_abz.graphql".to_string()),
+                tool_name_prefix: None,
+                endpoint: None,
+            },
+            &SCHEMA,
+            None,
+            MutationMode::None,
+            false,
+            false,
+        );
+
+        insta::assert_snapshot!(
+            result.unwrap_err().to_string(),
+            @"get_abz.graphql: Invalid response projection: invalid JSON path 'b[0]'"
+        );
+    }
+
     #[test]
     fn tool_empty_comment_description() {
         let operation = Operation::from_document(
@@ -1982,6 +3121,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2007,6 +3148,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2036,6 +3179,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2070,6 +3215,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2100,6 +3247,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &Schema::parse(
                 r#"
@@ -2129,7 +3278,7 @@ mod tests {
         .unwrap()
         .unwrap();
 
-        insta::assert_debug_snapshot!(operation.tool, @r###"
+        insta::assert_debug_snapshot!(operation.tool, @r##"
         Tool {
             name: "Test",
             title: None,
@@ -2161,7 +3310,48 @@ mod tests {
                     },
                 },
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "field": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "description": String("the Query.field field"),
+                                                    "type": String("object"),
+                                                    "properties": Object {
+                                                        "id": Object {},
+                                                    },
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -2175,7 +3365,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "##);
     }
 
     #[test]
@@ -2190,6 +3380,8 @@ mod tests {
                     serde_json::Value::String("v".to_string()),
                 )])),
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2201,7 +3393,7 @@ mod tests {
         .unwrap();
         let tool = Tool::from(operation);
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r#"
         Tool {
             name: "QueryName",
             title: None,
@@ -2216,7 +3408,44 @@ mod tests {
                     },
                 },
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -2230,7 +3459,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "#);
     }
 
     #[test]
@@ -2243,6 +3472,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2277,6 +3508,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2315,6 +3548,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2352,6 +3587,8 @@ mod tests {
             headers: None,
             variables: None,
             source_path: None,
+            tool_name_prefix: None,
+            endpoint: None,
         };
         let operation =
             Operation::from_document(raw_op, &SCHEMA, None, MutationMode::None, false, false)
@@ -2372,6 +3609,8 @@ mod tests {
             headers: None,
             variables: None,
             source_path: None,
+            tool_name_prefix: None,
+            endpoint: None,
         };
         let operation =
             Operation::from_document(raw_op, &SCHEMA, None, MutationMode::Explicit, false, false)
@@ -2391,6 +3630,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2425,6 +3666,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2459,6 +3702,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2493,6 +3738,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2531,6 +3778,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2560,6 +3809,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2598,6 +3849,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2627,6 +3880,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2638,7 +3893,7 @@ mod tests {
         .unwrap();
         let tool = Tool::from(operation);
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r##"
         Tool {
             name: "QueryName",
             title: None,
@@ -2681,7 +3936,44 @@ mod tests {
                     },
                 },
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -2695,7 +3987,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "##);
 
         let json = to_sorted_json!(tool.input_schema);
         insta::assert_snapshot!(serde_json::to_string_pretty(&json).unwrap(), @r###"
@@ -2747,6 +4039,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2758,7 +4052,7 @@ mod tests {
         .unwrap();
         let tool = Tool::from(operation);
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r##"
         Tool {
             name: "QueryName",
             title: None,
@@ -2797,7 +4091,44 @@ mod tests {
                     },
                 },
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -2811,7 +4142,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "##);
 
         let json = to_sorted_json!(tool.input_schema);
         insta::assert_snapshot!(serde_json::to_string_pretty(&json).unwrap(), @r###"
@@ -2852,23 +4183,54 @@ mod tests {
 
     #[test]
     fn subscriptions() {
-        assert!(
-            Operation::from_document(
-                RawOperation {
-                    source_text: "subscription SubscriptionName { id }".to_string(),
-                    persisted_query_id: None,
-                    headers: None,
-                    variables: None,
-                    source_path: None,
-                },
-                &SCHEMA,
-                None,
-                MutationMode::None,
-                false,
-                false,
-            )
-            .unwrap()
-            .is_none()
+        let operation = Operation::from_document(
+            RawOperation {
+                source_text: "subscription SubscriptionName { id }".to_string(),
+                persisted_query_id: None,
+                headers: None,
+                variables: None,
+                source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
+            },
+            &SCHEMA,
+            None,
+            MutationMode::None,
+            false,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(operation.operation_type(), OperationType::Subscription);
+        assert_eq!(operation.tool.name, "SubscriptionName");
+        assert_eq!(
+            operation.tool.annotations.and_then(|a| a.read_only_hint),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn persisted_query_id_is_sent_when_enabled() {
+        let raw_operation = RawOperation::from((
+            "4f059505-fe13-4043-819a-461dd82dd5ed".to_string(),
+            "query QueryName { id }".to_string(),
+        ));
+        let operation = Operation::from_document(
+            raw_operation,
+            &SCHEMA,
+            None,
+            MutationMode::None,
+            false,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(operation.persisted_query_id(), None);
+        assert_eq!(
+            operation.with_persisted_queries(true).persisted_query_id(),
+            Some("4f059505-fe13-4043-819a-461dd82dd5ed".to_string())
         );
     }
 
@@ -2882,6 +4244,8 @@ mod tests {
                     headers: None,
                     variables: None,
                     source_path: None,
+                    tool_name_prefix: None,
+                    endpoint: None,
                 },
                 &SCHEMA,
                 None,
@@ -2904,6 +4268,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2914,7 +4280,7 @@ mod tests {
         .unwrap()
         .unwrap();
 
-        insta::assert_debug_snapshot!(operation, @r###"
+        insta::assert_debug_snapshot!(operation, @r#"
         Operation {
             tool: Tool {
                 name: "MutationName",
@@ -2924,9 +4290,51 @@ mod tests {
                 ),
                 input_schema: {
                     "type": String("object"),
-                    "properties": Object {},
+                    "properties": Object {
+                        "dryRun": Object {
+                            "type": String("boolean"),
+                            "description": String("Validate the input and return the request that would be sent, without running the mutation"),
+                        },
+                    },
                 },
-                output_schema: None,
+                output_schema: Some(
+                    {
+                        "type": String("object"),
+                        "properties": Object {
+                            "data": Object {
+                                "oneOf": Array [
+                                    Object {
+                                        "type": String("object"),
+                                        "properties": Object {
+                                            "id": Object {
+                                                "oneOf": Array [
+                                                    Object {
+                                                        "type": String("string"),
+                                                    },
+                                                    Object {
+                                                        "type": String("null"),
+                                                    },
+                                                ],
+                                            },
+                                        },
+                                    },
+                                    Object {
+                                        "type": String("null"),
+                                    },
+                                ],
+                            },
+                            "errors": Object {
+                                "type": String("array"),
+                                "items": Object {
+                                    "type": String("object"),
+                                },
+                            },
+                            "extensions": Object {
+                                "type": String("object"),
+                            },
+                        },
+                    },
+                ),
                 annotations: Some(
                     ToolAnnotations {
                         title: None,
@@ -2946,10 +4354,18 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             operation_name: "MutationName",
+            operation_type: Mutation,
+            endpoint: None,
+            projection: None,
+            connection: None,
+            dry_run: true,
+            persisted_queries: false,
         }
-        "###);
+        "#);
     }
 
     #[test]
@@ -2961,6 +4377,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -2971,7 +4389,7 @@ mod tests {
         .unwrap()
         .unwrap();
 
-        insta::assert_debug_snapshot!(operation, @r###"
+        insta::assert_debug_snapshot!(operation, @r#"
         Operation {
             tool: Tool {
                 name: "MutationName",
@@ -2981,9 +4399,51 @@ mod tests {
                 ),
                 input_schema: {
                     "type": String("object"),
-                    "properties": Object {},
+                    "properties": Object {
+                        "dryRun": Object {
+                            "type": String("boolean"),
+                            "description": String("Validate the input and return the request that would be sent, without running the mutation"),
+                        },
+                    },
                 },
-                output_schema: None,
+                output_schema: Some(
+                    {
+                        "type": String("object"),
+                        "properties": Object {
+                            "data": Object {
+                                "oneOf": Array [
+                                    Object {
+                                        "type": String("object"),
+                                        "properties": Object {
+                                            "id": Object {
+                                                "oneOf": Array [
+                                                    Object {
+                                                        "type": String("string"),
+                                                    },
+                                                    Object {
+                                                        "type": String("null"),
+                                                    },
+                                                ],
+                                            },
+                                        },
+                                    },
+                                    Object {
+                                        "type": String("null"),
+                                    },
+                                ],
+                            },
+                            "errors": Object {
+                                "type": String("array"),
+                                "items": Object {
+                                    "type": String("object"),
+                                },
+                            },
+                            "extensions": Object {
+                                "type": String("object"),
+                            },
+                        },
+                    },
+                ),
                 annotations: Some(
                     ToolAnnotations {
                         title: None,
@@ -3003,10 +4463,66 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             operation_name: "MutationName",
+            operation_type: Mutation,
+            endpoint: None,
+            projection: None,
+            connection: None,
+            dry_run: true,
+            persisted_queries: false,
         }
-        "###);
+        "#);
+    }
+
+    #[test]
+    fn mutation_dry_run_returns_the_request_body() {
+        let operation = Operation::from_document(
+            RawOperation {
+                source_text: "mutation MutationName($flag: Boolean!) { id @include(if: $flag) }"
+                    .to_string(),
+                persisted_query_id: None,
+                headers: None,
+                variables: None,
+                source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
+            },
+            &SCHEMA,
+            None,
+            MutationMode::All,
+            false,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+
+        let input = json!({ "flag": true, "dryRun": true });
+        assert!(operation.is_dry_run(&input).unwrap());
+        let result = operation.dry_run(input, &SCHEMA).unwrap();
+        let body = result.structured_content.unwrap();
+        assert_eq!(body["variables"], json!({ "flag": true }));
+        assert_eq!(body["operationName"], "MutationName");
+        assert_eq!(
+            body["query"],
+            "mutation MutationName($flag: Boolean!) { id @include(if: $flag) }"
+        );
+
+        assert!(
+            operation
+                .dry_run(json!({ "flag": "yes", "dryRun": true }), &SCHEMA)
+                .is_err()
+        );
+
+        let schema_without_mutations =
+            Schema::parse_and_validate("type Query { id: String }", "schema.graphql").unwrap();
+        assert!(
+            operation
+                .dry_run(json!({ "dryRun": true }), &schema_without_mutations)
+                .is_err()
+        );
     }
 
     #[test]
@@ -3018,6 +4534,8 @@ mod tests {
                 headers: None,
                 variables: None,
                 source_path: None,
+                tool_name_prefix: None,
+                endpoint: None,
             },
             &SCHEMA,
             None,
@@ -3029,7 +4547,7 @@ mod tests {
         .unwrap();
         let tool = Tool::from(operation);
 
-        insta::assert_debug_snapshot!(tool, @r###"
+        insta::assert_debug_snapshot!(tool, @r#"
         Tool {
             name: "QueryName",
             title: None,
@@ -3040,7 +4558,44 @@ mod tests {
                 "type": String("object"),
                 "properties": Object {},
             },
-            output_schema: None,
+            output_schema: Some(
+                {
+                    "type": String("object"),
+                    "properties": Object {
+                        "data": Object {
+                            "oneOf": Array [
+                                Object {
+                                    "type": String("object"),
+                                    "properties": Object {
+                                        "id": Object {
+                                            "oneOf": Array [
+                                                Object {
+                                                    "type": String("string"),
+                                                },
+                                                Object {
+                                                    "type": String("null"),
+                                                },
+                                            ],
+                                        },
+                                    },
+                                },
+                                Object {
+                                    "type": String("null"),
+                                },
+                            ],
+                        },
+                        "errors": Object {
+                            "type": String("array"),
+                            "items": Object {
+                                "type": String("object"),
+                            },
+                        },
+                        "extensions": Object {
+                            "type": String("object"),
+                        },
+                    },
+                },
+            ),
             annotations: Some(
                 ToolAnnotations {
                     title: None,
@@ -3054,7 +4609,7 @@ mod tests {
             ),
             icons: None,
         }
-        "###);
+        "#);
         insta::assert_snapshot!(serde_json::to_string_pretty(&serde_json::json!(tool.input_schema)).unwrap(), @r#"
         {
           "type": "object",
@@ -1,7 +1,7 @@
 use std::{
     collections::HashMap,
     fs,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
@@ -13,19 +13,21 @@ use apollo_mcp_registry::{
     uplink::persisted_queries::{ManifestSource, event::Event as ManifestEvent},
 };
 use futures::{Stream, StreamExt as _};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use tracing::warn;
 
 use crate::event::Event;
 
-use super::RawOperation;
+use super::{RawOperation, fragments::resolve_shared_fragments};
 
-const OPERATION_DOCUMENT_EXTENSION: &str = "graphql";
+/// The files loaded from directories by default
+pub const DEFAULT_INCLUDE: [&str; 2] = ["**/*.graphql", "**/*.gql"];
 
 /// The source of the operations exposed as MCP tools
 #[derive(Clone, Debug)]
 pub enum OperationSource {
-    /// GraphQL document files
-    Files(Vec<PathBuf>),
+    /// GraphQL document files, or directories of them
+    Files(Vec<PathBuf>, FileOptions),
 
     /// Persisted Query manifest
     Manifest(ManifestSource),
@@ -41,7 +43,9 @@ impl OperationSource {
     #[tracing::instrument(skip_all, fields(operation_source = ?self))]
     pub async fn into_stream(self) -> impl Stream<Item = Event> {
         match self {
-            OperationSource::Files(paths) => Self::stream_file_changes(paths).boxed(),
+            OperationSource::Files(paths, options) => {
+                Self::stream_file_changes(paths, options).boxed()
+            }
             OperationSource::Manifest(manifest_source) => manifest_source
                 .into_stream()
                 .await
@@ -75,68 +79,27 @@ impl OperationSource {
     }
 
     #[tracing::instrument]
-    fn stream_file_changes(paths: Vec<PathBuf>) -> impl Stream<Item = Event> {
+    fn stream_file_changes(paths: Vec<PathBuf>, options: FileOptions) -> impl Stream<Item = Event> {
         let path_count = paths.len();
         let state = Arc::new(Mutex::new(HashMap::<PathBuf, Vec<RawOperation>>::new()));
         futures::stream::select_all(paths.into_iter().map(|path| {
             let state = Arc::clone(&state);
+            let options = options.clone();
             files::watch(path.as_ref())
                 .filter_map(move |_| {
                     let path = path.clone();
                     let state = Arc::clone(&state);
+                    let options = options.clone();
                     async move {
-                        let mut operations = Vec::new();
-                        if path.is_dir() {
-                            // Handle a directory
-                            if let Ok(entries) = fs::read_dir(&path) {
-                                for entry in entries.flatten() {
-                                    let entry_path = entry.path();
-                                    if entry_path.extension().and_then(|e| e.to_str())
-                                        == Some(OPERATION_DOCUMENT_EXTENSION)
-                                    {
-                                        match fs::read_to_string(&entry_path) {
-                                            Ok(content) => {
-                                                // Be forgiving of empty files in the directory case.
-                                                // It likely means a new file was created in an editor,
-                                                // but the operation hasn't been written yet.
-                                                if !content.trim().is_empty() {
-                                                    operations.push(RawOperation::from((
-                                                        content,
-                                                        entry_path.to_str().map(|s| s.to_string()),
-                                                    )));
-                                                }
-                                            }
-                                            Err(e) => {
-                                                return Some(Event::OperationError(
-                                                    e,
-                                                    path.to_str().map(|s| s.to_string()),
-                                                ));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        } else {
-                            // Handle a single file
-                            match fs::read_to_string(&path) {
-                                Ok(content) => {
-                                    if !content.trim().is_empty() {
-                                        operations.push(RawOperation::from((
-                                            content,
-                                            path.to_str().map(|s| s.to_string()),
-                                        )));
-                                    } else {
-                                        warn!(?path, "Empty operation file");
-                                    }
-                                }
-                                Err(e) => {
-                                    return Some(Event::OperationError(
-                                        e,
-                                        path.to_str().map(|s| s.to_string()),
-                                    ));
-                                }
+                        let operations = match read_operations(&path, &options) {
+                            Ok(operations) => operations,
+                            Err(e) => {
+                                return Some(Event::OperationError(
+                                    e,
+                                    path.to_str().map(|s| s.to_string()),
+                                ));
                             }
-                        }
+                        };
                         match state.lock() {
                             Ok(mut state) => {
                                 state.insert(path.clone(), operations);
@@ -145,9 +108,11 @@ impl OperationSource {
                                 // loaded, then send a single event with the operations for all
                                 // paths.
                                 if state.len() == path_count {
-                                    Some(Event::OperationsUpdated(
+                                    // Operations are resolved against the fragments of all
+                                    // paths, so fragment changes reload every dependent operation
+                                    Some(Event::OperationsUpdated(resolve_shared_fragments(
                                         state.values().flatten().cloned().collect::<Vec<_>>(),
-                                    ))
+                                    )))
                                 } else {
                                     None
                                 }
@@ -165,6 +130,149 @@ impl OperationSource {
     }
 }
 
+/// Read the operations in a GraphQL document file, or in the matching files of a directory
+/// and its subdirectories
+pub(super) fn read_operations(
+    path: &Path,
+    options: &FileOptions,
+) -> std::io::Result<Vec<RawOperation>> {
+    let mut operations = Vec::new();
+    if path.is_dir() {
+        // Handle a directory
+        let mut entry_paths = Vec::new();
+        options.collect_files(path, path, &mut entry_paths);
+        entry_paths.sort();
+        for entry_path in entry_paths {
+            let content = fs::read_to_string(&entry_path)?;
+            // Be forgiving of empty files in the directory case.
+            // It likely means a new file was created in an editor,
+            // but the operation hasn't been written yet.
+            if !content.trim().is_empty() {
+                operations.push(
+                    RawOperation::from((content, entry_path.to_str().map(|s| s.to_string())))
+                        .with_tool_name_prefix(options.tool_name_prefix(path, &entry_path))
+                        .with_endpoint(options.endpoint.clone()),
+                );
+            }
+        }
+    } else {
+        // Handle a single file
+        let content = fs::read_to_string(path)?;
+        if !content.trim().is_empty() {
+            operations.push(
+                RawOperation::from((content, path.to_str().map(|s| s.to_string())))
+                    .with_endpoint(options.endpoint.clone()),
+            );
+        } else {
+            warn!(?path, "Empty operation file");
+        }
+    }
+    Ok(operations)
+}
+
+/// Options for loading operations from the files in directories
+#[derive(Clone, Debug)]
+pub struct FileOptions {
+    /// The files to load, relative to the directory
+    include: GlobSet,
+
+    /// The files to skip, relative to the directory
+    exclude: GlobSet,
+
+    /// Whether to prefix tool names with the path of their file's subdirectory
+    prefix_tool_names: bool,
+
+    /// The named endpoint targeted by the operations in the files
+    endpoint: Option<String>,
+}
+
+impl FileOptions {
+    pub fn new(
+        include: &[String],
+        exclude: &[String],
+        prefix_tool_names: bool,
+    ) -> Result<Self, globset::Error> {
+        Ok(Self {
+            include: glob_set(include)?,
+            exclude: glob_set(exclude)?,
+            prefix_tool_names,
+            endpoint: None,
+        })
+    }
+
+    /// Target a named endpoint with the operations in the files, unless they declare their own
+    pub fn with_endpoint(mut self, endpoint: Option<String>) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    /// Collect the files to load in a directory and its subdirectories
+    fn collect_files(&self, root: &Path, dir: &Path, files: &mut Vec<PathBuf>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let entry_path = entry.path();
+            if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                self.collect_files(root, &entry_path, files);
+            } else if let Ok(relative_path) = entry_path.strip_prefix(root)
+                && self.include.is_match(relative_path)
+                && !self.exclude.is_match(relative_path)
+            {
+                files.push(entry_path);
+            }
+        }
+    }
+
+    /// The prefix for the tool names of operations in a file, made from the path of its
+    /// subdirectory with characters that aren't allowed in tool names replaced by `_`
+    fn tool_name_prefix(&self, root: &Path, file: &Path) -> Option<String> {
+        if !self.prefix_tool_names {
+            return None;
+        }
+        let directory = file.strip_prefix(root).ok()?.parent()?;
+        let prefix = directory
+            .components()
+            .map(|component| {
+                component
+                    .as_os_str()
+                    .to_string_lossy()
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() || c == '-' {
+                            c
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("_");
+        (!prefix.is_empty()).then_some(prefix)
+    }
+}
+
+impl Default for FileOptions {
+    fn default() -> Self {
+        Self {
+            include: glob_set(&DEFAULT_INCLUDE.map(String::from))
+                .unwrap_or_else(|_| GlobSet::empty()),
+            exclude: GlobSet::empty(),
+            prefix_tool_names: false,
+            endpoint: None,
+        }
+    }
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet, globset::Error> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(GlobBuilder::new(pattern).literal_separator(true).build()?);
+    }
+    builder.build()
+}
+
 impl From<ManifestSource> for OperationSource {
     fn from(manifest_source: ManifestSource) -> Self {
         OperationSource::Manifest(manifest_source)
@@ -173,6 +281,97 @@ impl From<ManifestSource> for OperationSource {
 
 impl From<Vec<PathBuf>> for OperationSource {
     fn from(paths: Vec<PathBuf>) -> Self {
-        OperationSource::Files(paths)
+        OperationSource::Files(paths, FileOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn loaded(root: &Path, options: &FileOptions) -> Vec<(String, Option<String>)> {
+        read_operations(root, options)
+            .unwrap()
+            .into_iter()
+            .map(|operation| {
+                let path = operation.source_path.unwrap();
+                let relative = Path::new(&path).strip_prefix(root).unwrap();
+                (
+                    relative.to_string_lossy().into_owned(),
+                    operation.tool_name_prefix,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_reads_directories_recursively() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "root.graphql", "query Root { id }");
+        write(dir.path(), "billing/invoices/list.gql", "query List { id }");
+        write(dir.path(), "billing/README.md", "# Billing");
+
+        assert_eq!(
+            loaded(dir.path(), &FileOptions::default()),
+            vec![
+                ("billing/invoices/list.gql".to_string(), None),
+                ("root.graphql".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_applies_include_and_exclude_globs() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "root.graphql", "query Root { id }");
+        write(dir.path(), "billing/list.graphql", "query List { id }");
+        write(
+            dir.path(),
+            "billing/drafts/draft.graphql",
+            "query Draft { id }",
+        );
+        write(dir.path(), "users/get.graphql", "query Get { id }");
+
+        let options = FileOptions::new(
+            &["billing/**/*.graphql".to_string()],
+            &["**/drafts/**".to_string()],
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            loaded(dir.path(), &options),
+            vec![("billing/list.graphql".to_string(), None)]
+        );
+    }
+
+    #[test]
+    fn it_prefixes_tool_names_with_the_relative_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "root.graphql", "query Root { id }");
+        write(
+            dir.path(),
+            "billing/sub dir/list.graphql",
+            "query List { id }",
+        );
+
+        let options = FileOptions::new(&DEFAULT_INCLUDE.map(String::from), &[], true).unwrap();
+
+        assert_eq!(
+            loaded(dir.path(), &options),
+            vec![
+                (
+                    "billing/sub dir/list.graphql".to_string(),
+                    Some("billing_sub_dir".to_string())
+                ),
+                ("root.graphql".to_string(), None),
+            ]
+        );
     }
 }
@@ -0,0 +1,443 @@
+//! Response projections
+//!
+//! A projection limits the GraphQL response returned by an operation tool to a set of
+//! JSON paths within `data`, and caps the length of every array in the result. It can be
+//! declared with comment directives in the operation file:
+//!
+//! ```graphql
+//! # Get the documents in a folder
+//! # @projection documents[*].id, documents[*].title
+//! # @maxArrayLength 20
+//! query GetDocuments($folder: ID!) { ... }
+//! ```
+//!
+//! or in the `projections` section of the config file, keyed by operation name.
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+const PROJECTION_DIRECTIVE: &str = "@projection";
+const MAX_ARRAY_LENGTH_DIRECTIVE: &str = "@maxArrayLength";
+
+/// A projection of the GraphQL response returned by an operation tool
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct ResponseProjection {
+    /// JSON paths within `data` to keep, such as `documents[*].title`. All of `data` is kept when empty.
+    pub paths: Vec<JsonPath>,
+
+    /// The maximum number of items to keep in any array of the response
+    pub max_array_length: Option<usize>,
+}
+
+/// A path to a value within a JSON document, made of `.`-separated field names and
+/// `[*]` to select every item of an array, optionally starting with `$`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+#[schemars(with = "String")]
+pub struct JsonPath(Vec<Segment>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Each,
+}
+
+/// An array that was shortened by a projection
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Truncation {
+    /// The location of the array in the response
+    pub path: String,
+
+    /// The number of items in the array before it was shortened
+    pub length: usize,
+}
+
+impl ResponseProjection {
+    /// Remove projection directives from the comments on an operation, returning the
+    /// remaining comments and the projection they declare, if any
+    pub(crate) fn from_comments(
+        comments: Option<String>,
+    ) -> Result<(Option<String>, Option<Self>), String> {
+        let Some(comments) = comments else {
+            return Ok((None, None));
+        };
+
+        let mut projection: Option<Self> = None;
+        let mut remaining = Vec::new();
+        for line in comments.lines() {
+            let content = line
+                .trim_start_matches(|c: char| c.is_whitespace() || c == ',')
+                .strip_prefix('#')
+                .map(str::trim);
+            if let Some(paths) = content.and_then(|c| directive_arguments(c, PROJECTION_DIRECTIVE))
+            {
+                let paths = paths
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|path| !path.is_empty())
+                    .map(JsonPath::from_str)
+                    .collect::<Result<Vec<_>, _>>()?;
+                projection.get_or_insert_default().paths.extend(paths);
+            } else if let Some(length) =
+                content.and_then(|c| directive_arguments(c, MAX_ARRAY_LENGTH_DIRECTIVE))
+            {
+                let length = length.trim().parse().map_err(|_| {
+                    format!("{MAX_ARRAY_LENGTH_DIRECTIVE} expects a number, got '{length}'")
+                })?;
+                projection.get_or_insert_default().max_array_length = Some(length);
+            } else {
+                remaining.push(line);
+            }
+        }
+
+        Ok((Some(remaining.join("\n")), projection))
+    }
+
+    /// Apply the projection to a GraphQL response, returning the arrays that were shortened
+    pub(crate) fn apply(&self, response: &mut Value) -> Vec<Truncation> {
+        let Some(data) = response.get_mut("data").filter(|data| data.is_object()) else {
+            return Vec::new();
+        };
+
+        if !self.paths.is_empty() {
+            let mut projected = Value::Object(Map::new());
+            for path in &self.paths {
+                if let Some(selected) = select(data, &path.0) {
+                    merge(&mut projected, selected);
+                }
+            }
+            *data = projected;
+        }
+
+        let mut truncations = Vec::new();
+        if let Some(max_length) = self.max_array_length {
+            truncate(data, max_length, "data".to_string(), &mut truncations);
+        }
+        truncations
+    }
+}
+
+/// The arguments of a directive in the content of a comment, if the comment starts with the
+/// whole directive name
+fn directive_arguments<'a>(content: &'a str, directive: &str) -> Option<&'a str> {
+    content
+        .strip_prefix(directive)
+        .filter(|arguments| arguments.is_empty() || arguments.starts_with(char::is_whitespace))
+}
+
+impl Truncation {
+    /// Describe truncated arrays for the `meta` of a tool result
+    pub(crate) fn meta(truncations: &[Truncation]) -> Value {
+        json!({ "truncated": truncations })
+    }
+}
+
+/// Select the value at a path, keeping the structure of the objects and arrays leading to it
+fn select(value: &Value, segments: &[Segment]) -> Option<Value> {
+    if value.is_null() {
+        return Some(Value::Null);
+    }
+    match segments.split_first() {
+        None => Some(value.clone()),
+        Some((Segment::Field(name), rest)) => {
+            let selected = select(value.as_object()?.get(name)?, rest)?;
+            Some(Value::Object(Map::from_iter([(name.clone(), selected)])))
+        }
+        Some((Segment::Each, rest)) => Some(Value::Array(
+            value
+                .as_array()?
+                .iter()
+                // Unmatched items are kept as null so arrays from different paths still line up
+                .map(|item| select(item, rest).unwrap_or(Value::Null))
+                .collect(),
+        )),
+    }
+}
+
+fn merge(target: &mut Value, source: Value) {
+    match (target, source) {
+        (Value::Object(target), Value::Object(source)) => {
+            for (key, value) in source {
+                match target.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(target), Value::Array(source)) => {
+            for (target, source) in target.iter_mut().zip(source) {
+                merge(target, source);
+            }
+        }
+        (target, Value::Null) if !target.is_null() => {}
+        (target, source) => *target = source,
+    }
+}
+
+fn truncate(value: &mut Value, max_length: usize, path: String, truncations: &mut Vec<Truncation>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                truncate(value, max_length, format!("{path}.{key}"), truncations);
+            }
+        }
+        Value::Array(array) => {
+            if array.len() > max_length {
+                truncations.push(Truncation {
+                    path: path.clone(),
+                    length: array.len(),
+                });
+                array.truncate(max_length);
+            }
+            for (index, value) in array.iter_mut().enumerate() {
+                truncate(value, max_length, format!("{path}[{index}]"), truncations);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl JsonPath {
+    /// Replace every value at the path
+    pub(crate) fn replace(&self, value: &mut Value, replacement: &Value) {
+        replace(value, &self.0, replacement);
+    }
+}
+
+/// Replace the values at a path, leaving values that don't match it untouched
+fn replace(value: &mut Value, segments: &[Segment], replacement: &Value) {
+    match segments.split_first() {
+        None => *value = replacement.clone(),
+        Some((Segment::Field(name), rest)) => {
+            if let Some(value) = value
+                .as_object_mut()
+                .and_then(|object| object.get_mut(name))
+            {
+                replace(value, rest, replacement);
+            }
+        }
+        Some((Segment::Each, rest)) => {
+            if let Some(items) = value.as_array_mut() {
+                for item in items {
+                    replace(item, rest, replacement);
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = String;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid JSON path '{path}'");
+        let rest = match path.strip_prefix('$') {
+            Some(rest) => rest.strip_prefix('.').unwrap_or(rest),
+            None => path,
+        };
+        if rest.is_empty() {
+            return Ok(Self(Vec::new()));
+        }
+
+        let mut segments = Vec::new();
+        for part in rest.split('.') {
+            let (name, mut brackets) = part.split_at(part.find('[').unwrap_or(part.len()));
+            if name.is_empty() && brackets.is_empty() {
+                return Err(invalid());
+            }
+            if !name.is_empty() {
+                segments.push(Segment::Field(name.to_string()));
+            }
+            while !brackets.is_empty() {
+                brackets = brackets.strip_prefix("[*]").ok_or_else(invalid)?;
+                segments.push(Segment::Each);
+            }
+        }
+        Ok(Self(segments))
+    }
+}
+
+impl TryFrom<String> for JsonPath {
+    type Error = String;
+
+    fn try_from(path: String) -> Result<Self, Self::Error> {
+        path.parse()
+    }
+}
+
+impl Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("$")?;
+        for segment in &self.0 {
+            match segment {
+                Segment::Field(name) => write!(f, ".{name}")?,
+                Segment::Each => f.write_str("[*]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<JsonPath> for String {
+    fn from(path: JsonPath) -> Self {
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn projection(paths: &[&str], max_array_length: Option<usize>) -> ResponseProjection {
+        ResponseProjection {
+            paths: paths.iter().map(|path| path.parse().unwrap()).collect(),
+            max_array_length,
+        }
+    }
+
+    #[rstest]
+    #[case("documents", "$.documents")]
+    #[case("$.documents[*].title", "$.documents[*].title")]
+    #[case("$documents[*][*]", "$.documents[*][*]")]
+    #[case("$", "$")]
+    fn it_parses_paths(#[case] path: &str, #[case] expected: &str) {
+        assert_eq!(path.parse::<JsonPath>().unwrap().to_string(), expected);
+    }
+
+    #[rstest]
+    #[case("documents..title")]
+    #[case("documents[0]")]
+    #[case("documents[*")]
+    fn it_rejects_invalid_paths(#[case] path: &str) {
+        assert!(path.parse::<JsonPath>().is_err());
+    }
+
+    #[test]
+    fn it_keeps_only_selected_paths() {
+        let mut response = json!({
+            "data": {
+                "folder": { "id": "1", "name": "Reports" },
+                "documents": [
+                    { "id": "a", "title": "Q1", "body": "..." },
+                    { "id": "b", "title": "Q2", "body": "..." },
+                    null,
+                ],
+            },
+            "errors": [{ "message": "partial" }],
+        });
+
+        let truncations =
+            projection(&["documents[*].id", "$.documents[*].title"], None).apply(&mut response);
+
+        assert!(truncations.is_empty());
+        assert_eq!(
+            response,
+            json!({
+                "data": {
+                    "documents": [
+                        { "id": "a", "title": "Q1" },
+                        { "id": "b", "title": "Q2" },
+                        null,
+                    ],
+                },
+                "errors": [{ "message": "partial" }],
+            })
+        );
+    }
+
+    #[test]
+    fn it_truncates_arrays() {
+        let mut response = json!({
+            "data": {
+                "documents": [
+                    { "tags": ["a", "b", "c"] },
+                    { "tags": [] },
+                    { "tags": ["d"] },
+                ],
+            },
+        });
+
+        let truncations = projection(&[], Some(2)).apply(&mut response);
+
+        assert_eq!(
+            response,
+            json!({ "data": { "documents": [{ "tags": ["a", "b"] }, { "tags": [] }] } })
+        );
+        assert_eq!(
+            truncations,
+            vec![
+                Truncation {
+                    path: "data.documents".to_string(),
+                    length: 3,
+                },
+                Truncation {
+                    path: "data.documents[0].tags".to_string(),
+                    length: 3,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_ignores_responses_without_data() {
+        let mut response = json!({ "data": null, "errors": [{ "message": "failed" }] });
+
+        projection(&["documents"], Some(1)).apply(&mut response);
+
+        assert_eq!(
+            response,
+            json!({ "data": null, "errors": [{ "message": "failed" }] })
+        );
+    }
+
+    #[test]
+    fn it_extracts_directives_from_comments() {
+        let comments = "# Get documents\n# @projection documents[*].id, documents[*].title\n  # @maxArrayLength 5\n# in a folder\n";
+
+        let (comments, projection) =
+            ResponseProjection::from_comments(Some(comments.to_string())).unwrap();
+
+        assert_eq!(comments, Some("# Get documents\n# in a folder".to_string()));
+        assert_eq!(
+            projection,
+            Some(self::projection(
+                &["documents[*].id", "documents[*].title"],
+                Some(5)
+            ))
+        );
+    }
+
+    #[test]
+    fn it_ignores_comments_starting_like_directives() {
+        let comments = "# @projectionNotes are below
+# @maxArrayLengths vary";
+
+        let (comments, projection) =
+            ResponseProjection::from_comments(Some(comments.to_string())).unwrap();
+
+        assert_eq!(
+            comments,
+            Some("# @projectionNotes are below\n# @maxArrayLengths vary".to_string())
+        );
+        assert_eq!(projection, None);
+    }
+
+    #[test]
+    fn it_rejects_invalid_directives() {
+        assert!(
+            ResponseProjection::from_comments(Some("# @maxArrayLength many".to_string())).is_err()
+        );
+        assert!(
+            ResponseProjection::from_comments(Some("# @projection documents[0]".to_string()))
+                .is_err()
+        );
+    }
+}
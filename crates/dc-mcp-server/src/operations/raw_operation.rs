@@ -7,9 +7,16 @@ use apollo_mcp_registry::platform_api::operation_collections::{
 use http::{HeaderMap, HeaderName, HeaderValue};
 use serde_json::Value;
 
-use crate::{custom_scalar_map::CustomScalarMap, errors::OperationError};
+use crate::{
+    custom_scalar_map::CustomScalarMap,
+    errors::OperationError,
+    redaction::{REDACTED, redactor},
+};
 
-use super::{MutationMode, operation::Operation};
+use super::{
+    MutationMode, ResponseProjection, TargetEndpoint, endpoint::endpoint_directive,
+    operation::Operation,
+};
 
 #[derive(Debug, Clone)]
 pub struct RawOperation {
@@ -18,25 +25,71 @@ pub struct RawOperation {
     pub(super) headers: Option<HeaderMap<HeaderValue>>,
     pub(super) variables: Option<HashMap<String, Value>>,
     pub(super) source_path: Option<String>,
+    pub(super) tool_name_prefix: Option<String>,
+    pub(super) endpoint: Option<String>,
 }
 
 impl RawOperation {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn into_operation(
         self,
         schema: &Valid<apollo_compiler::Schema>,
         custom_scalars: Option<&CustomScalarMap>,
         mutation_mode: MutationMode,
+        persisted_queries: bool,
         disable_type_description: bool,
         disable_schema_description: bool,
+        projections: &HashMap<String, ResponseProjection>,
+        endpoints: &HashMap<String, TargetEndpoint>,
     ) -> Result<Option<Operation>, OperationError> {
-        Operation::from_document(
+        // Operations targeting an endpoint with its own schema are validated against it
+        let schema = match self.endpoint() {
+            Some(name) => match endpoints.get(name) {
+                Some(endpoint) => endpoint.schema.as_ref().unwrap_or(schema),
+                None => {
+                    return Err(OperationError::UnknownEndpoint {
+                        endpoint: name.to_string(),
+                        source_path: self.source_path,
+                    });
+                }
+            },
+            None => schema,
+        };
+        let operation = Operation::from_document(
             self,
             schema,
             custom_scalars,
             mutation_mode,
             disable_type_description,
             disable_schema_description,
-        )
+        )?;
+
+        // Projections from config take precedence over those declared in the operation
+        Ok(operation.map(|operation| {
+            let operation = operation.with_persisted_queries(persisted_queries);
+            match projections.get(operation.operation_name()) {
+                Some(projection) => operation.with_projection(projection.clone()),
+                None => operation,
+            }
+        }))
+    }
+
+    /// The name of the endpoint the operation targets, declared with a comment directive or
+    /// by its source
+    pub(crate) fn endpoint(&self) -> Option<&str> {
+        endpoint_directive(&self.source_text).or(self.endpoint.as_deref())
+    }
+
+    /// Target a named endpoint, unless the operation declares its own
+    pub(super) fn with_endpoint(mut self, endpoint: Option<String>) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    /// Prefix the name of the operation's tool
+    pub(super) fn with_tool_name_prefix(mut self, prefix: Option<String>) -> Self {
+        self.tool_name_prefix = prefix;
+        self
     }
 }
 
@@ -48,6 +101,8 @@ impl From<(String, Option<String>)> for RawOperation {
             headers: None,
             variables: None,
             source_path,
+            tool_name_prefix: None,
+            endpoint: None,
         }
     }
 }
@@ -60,6 +115,8 @@ impl From<(String, String)> for RawOperation {
             headers: None,
             variables: None,
             source_path: None,
+            tool_name_prefix: None,
+            endpoint: None,
         }
     }
 }
@@ -100,6 +157,8 @@ impl TryFrom<&OperationData> for RawOperation {
             headers,
             variables,
             source_path: None,
+            tool_name_prefix: None,
+            endpoint: None,
         })
     }
 }
@@ -123,12 +182,17 @@ impl serde::Serialize for RawOperation {
             state.serialize_field("variables", variables)?;
         }
         if let Some(ref headers) = self.headers {
+            let redactor = redactor();
             state.serialize_field(
                 "headers",
                 headers
                     .iter()
                     .map(|(name, value)| {
-                        format!("{}: {}", name, value.to_str().unwrap_or_default())
+                        if redactor.is_sensitive_header(name.as_str()) {
+                            format!("{name}: {REDACTED}")
+                        } else {
+                            format!("{}: {}", name, value.to_str().unwrap_or_default())
+                        }
                     })
                     .collect::<Vec<_>>()
                     .join("\n")
@@ -138,6 +202,12 @@ impl serde::Serialize for RawOperation {
         if let Some(ref path) = self.source_path {
             state.serialize_field("source_path", path)?;
         }
+        if let Some(ref prefix) = self.tool_name_prefix {
+            state.serialize_field("tool_name_prefix", prefix)?;
+        }
+        if let Some(endpoint) = self.endpoint() {
+            state.serialize_field("endpoint", endpoint)?;
+        }
 
         state.end()
     }
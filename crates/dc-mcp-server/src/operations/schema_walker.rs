@@ -3,13 +3,17 @@
 //! The types in this module generate JSON schemas for GraphQL types by walking
 //! the types recursively.
 
-use apollo_compiler::{Schema as GraphQLSchema, ast::Type};
+use apollo_compiler::{
+    Name as GraphQLName, Schema as GraphQLSchema,
+    ast::{Document, Selection, Type},
+};
 use schemars::Schema;
 use serde_json::{Map, Value};
 
 use crate::custom_scalar_map::CustomScalarMap;
 
 mod name;
+mod selection;
 mod r#type;
 
 /// Convert a GraphQL type into a JSON Schema.
@@ -33,6 +37,29 @@ pub fn type_to_schema(
     .into()
 }
 
+/// Convert a GraphQL selection set into a JSON Schema describing the selected
+/// fields of the response.
+///
+/// Note: Like [type_to_schema], this is recursive over nested selection sets.
+pub fn selection_set_to_schema(
+    selection_set: &[Selection],
+    parent_type: &GraphQLName,
+    document: &Document,
+    schema: &GraphQLSchema,
+    definitions: &mut Map<String, Value>,
+    custom_scalar_map: Option<&CustomScalarMap>,
+) -> Schema {
+    selection::SelectionSet {
+        cache: definitions,
+        custom_scalar_map,
+        document,
+        parent_type,
+        schema,
+        selection_set,
+    }
+    .into()
+}
+
 /// Modifies a schema to include an optional description
 fn with_desc(mut schema: Schema, description: &Option<String>) -> Schema {
     if let Some(desc) = description {
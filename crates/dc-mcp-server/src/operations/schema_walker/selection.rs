@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+
+use apollo_compiler::{
+    Name as GraphQLName, Node, Schema as GraphQLSchema,
+    ast::{Definition, Document, Field, Selection, Type as GraphQLType},
+};
+use schemars::{Schema as JSONSchema, json_schema};
+use serde_json::{Map, Value};
+use tracing::warn;
+
+use crate::custom_scalar_map::CustomScalarMap;
+
+use super::{r#type::Type, with_desc};
+
+/// A GraphQL Selection Set Walker
+pub(super) struct SelectionSet<'a> {
+    /// The definition cache which contains full schemas for nested types
+    pub(super) cache: &'a mut Map<String, Value>,
+
+    /// Custom scalar map for supplementing information from the GraphQL schema
+    pub(super) custom_scalar_map: Option<&'a CustomScalarMap>,
+
+    /// The document containing the selection set, used to resolve fragment spreads
+    pub(super) document: &'a Document,
+
+    /// The name of the type the selections are made on
+    pub(super) parent_type: &'a GraphQLName,
+
+    /// The original GraphQL schema with all type information
+    pub(super) schema: &'a GraphQLSchema,
+
+    /// The actual selections to translate into a JSON schema
+    pub(super) selection_set: &'a [Selection],
+}
+
+impl From<SelectionSet<'_>> for JSONSchema {
+    fn from(
+        SelectionSet {
+            cache,
+            custom_scalar_map,
+            document,
+            parent_type,
+            schema,
+            selection_set,
+        }: SelectionSet,
+    ) -> Self {
+        Walker {
+            cache,
+            custom_scalar_map,
+            document,
+            schema,
+            fragments: HashSet::new(),
+        }
+        .object(parent_type, selection_set)
+    }
+}
+
+struct Walker<'a> {
+    cache: &'a mut Map<String, Value>,
+    custom_scalar_map: Option<&'a CustomScalarMap>,
+    document: &'a Document,
+    schema: &'a GraphQLSchema,
+
+    /// The fragments currently being expanded, to guard against cycles
+    fragments: HashSet<GraphQLName>,
+}
+
+impl Walker<'_> {
+    fn object(&mut self, parent_type: &str, selection_set: &[Selection]) -> JSONSchema {
+        let mut properties = Map::new();
+        self.collect(parent_type, selection_set, &mut properties);
+
+        // Fields are not marked as required, since a projection may remove them from the response
+        json_schema!({
+            "type": "object",
+            "properties": properties,
+        })
+    }
+
+    /// Collect the schemas of all fields in a selection set, keyed by their response name
+    fn collect(
+        &mut self,
+        parent_type: &str,
+        selection_set: &[Selection],
+        properties: &mut Map<String, Value>,
+    ) {
+        for selection in selection_set {
+            match selection {
+                Selection::Field(field) => {
+                    let response_name = field.alias.as_ref().unwrap_or(&field.name).to_string();
+                    let field_schema = self.field(parent_type, field).into();
+                    match properties.get_mut(&response_name) {
+                        Some(existing) => merge(existing, field_schema),
+                        None => {
+                            properties.insert(response_name, field_schema);
+                        }
+                    }
+                }
+                Selection::InlineFragment(fragment) => {
+                    let type_condition = fragment
+                        .type_condition
+                        .as_ref()
+                        .map(GraphQLName::as_str)
+                        .unwrap_or(parent_type);
+                    self.collect(type_condition, &fragment.selection_set, properties);
+                }
+                Selection::FragmentSpread(spread) => {
+                    let document = self.document;
+                    let Some(fragment) =
+                        document
+                            .definitions
+                            .iter()
+                            .find_map(|definition| match definition {
+                                Definition::FragmentDefinition(fragment)
+                                    if fragment.name == spread.fragment_name =>
+                                {
+                                    Some(fragment)
+                                }
+                                _ => None,
+                            })
+                    else {
+                        warn!(name=?spread.fragment_name, "Fragment not found in document");
+                        continue;
+                    };
+
+                    if self.fragments.insert(fragment.name.clone()) {
+                        self.collect(
+                            &fragment.type_condition,
+                            &fragment.selection_set,
+                            properties,
+                        );
+                        self.fragments.remove(&fragment.name);
+                    }
+                }
+            }
+        }
+    }
+
+    fn field(&mut self, parent_type: &str, field: &Node<Field>) -> JSONSchema {
+        let Ok(definition) = self.schema.type_field(parent_type, &field.name) else {
+            warn!(name=?field.name, r#type=parent_type, "Field not found in schema");
+            return json_schema!({});
+        };
+        let description = definition.description.as_ref().map(Node::to_string);
+
+        let field_schema = if field.selection_set.is_empty() {
+            // Leaf fields are scalars or enums, which are handled like input types
+            JSONSchema::from(Type {
+                cache: self.cache,
+                custom_scalar_map: self.custom_scalar_map,
+                description: &description,
+                schema: self.schema,
+                r#type: &definition.ty,
+            })
+        } else {
+            let object = self.object(definition.ty.inner_named_type(), &field.selection_set);
+            with_desc(list_of(&definition.ty, object), &description)
+        };
+
+        nullable(&definition.ty, field_schema)
+    }
+}
+
+/// Wrap the schema of a named type in the arrays of its list type
+fn list_of(r#type: &GraphQLType, schema: JSONSchema) -> JSONSchema {
+    match r#type {
+        GraphQLType::Named(_) | GraphQLType::NonNullNamed(_) => schema,
+        GraphQLType::List(list) | GraphQLType::NonNullList(list) => json_schema!({
+            "type": "array",
+            "items": nullable(list, list_of(list, schema)),
+        }),
+    }
+}
+
+/// Allow null for nullable types, since the response contains every selected field
+fn nullable(r#type: &GraphQLType, schema: JSONSchema) -> JSONSchema {
+    if r#type.is_non_null() {
+        schema
+    } else {
+        json_schema!({"oneOf": [
+            schema,
+            {"type": "null"},
+        ]})
+    }
+}
+
+/// Merge the schemas of fields selected more than once under the same response name
+fn merge(existing: &mut Value, schema: Value) {
+    match (existing, schema) {
+        (Value::Object(existing), Value::Object(schema)) => {
+            for (key, value) in schema {
+                match existing.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        existing.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(existing), Value::Array(schema)) => {
+            for (existing, schema) in existing.iter_mut().zip(schema) {
+                merge(existing, schema);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,437 @@
+//! Limits for ad hoc operations
+//!
+//! The `execute` tool runs operations written by the model, so a valid operation can still be
+//! deep or wide enough to overload the GraphQL endpoint. Operations are checked against limits
+//! on their depth, their number of aliases and root fields, and an estimate of their cost
+//! before they are sent.
+//!
+//! The cost of a field is its weight plus the cost of its selections, multiplied by the
+//! expected size of the list it returns. Weights come from the config, then from `@cost`
+//! directives on the field or its type, and default to 1 for fields returning objects and 0 for
+//! fields returning scalars. List sizes come from the slicing arguments or `assumedSize` of a
+//! `@listSize` directive, and default to `default_list_size`.
+
+use std::collections::{HashMap, HashSet};
+
+use apollo_compiler::{
+    ExecutableDocument, Schema,
+    ast::{DirectiveList, Value},
+    executable::{Field, Selection, SelectionSet},
+    schema::ExtendedType,
+    validation::Valid,
+};
+use rmcp::model::ErrorCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::errors::McpError;
+
+/// Configuration for the limits on ad hoc operations
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct QueryLimitsConfig {
+    /// The maximum depth of nested selections
+    pub max_depth: Option<usize>,
+
+    /// The maximum number of aliased fields
+    pub max_aliases: Option<usize>,
+
+    /// The maximum number of root fields
+    pub max_root_fields: Option<usize>,
+
+    /// The maximum estimated cost
+    pub max_cost: Option<u64>,
+
+    /// The size assumed for lists without a `@listSize` directive (default: 10)
+    pub default_list_size: u64,
+
+    /// Weights of fields, keyed by `Type.field`, or of all fields returning a type, keyed by
+    /// the type name. These take precedence over `@cost` directives.
+    pub costs: HashMap<String, u64>,
+}
+
+impl Default for QueryLimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            max_aliases: None,
+            max_root_fields: None,
+            max_cost: None,
+            default_list_size: 10,
+            costs: HashMap::new(),
+        }
+    }
+}
+
+impl QueryLimitsConfig {
+    /// Whether any limit is configured
+    pub fn is_enabled(&self) -> bool {
+        self.max_depth.is_some()
+            || self.max_aliases.is_some()
+            || self.max_root_fields.is_some()
+            || self.max_cost.is_some()
+    }
+
+    /// Check an operation against the limits, with an explanation the model can act on when
+    /// it is rejected
+    pub(crate) fn check(
+        &self,
+        schema: &Valid<Schema>,
+        source_text: &str,
+        operation_name: Option<&str>,
+        variables: &serde_json::Value,
+    ) -> Result<(), McpError> {
+        // Operations that don't validate are left for the endpoint to reject, so only the
+        // parts that match the schema are measured
+        let document = ExecutableDocument::parse(schema, source_text, "operation.graphql")
+            .unwrap_or_else(|with_errors| with_errors.partial);
+        let Ok(operation) = document.operations.get(operation_name) else {
+            return Ok(());
+        };
+
+        let measure = Measure {
+            config: self,
+            schema,
+            document: &document,
+            variables,
+        };
+        let root_fields = measure.fields(&operation.selection_set, &mut HashSet::new());
+        let mut stats = Stats::default();
+        let cost = measure.selection_set(&operation.selection_set, 1, &mut stats, &mut Vec::new());
+
+        let checks = [
+            (
+                "depth",
+                self.max_depth.map(|max| max as u64),
+                stats.depth as u64,
+                "Request fewer levels of nested fields, and make follow-up calls for the details you need",
+            ),
+            (
+                "aliases",
+                self.max_aliases.map(|max| max as u64),
+                stats.aliases as u64,
+                "Use fewer aliases, and split repeated lookups across several calls",
+            ),
+            (
+                "root_fields",
+                self.max_root_fields.map(|max| max as u64),
+                root_fields as u64,
+                "Select fewer top-level fields, and split the operation into several calls",
+            ),
+            (
+                "cost",
+                self.max_cost,
+                cost,
+                "Request fewer fields, smaller pages of lists (for example with `first` or `limit` arguments), or fewer nested lists",
+            ),
+        ];
+        for (limit, max, value, advice) in checks {
+            if let Some(max) = max.filter(|max| value > *max) {
+                return Err(McpError::new(
+                    ErrorCode::INVALID_PARAMS,
+                    format!(
+                        "The operation exceeds the {} limit: {value} is over the maximum of {max}. {advice}.",
+                        limit.replace('_', " ")
+                    ),
+                    Some(json!({
+                        "limit": limit,
+                        "value": value,
+                        "max": max,
+                    })),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    depth: usize,
+    aliases: usize,
+}
+
+struct Measure<'a> {
+    config: &'a QueryLimitsConfig,
+    schema: &'a Schema,
+    document: &'a ExecutableDocument,
+    variables: &'a serde_json::Value,
+}
+
+impl Measure<'_> {
+    /// The fields of a selection set, with fragments expanded
+    fn fields<'s>(
+        &'s self,
+        selection_set: &'s SelectionSet,
+        fragments: &mut HashSet<&'s str>,
+    ) -> usize {
+        selection_set
+            .selections
+            .iter()
+            .map(|selection| match selection {
+                Selection::Field(_) => 1,
+                Selection::InlineFragment(inline) => self.fields(&inline.selection_set, fragments),
+                Selection::FragmentSpread(spread) => {
+                    match self.document.fragments.get(&spread.fragment_name) {
+                        Some(fragment) if fragments.insert(spread.fragment_name.as_str()) => {
+                            let count = self.fields(&fragment.selection_set, fragments);
+                            fragments.remove(spread.fragment_name.as_str());
+                            count
+                        }
+                        _ => 0,
+                    }
+                }
+            })
+            .sum()
+    }
+
+    /// The cost of a selection set at a depth, recording its depth and aliases
+    fn selection_set<'s>(
+        &'s self,
+        selection_set: &'s SelectionSet,
+        depth: usize,
+        stats: &mut Stats,
+        fragments: &mut Vec<&'s str>,
+    ) -> u64 {
+        selection_set
+            .selections
+            .iter()
+            .map(|selection| match selection {
+                Selection::Field(field) => {
+                    stats.depth = stats.depth.max(depth);
+                    if field.alias.is_some() {
+                        stats.aliases += 1;
+                    }
+                    let children =
+                        self.selection_set(&field.selection_set, depth + 1, stats, fragments);
+                    self.list_size(field).saturating_mul(
+                        self.weight(selection_set.ty.as_str(), field)
+                            .saturating_add(children),
+                    )
+                }
+                Selection::InlineFragment(inline) => {
+                    self.selection_set(&inline.selection_set, depth, stats, fragments)
+                }
+                Selection::FragmentSpread(spread) => {
+                    let name = spread.fragment_name.as_str();
+                    match self.document.fragments.get(name) {
+                        // Skip fragments that spread themselves, which don't validate
+                        Some(fragment) if !fragments.contains(&name) => {
+                            fragments.push(name);
+                            let cost = self.selection_set(
+                                &fragment.selection_set,
+                                depth,
+                                stats,
+                                fragments,
+                            );
+                            fragments.pop();
+                            cost
+                        }
+                        _ => 0,
+                    }
+                }
+            })
+            .fold(0, u64::saturating_add)
+    }
+
+    fn weight(&self, parent_type: &str, field: &Field) -> u64 {
+        let type_name = field.ty().inner_named_type().as_str();
+        let type_def = self.schema.types.get(type_name);
+        self.config
+            .costs
+            .get(&format!("{parent_type}.{}", field.name))
+            .or_else(|| self.config.costs.get(type_name))
+            .copied()
+            .or_else(|| cost_directive(&field.definition.directives))
+            .or_else(|| {
+                type_def.and_then(|type_def| {
+                    type_def
+                        .directives()
+                        .get("cost")
+                        .and_then(|directive| directive.specified_argument_by_name("weight"))
+                        .and_then(|weight| to_u64(weight, self.variables))
+                })
+            })
+            .unwrap_or(match type_def {
+                Some(
+                    ExtendedType::Object(_) | ExtendedType::Interface(_) | ExtendedType::Union(_),
+                ) => 1,
+                _ => 0,
+            })
+    }
+
+    fn list_size(&self, field: &Field) -> u64 {
+        if !field.ty().is_list() {
+            return 1;
+        }
+        let list_size = field.definition.directives.get("listSize");
+        list_size
+            .and_then(|directive| directive.specified_argument_by_name("slicingArguments"))
+            .and_then(|arguments| arguments.as_list())
+            .and_then(|arguments| {
+                arguments
+                    .iter()
+                    .filter_map(|argument| argument.as_str())
+                    .filter_map(|argument| field.specified_argument_by_name(argument))
+                    .filter_map(|value| to_u64(value, self.variables))
+                    .max()
+            })
+            .or_else(|| {
+                list_size
+                    .and_then(|directive| directive.specified_argument_by_name("assumedSize"))
+                    .and_then(|size| to_u64(size, self.variables))
+            })
+            .unwrap_or(self.config.default_list_size)
+    }
+}
+
+/// The weight of a `@cost` directive, which is an `Int` or a `String` depending on the spec
+fn cost_directive(directives: &DirectiveList) -> Option<u64> {
+    directives
+        .get("cost")
+        .and_then(|directive| directive.specified_argument_by_name("weight"))
+        .and_then(|weight| to_u64(weight, &serde_json::Value::Null))
+}
+
+fn to_u64(value: &Value, variables: &serde_json::Value) -> Option<u64> {
+    match value {
+        Value::Int(int) => int.as_str().parse().ok(),
+        Value::String(string) => string
+            .parse::<f64>()
+            .ok()
+            .map(|weight| weight.ceil() as u64),
+        Value::Variable(name) => variables
+            .get(name.as_str())
+            .and_then(serde_json::Value::as_u64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const SCHEMA: &str = r#"
+        directive @cost(weight: Int!) on FIELD_DEFINITION | OBJECT
+        directive @listSize(assumedSize: Int, slicingArguments: [String!]) on FIELD_DEFINITION
+
+        type Query {
+            user(id: ID!): User
+            users(first: Int): [User] @listSize(slicingArguments: ["first"])
+            search(term: String): [User] @cost(weight: 5) @listSize(assumedSize: 3)
+        }
+
+        type User {
+            id: ID
+            name: String
+            friends: [User]
+            posts: [Post]
+        }
+
+        type Post @cost(weight: 2) {
+            title: String
+        }
+    "#;
+
+    fn check(
+        config: QueryLimitsConfig,
+        source_text: &str,
+        variables: serde_json::Value,
+    ) -> Result<(), McpError> {
+        let schema = Schema::parse_and_validate(SCHEMA, "schema.graphql").unwrap();
+        config.check(&schema, source_text, None, &variables)
+    }
+
+    #[test]
+    fn it_limits_depth() {
+        let config = QueryLimitsConfig {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+
+        assert!(check(config.clone(), "{ user(id: 1) { id } }", json!({})).is_ok());
+        let error = check(
+            config,
+            "query { ...UserFields } fragment UserFields on Query { user(id: 1) { friends { id } } }",
+            json!({}),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.message,
+            "The operation exceeds the depth limit: 3 is over the maximum of 2. Request fewer levels of nested fields, and make follow-up calls for the details you need."
+        );
+        assert_eq!(
+            error.data,
+            Some(json!({ "limit": "depth", "value": 3, "max": 2 }))
+        );
+    }
+
+    #[test]
+    fn it_limits_aliases_and_root_fields() {
+        let config = QueryLimitsConfig {
+            max_aliases: Some(1),
+            max_root_fields: Some(2),
+            ..Default::default()
+        };
+
+        assert!(
+            check(
+                config.clone(),
+                "{ a: user(id: 1) { id } b: user(id: 2) { id } }",
+                json!({})
+            )
+            .unwrap_err()
+            .message
+            .contains("aliases limit: 2")
+        );
+        assert!(
+            check(
+                config,
+                "{ user(id: 1) { id } users { id } search { id } }",
+                json!({})
+            )
+            .unwrap_err()
+            .message
+            .contains("root fields limit: 3")
+        );
+    }
+
+    #[test]
+    fn it_estimates_cost() {
+        let config = QueryLimitsConfig {
+            max_cost: Some(0),
+            costs: HashMap::from([("User.name".to_string(), 4)]),
+            ..Default::default()
+        };
+        let cost = |source_text: &str, variables: serde_json::Value| {
+            check(config.clone(), source_text, variables)
+                .unwrap_err()
+                .data
+                .unwrap()["value"]
+                .clone()
+        };
+
+        // 1 for the user, 4 for its name from the config
+        assert_eq!(cost("{ user(id: 1) { id name } }", json!({})), json!(5));
+        // 10 users by default, each with 10 posts weighing 2 from the type directive
+        assert_eq!(
+            cost("{ users { posts { title } } }", json!({})),
+            json!(10 * (1 + 10 * 2))
+        );
+        // The slicing argument sets the size of the list, from a literal or a variable
+        assert_eq!(cost("{ users(first: 2) { id } }", json!({})), json!(2));
+        assert_eq!(
+            cost(
+                "query($n: Int) { users(first: $n) { id } }",
+                json!({ "n": 3 })
+            ),
+            json!(3)
+        );
+        // The field directive sets the weight and the assumed size
+        assert_eq!(cost("{ search { id } }", json!({})), json!(15));
+    }
+}
@@ -0,0 +1,352 @@
+//! Rate limits for tool calls
+//!
+//! Tool calls are limited with token buckets: each bucket holds up to `burst` calls and
+//! refills at `requests` calls per `period`. A call is only accepted if every bucket that
+//! applies to it has room, so a single runaway agent can't exhaust the GraphQL API.
+//!
+//! Buckets apply to all calls (`global`), to the calls of a tool (`tools`), and to the calls
+//! of a client (`per_client`). Clients are identified by the subject of their validated
+//! token, their MCP session, or the client name they sent when initializing, in that order.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use rmcp::model::ErrorCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::errors::McpError;
+use crate::generated::telemetry::{TelemetryAttribute, TelemetryMetric};
+use crate::meter;
+
+/// The JSON-RPC error code of rejected tool calls, in the range reserved for server errors
+pub const RATE_LIMITED: ErrorCode = ErrorCode(-32029);
+
+/// The number of client buckets kept before full buckets are discarded
+const MAX_CLIENT_BUCKETS: usize = 10_000;
+
+/// Configuration for rate limiting tool calls
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// The limit for all tool calls
+    pub global: Option<RateLimit>,
+
+    /// The limit for the tool calls of each client
+    pub per_client: Option<RateLimit>,
+
+    /// The limits for the calls of specific tools, keyed by tool name
+    pub tools: HashMap<String, RateLimit>,
+}
+
+impl RateLimitConfig {
+    /// Whether any limit is configured
+    pub fn is_enabled(&self) -> bool {
+        self.global.is_some() || self.per_client.is_some() || !self.tools.is_empty()
+    }
+}
+
+/// A token bucket limit
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    /// The number of calls allowed per period
+    pub requests: u32,
+
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// The period over which `requests` calls are allowed
+    pub period: Duration,
+
+    /// The number of calls that can be made at once after a quiet spell (default: requests)
+    pub burst: Option<u32>,
+}
+
+impl RateLimit {
+    fn capacity(&self) -> f64 {
+        f64::from(self.burst.unwrap_or(self.requests))
+    }
+
+    /// Calls refilled per second
+    fn rate(&self) -> f64 {
+        f64::from(self.requests) / self.period.as_secs_f64()
+    }
+}
+
+/// The limit that rejected a tool call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitScope {
+    Global,
+    Tool,
+    Client,
+}
+
+impl fmt::Display for RateLimitScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RateLimitScope::Global => "global",
+            RateLimitScope::Tool => "tool",
+            RateLimitScope::Client => "client",
+        })
+    }
+}
+
+/// A rejected tool call
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimited {
+    pub scope: RateLimitScope,
+    pub retry_after: Duration,
+}
+
+impl RateLimited {
+    /// The MCP error returned for the rejected call, with a hint of when to retry in seconds
+    pub fn into_error(self, tool_name: &str) -> McpError {
+        let retry_after = self.retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        McpError::new(
+            RATE_LIMITED,
+            format!(
+                "Rate limit exceeded for {tool_name} ({} limit). Retry after {retry_after}s",
+                self.scope
+            ),
+            Some(json!({
+                "scope": self.scope.to_string(),
+                "retryAfter": retry_after,
+            })),
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn full(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.capacity(),
+            updated_at: now,
+        }
+    }
+
+    /// Refill the bucket, returning how long until it holds a call
+    fn refill(&mut self, limit: &RateLimit, now: Instant) -> Option<Duration> {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.rate()).min(limit.capacity());
+        self.updated_at = now;
+        (self.tokens < 1.0).then(|| {
+            Duration::try_from_secs_f64((1.0 - self.tokens) / limit.rate()).unwrap_or(Duration::MAX)
+        })
+    }
+}
+
+#[derive(Default)]
+struct State {
+    global: Option<Bucket>,
+    tools: HashMap<String, Bucket>,
+    clients: HashMap<String, Bucket>,
+}
+
+/// Rate limits shared by all sessions
+#[derive(Clone)]
+pub struct RateLimiter {
+    config: Arc<RateLimitConfig>,
+    state: Arc<Mutex<State>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    /// Accept a call to a tool by a client, or reject it and record the rejection
+    pub fn check(&self, tool_name: &str, client: Option<&str>) -> Result<(), RateLimited> {
+        let result = self.acquire(tool_name, client, Instant::now());
+        if let Err(rejection) = &result {
+            meter::METER
+                .u64_counter(TelemetryMetric::RateLimitRejectedCount.as_str())
+                .build()
+                .add(
+                    1,
+                    &[
+                        opentelemetry::KeyValue::new(
+                            TelemetryAttribute::ToolName.to_key(),
+                            tool_name.to_string(),
+                        ),
+                        opentelemetry::KeyValue::new(
+                            TelemetryAttribute::RateLimitScope.to_key(),
+                            rejection.scope.to_string(),
+                        ),
+                    ],
+                );
+        }
+        result
+    }
+
+    fn acquire(
+        &self,
+        tool_name: &str,
+        client: Option<&str>,
+        now: Instant,
+    ) -> Result<(), RateLimited> {
+        let Ok(mut state) = self.state.lock() else {
+            return Ok(());
+        };
+        let State {
+            global,
+            tools,
+            clients,
+        } = &mut *state;
+
+        if clients.len() > MAX_CLIENT_BUCKETS
+            && let Some(limit) = &self.config.per_client
+        {
+            clients.retain(|_, bucket| {
+                bucket.refill(limit, now);
+                bucket.tokens < limit.capacity()
+            });
+        }
+
+        let mut buckets = Vec::new();
+        if let Some(limit) = &self.config.global {
+            let bucket = global.get_or_insert_with(|| Bucket::full(limit, now));
+            buckets.push((RateLimitScope::Global, limit, bucket));
+        }
+        if let Some(limit) = self.config.tools.get(tool_name) {
+            let bucket = tools
+                .entry(tool_name.to_string())
+                .or_insert_with(|| Bucket::full(limit, now));
+            buckets.push((RateLimitScope::Tool, limit, bucket));
+        }
+        if let (Some(limit), Some(client)) = (&self.config.per_client, client) {
+            let bucket = clients
+                .entry(client.to_string())
+                .or_insert_with(|| Bucket::full(limit, now));
+            buckets.push((RateLimitScope::Client, limit, bucket));
+        }
+
+        // Only take from the buckets if all of them have room
+        let rejection = buckets
+            .iter_mut()
+            .filter_map(|(scope, limit, bucket)| {
+                bucket.refill(limit, now).map(|retry_after| RateLimited {
+                    scope: *scope,
+                    retry_after,
+                })
+            })
+            .max_by_key(|rejection| rejection.retry_after);
+        if let Some(rejection) = rejection {
+            return Err(rejection);
+        }
+        for (_, _, bucket) in buckets {
+            bucket.tokens -= 1.0;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(requests: u32, period: Duration) -> RateLimit {
+        RateLimit {
+            requests,
+            period,
+            burst: None,
+        }
+    }
+
+    #[test]
+    fn it_limits_each_client() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            per_client: Some(limit(2, Duration::from_secs(10))),
+            ..Default::default()
+        });
+        let now = Instant::now();
+
+        assert!(limiter.acquire("GetUser", Some("a"), now).is_ok());
+        assert!(limiter.acquire("GetUser", Some("a"), now).is_ok());
+        assert_eq!(
+            limiter.acquire("GetUser", Some("a"), now),
+            Err(RateLimited {
+                scope: RateLimitScope::Client,
+                retry_after: Duration::from_secs(5),
+            })
+        );
+        assert!(limiter.acquire("GetUser", Some("b"), now).is_ok());
+        assert!(limiter.acquire("GetUser", None, now).is_ok());
+
+        // Half the period refills one call
+        let later = now + Duration::from_secs(5);
+        assert!(limiter.acquire("GetUser", Some("a"), later).is_ok());
+        assert!(limiter.acquire("GetUser", Some("a"), later).is_err());
+    }
+
+    #[test]
+    fn it_only_takes_from_buckets_when_all_have_room() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            global: Some(limit(2, Duration::from_secs(1))),
+            tools: HashMap::from([("Report".to_string(), limit(1, Duration::from_secs(60)))]),
+            ..Default::default()
+        });
+        let now = Instant::now();
+
+        assert!(limiter.acquire("Report", None, now).is_ok());
+        assert_eq!(
+            limiter.acquire("Report", None, now).unwrap_err().scope,
+            RateLimitScope::Tool
+        );
+        assert!(limiter.acquire("GetUser", None, now).is_ok());
+        assert_eq!(
+            limiter.acquire("GetUser", None, now).unwrap_err().scope,
+            RateLimitScope::Global
+        );
+    }
+
+    #[test]
+    fn it_allows_bursts() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            global: Some(RateLimit {
+                burst: Some(3),
+                ..limit(1, Duration::from_secs(1))
+            }),
+            ..Default::default()
+        });
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.acquire("GetUser", None, now).is_ok());
+        }
+        assert!(limiter.acquire("GetUser", None, now).is_err());
+    }
+
+    #[test]
+    fn it_returns_a_retry_after_hint() {
+        let error = RateLimited {
+            scope: RateLimitScope::Global,
+            retry_after: Duration::from_millis(1200),
+        }
+        .into_error("GetUser");
+
+        assert_eq!(error.code, RATE_LIMITED);
+        assert_eq!(
+            error.message,
+            "Rate limit exceeded for GetUser (global limit). Retry after 2s"
+        );
+        assert_eq!(
+            error.data,
+            Some(json!({ "scope": "global", "retryAfter": 2 }))
+        );
+    }
+}
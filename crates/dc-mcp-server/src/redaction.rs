@@ -0,0 +1,283 @@
+//! Redaction of secrets
+//!
+//! A process-wide redactor keeps secrets out of logs, exported spans, error messages and the
+//! audit log. It replaces the values of sensitive headers, the values of variables at
+//! configured paths, and text matching configured patterns. Authorization and cookie headers,
+//! bearer tokens, JSON Web Tokens and the token fields of JSON bodies are always redacted.
+
+use std::{borrow::Cow, collections::HashSet, sync::OnceLock};
+
+use regex::{Captures, Regex};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::operations::JsonPath;
+
+/// The text replacing redacted values
+pub const REDACTED: &str = "[REDACTED]";
+
+/// The capture group of a pattern holding the secret, when only part of a match is secret
+const SECRET_GROUP: &str = "secret";
+
+/// Headers whose values are always redacted
+const DEFAULT_HEADERS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+    "set-cookie",
+    "x-api-key",
+];
+
+/// Patterns that are always redacted
+const DEFAULT_PATTERNS: &[&str] = &[
+    // Bearer tokens outside of a header
+    r"(?i)\bbearer\s+(?P<secret>[A-Za-z0-9\-._~+/]+=*)",
+    // JSON Web Tokens
+    r"\beyJ[A-Za-z0-9_-]*\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]*",
+    // Token fields of JSON bodies, such as token endpoint responses
+    r#"(?i)"(?:access_?token|refresh_?token|id_?token|client_?secret|password)"\s*:\s*"(?P<secret>[^"]*)""#,
+];
+
+static REDACTOR: OnceLock<Redactor> = OnceLock::new();
+
+/// Configuration for redacting secrets, in addition to the ones that are always redacted
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct RedactionConfig {
+    /// The names of headers whose values are redacted
+    pub headers: Vec<String>,
+
+    /// The paths of variables whose values are redacted, such as `input.password`
+    pub variables: Vec<JsonPath>,
+
+    /// Regular expressions matching secrets in text. If a pattern has a capture group named
+    /// `secret`, only that group is redacted.
+    pub patterns: Vec<String>,
+}
+
+/// Redacts secrets from text, headers and variables
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    /// The lowercased names of the headers to redact
+    headers: HashSet<String>,
+
+    variables: Vec<JsonPath>,
+    patterns: Vec<Regex>,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::with_rules(&[], Vec::new(), Vec::new())
+    }
+}
+
+impl Redactor {
+    pub fn new(config: &RedactionConfig) -> Result<Self, regex::Error> {
+        let patterns = config
+            .patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::with_rules(
+            &config.headers,
+            config.variables.clone(),
+            patterns,
+        ))
+    }
+
+    fn with_rules(headers: &[String], variables: Vec<JsonPath>, patterns: Vec<Regex>) -> Self {
+        let headers: HashSet<String> = DEFAULT_HEADERS
+            .iter()
+            .map(ToString::to_string)
+            .chain(headers.iter().map(|name| name.to_lowercase()))
+            .collect();
+
+        // Header values in `name: value` lines, `"name": "value"` maps and `name=value` pairs
+        let mut names: Vec<String> = headers.iter().map(|name| regex::escape(name)).collect();
+        names.sort();
+        let header_pattern = format!(
+            r#"(?i)"?\b(?:{})"?\s*[:=]\s*"?(?P<{SECRET_GROUP}>[^"\r\n,}}]+)"#,
+            names.join("|")
+        );
+
+        let patterns = std::iter::once(header_pattern.as_str())
+            .chain(DEFAULT_PATTERNS.iter().copied())
+            .filter_map(|pattern| Regex::new(pattern).ok())
+            .chain(patterns)
+            .collect();
+        Self {
+            headers,
+            variables,
+            patterns,
+        }
+    }
+
+    /// Whether the value of a header is redacted
+    pub fn is_sensitive_header(&self, name: &str) -> bool {
+        self.headers.contains(&name.to_lowercase())
+    }
+
+    /// Redact the secrets found in text
+    pub fn redact_str<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut redacted: Option<String> = None;
+        for pattern in &self.patterns {
+            let current = redacted.as_deref().unwrap_or(text);
+            let replaced = match replace(pattern, current) {
+                Cow::Owned(replaced) => Some(replaced),
+                Cow::Borrowed(_) => None,
+            };
+            if replaced.is_some() {
+                redacted = replaced;
+            }
+        }
+        redacted.map_or(Cow::Borrowed(text), Cow::Owned)
+    }
+
+    /// Redact the variables at the configured paths, and the secrets found in the others
+    pub fn redact_variables(&self, variables: &mut Value) {
+        let redacted = Value::String(REDACTED.to_string());
+        for path in &self.variables {
+            path.replace(variables, &redacted);
+        }
+        self.redact_value(variables);
+    }
+
+    /// Redact the secrets found in the strings of a JSON value
+    pub fn redact_value(&self, value: &mut Value) {
+        match value {
+            Value::String(text) => {
+                if let Cow::Owned(redacted) = self.redact_str(text) {
+                    *text = redacted;
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact_value(item)),
+            Value::Object(object) => object
+                .values_mut()
+                .for_each(|value| self.redact_value(value)),
+            _ => {}
+        }
+    }
+}
+
+/// Replace the matches of a pattern, or only their secret if the pattern captures it
+fn replace<'a>(pattern: &Regex, text: &'a str) -> Cow<'a, str> {
+    pattern.replace_all(text, |captures: &Captures| {
+        match (captures.get(0), captures.name(SECRET_GROUP)) {
+            (Some(whole), Some(secret)) => format!(
+                "{}{REDACTED}{}",
+                text.get(whole.start()..secret.start()).unwrap_or_default(),
+                text.get(secret.end()..whole.end()).unwrap_or_default()
+            ),
+            _ => REDACTED.to_string(),
+        }
+    })
+}
+
+/// Install the redactor used across the server. It can only be installed once, before it is
+/// first used, so the redactor is returned if another one is already in use.
+pub fn install(redactor: Redactor) -> Result<(), Redactor> {
+    REDACTOR.set(redactor)
+}
+
+/// The redactor used across the server, which only redacts the default secrets unless
+/// another one was installed
+pub fn redactor() -> &'static Redactor {
+    REDACTOR.get_or_init(Redactor::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn redactor(headers: &[&str], variables: &[&str], patterns: &[&str]) -> Redactor {
+        Redactor::new(&RedactionConfig {
+            headers: headers.iter().map(ToString::to_string).collect(),
+            variables: variables.iter().map(|path| path.parse().unwrap()).collect(),
+            patterns: patterns.iter().map(ToString::to_string).collect(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn it_redacts_default_secrets() {
+        let redactor = Redactor::default();
+
+        assert_eq!(
+            redactor.redact_str(r#"{"authorization": "Bearer abc.def", "x-tenant": "acme"}"#),
+            r#"{"authorization": "[REDACTED]", "x-tenant": "acme"}"#
+        );
+        assert_eq!(
+            redactor.redact_str("Authorization: Bearer abc\nX-Tenant: acme"),
+            "Authorization: [REDACTED]\nX-Tenant: acme"
+        );
+        assert_eq!(
+            redactor.redact_str("token expired: bearer abc123"),
+            "token expired: bearer [REDACTED]"
+        );
+        assert_eq!(
+            redactor.redact_str(r#"{"accessToken":"abc","expiresIn":300}"#),
+            r#"{"accessToken":"[REDACTED]","expiresIn":300}"#
+        );
+        assert_eq!(
+            redactor.redact_str("token eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiIxIn0.sig was rejected"),
+            "token [REDACTED] was rejected"
+        );
+        assert!(matches!(
+            redactor.redact_str("nothing to hide"),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn it_redacts_configured_headers_and_patterns() {
+        let redactor = redactor(
+            &["X-Customer-Key"],
+            &[],
+            &[r"sk_live_\w+", r"pin=(?P<secret>\d+)"],
+        );
+
+        assert!(redactor.is_sensitive_header("x-customer-key"));
+        assert!(redactor.is_sensitive_header("Authorization"));
+        assert!(!redactor.is_sensitive_header("x-tenant"));
+        assert_eq!(
+            redactor.redact_str("x-customer-key: 1234, key sk_live_abc, pin=42"),
+            "x-customer-key: [REDACTED], key [REDACTED], pin=[REDACTED]"
+        );
+    }
+
+    #[test]
+    fn it_redacts_variables() {
+        let redactor = redactor(&[], &["input.password", "users[*].ssn"], &[]);
+        let mut variables = json!({
+            "input": { "name": "Ada", "password": "secret" },
+            "users": [{ "ssn": "123" }, { "name": "Grace" }],
+            "note": "call with Bearer abc",
+        });
+
+        redactor.redact_variables(&mut variables);
+
+        assert_eq!(
+            variables,
+            json!({
+                "input": { "name": "Ada", "password": REDACTED },
+                "users": [{ "ssn": REDACTED }, { "name": "Grace" }],
+                "note": "call with Bearer [REDACTED]",
+            })
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_patterns() {
+        assert!(
+            Redactor::new(&RedactionConfig {
+                patterns: vec![String::from("(")],
+                ..Default::default()
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,345 @@
+//! Response cache for query tools
+//!
+//! Agents often repeat identical lookups within seconds. When enabled, the results of query
+//! operation tools are kept in memory for a configurable time, keyed by the tool, its
+//! arguments, and the identity of the caller, so repeated calls are served without a request
+//! to the GraphQL endpoint. Mutations and subscriptions are never cached.
+//!
+//! The cache is cleared whenever the schema or the operations are updated.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use reqwest::header::{AUTHORIZATION, HeaderMap};
+use rmcp::model::CallToolResult;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest as _, Sha256};
+
+use crate::generated::telemetry::{TelemetryAttribute, TelemetryMetric};
+use crate::meter;
+
+/// Configuration for the response cache
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct ResponseCacheConfig {
+    /// Whether to cache the results of query tools (default: false)
+    pub enabled: bool,
+
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// How long results are kept (default: 30s)
+    pub ttl: Duration,
+
+    /// How long the results of specific operations are kept, keyed by operation name.
+    /// A TTL of `0s` disables caching for the operation.
+    #[schemars(with = "HashMap<String, String>")]
+    pub operations: HashMap<String, humantime_serde::Serde<Duration>>,
+
+    /// The maximum number of results to keep (default: 1000)
+    pub max_entries: usize,
+
+    /// The maximum size in bytes of a single result. Larger results aren't cached
+    /// (default: 1048576)
+    pub max_entry_bytes: usize,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl: Duration::from_secs(30),
+            operations: HashMap::new(),
+            max_entries: 1000,
+            max_entry_bytes: 1024 * 1024,
+        }
+    }
+}
+
+/// The identity of a cached result
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct CacheKey {
+    tool_name: String,
+    arguments: String,
+    identity: Option<String>,
+}
+
+impl CacheKey {
+    /// Identify a tool call by its tool, its arguments, and the credentials it is sent with
+    pub(crate) fn new(tool_name: &str, arguments: &Value, headers: &HeaderMap) -> Self {
+        Self {
+            tool_name: tool_name.to_string(),
+            arguments: sort_keys(arguments).to_string(),
+            identity: headers
+                .get(AUTHORIZATION)
+                .map(|value| format!("{:x}", Sha256::digest(value.as_bytes()))),
+        }
+    }
+}
+
+struct Entry {
+    result: CallToolResult,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<CacheKey, Entry>,
+    uses: u64,
+}
+
+/// An in-memory cache of query tool results, shared by all sessions
+#[derive(Clone)]
+pub struct ResponseCache {
+    config: Arc<ResponseCacheConfig>,
+    state: Arc<Mutex<State>>,
+}
+
+impl ResponseCache {
+    pub fn new(config: ResponseCacheConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    /// Get the cached result of a tool call, recording a hit or a miss
+    pub(crate) fn get(&self, key: &CacheKey) -> Option<CallToolResult> {
+        let result = self.state.lock().ok().and_then(|mut state| {
+            state.uses += 1;
+            let uses = state.uses;
+            let now = Instant::now();
+            match state.entries.get_mut(key) {
+                Some(entry) if entry.expires_at > now => {
+                    entry.last_used = uses;
+                    Some(entry.result.clone())
+                }
+                Some(_) => {
+                    state.entries.remove(key);
+                    None
+                }
+                None => None,
+            }
+        });
+
+        let metric = if result.is_some() {
+            TelemetryMetric::ResponseCacheHitCount
+        } else {
+            TelemetryMetric::ResponseCacheMissCount
+        };
+        meter::METER.u64_counter(metric.as_str()).build().add(
+            1,
+            &[opentelemetry::KeyValue::new(
+                TelemetryAttribute::ToolName.to_key(),
+                key.tool_name.clone(),
+            )],
+        );
+        result
+    }
+
+    /// Cache the result of a tool call for the TTL of its operation. Errors, partial data
+    /// returned with errors, and results over the size limit aren't cached.
+    pub(crate) fn insert(&self, key: CacheKey, operation_name: &str, result: &CallToolResult) {
+        let ttl = self
+            .config
+            .operations
+            .get(operation_name)
+            .map(|ttl| **ttl)
+            .unwrap_or(self.config.ttl);
+        if ttl.is_zero() || self.config.max_entries == 0 || result.is_error == Some(true) {
+            return;
+        }
+        // Errors returned with partial data are often transient, so they shouldn't be repeated
+        if result
+            .meta
+            .as_ref()
+            .is_some_and(|meta| meta.contains_key("errors"))
+        {
+            return;
+        }
+        let size = serde_json::to_vec(result).map_or(usize::MAX, |bytes| bytes.len());
+        if size > self.config.max_entry_bytes {
+            return;
+        }
+
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        if !state.entries.contains_key(&key) && state.entries.len() >= self.config.max_entries {
+            state.entries.retain(|_, entry| entry.expires_at > now);
+        }
+        while !state.entries.contains_key(&key) && state.entries.len() >= self.config.max_entries {
+            // Evict the least recently used result
+            let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+
+        state.uses += 1;
+        let last_used = state.uses;
+        state.entries.insert(
+            key,
+            Entry {
+                result: result.clone(),
+                expires_at: now + ttl,
+                last_used,
+            },
+        );
+    }
+
+    /// Remove all cached results
+    pub(crate) fn clear(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.entries.clear();
+        }
+    }
+}
+
+/// Sort the keys of every object in a JSON value, so equal arguments produce equal keys
+fn sort_keys(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), sort_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(sort_keys).collect()),
+        _ => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+    use rmcp::model::{Content, Meta};
+    use serde_json::json;
+
+    use super::*;
+
+    fn cache(config: ResponseCacheConfig) -> ResponseCache {
+        ResponseCache::new(ResponseCacheConfig {
+            enabled: true,
+            ..config
+        })
+    }
+
+    fn result(text: &str) -> CallToolResult {
+        CallToolResult::success(vec![Content::text(text)])
+    }
+
+    fn key(tool_name: &str, arguments: Value) -> CacheKey {
+        CacheKey::new(tool_name, &arguments, &HeaderMap::new())
+    }
+
+    #[test]
+    fn it_keys_by_arguments_regardless_of_order() {
+        let cache = cache(ResponseCacheConfig::default());
+        cache.insert(
+            key("GetUser", json!({"a": 1, "b": 2})),
+            "GetUser",
+            &result("user"),
+        );
+
+        assert_eq!(
+            cache.get(&key("GetUser", json!({"b": 2, "a": 1}))),
+            Some(result("user"))
+        );
+        assert_eq!(cache.get(&key("GetUser", json!({"a": 2, "b": 2}))), None);
+        assert_eq!(cache.get(&key("GetOther", json!({"a": 1, "b": 2}))), None);
+    }
+
+    #[test]
+    fn it_keys_by_authorization() {
+        let cache = cache(ResponseCacheConfig::default());
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer a"));
+        cache.insert(
+            CacheKey::new("GetUser", &json!({}), &headers),
+            "GetUser",
+            &result("user"),
+        );
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer b"));
+        assert_eq!(
+            cache.get(&CacheKey::new("GetUser", &json!({}), &headers)),
+            None
+        );
+        assert_eq!(cache.get(&key("GetUser", json!({}))), None);
+    }
+
+    #[test]
+    fn it_expires_results() {
+        let cache = cache(ResponseCacheConfig {
+            operations: HashMap::from([(
+                "GetUser".to_string(),
+                humantime_serde::Serde::from(Duration::ZERO),
+            )]),
+            ttl: Duration::from_millis(1),
+            ..Default::default()
+        });
+        cache.insert(key("GetUser", json!({})), "GetUser", &result("user"));
+        cache.insert(key("GetOther", json!({})), "GetOther", &result("other"));
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert_eq!(cache.get(&key("GetUser", json!({}))), None);
+        assert_eq!(cache.get(&key("GetOther", json!({}))), None);
+    }
+
+    #[test]
+    fn it_enforces_size_limits() {
+        let cache = cache(ResponseCacheConfig {
+            max_entries: 2,
+            max_entry_bytes: 100,
+            ..Default::default()
+        });
+        cache.insert(key("A", json!({})), "A", &result("a"));
+        cache.insert(key("B", json!({})), "B", &result("b"));
+        cache.get(&key("A", json!({})));
+        cache.insert(key("C", json!({})), "C", &result("c"));
+        cache.insert(key("D", json!({})), "D", &result(&"d".repeat(100)));
+
+        assert_eq!(cache.get(&key("A", json!({}))), Some(result("a")));
+        assert_eq!(cache.get(&key("B", json!({}))), None);
+        assert_eq!(cache.get(&key("C", json!({}))), Some(result("c")));
+        assert_eq!(cache.get(&key("D", json!({}))), None);
+    }
+
+    #[test]
+    fn it_does_not_cache_errors() {
+        let cache = cache(ResponseCacheConfig::default());
+        cache.insert(
+            key("GetUser", json!({})),
+            "GetUser",
+            &CallToolResult::error(vec![Content::text("failed")]),
+        );
+        assert_eq!(cache.get(&key("GetUser", json!({}))), None);
+
+        let mut partial = result("partial");
+        partial.meta = Some(Meta(
+            json!({ "errors": [{ "category": "unknown" }] })
+                .as_object()
+                .cloned()
+                .unwrap(),
+        ));
+        cache.insert(key("GetUser", json!({})), "GetUser", &partial);
+        assert_eq!(cache.get(&key("GetUser", json!({}))), None);
+    }
+}
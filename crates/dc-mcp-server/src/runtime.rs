@@ -151,6 +151,14 @@ mod test {
 
             insta::assert_debug_snapshot!(config, @r#"
             Config {
+                audit: AuditConfig {
+                    file: None,
+                    otlp: None,
+                },
+                contract: ContractConfig {
+                    include: [],
+                    exclude: [],
+                },
                 cors: CorsConfig {
                     enabled: false,
                     origins: [],
@@ -198,6 +206,11 @@ mod test {
                         fragment: None,
                     },
                 ),
+                endpoints: {},
+                error_mapping: ErrorMappingConfig {
+                    partial_data: Allow,
+                    codes: {},
+                },
                 graphos: GraphOSConfig {
                     apollo_key: None,
                     apollo_graph_ref: None,
@@ -254,6 +267,18 @@ mod test {
                 introspection: Introspection {
                     execute: ExecuteConfig {
                         enabled: false,
+                        limits: QueryLimitsConfig {
+                            max_depth: None,
+                            max_aliases: None,
+                            max_root_fields: None,
+                            max_cost: None,
+                            default_list_size: 10,
+                            costs: {},
+                        },
+                    },
+                    filter: SchemaFilterConfig {
+                        allow: [],
+                        deny: [],
                     },
                     introspect: IntrospectConfig {
                         enabled: false,
@@ -281,15 +306,77 @@ mod test {
                     service_name: None,
                     version: None,
                 },
+                mutation_confirmation: MutationConfirmationConfig {
+                    enabled: false,
+                    timeout: 300s,
+                },
                 operations: Infer,
                 overrides: Overrides {
                     disable_type_description: false,
                     disable_schema_description: false,
                     enable_explorer: false,
                     mutation_mode: None,
+                    persisted_queries: false,
+                    tool_page_size: None,
+                },
+                projections: {},
+                rate_limits: RateLimitConfig {
+                    global: None,
+                    per_client: None,
+                    tools: {},
+                },
+                redaction: RedactionConfig {
+                    headers: [],
+                    variables: [],
+                    patterns: [],
+                },
+                response_cache: ResponseCacheConfig {
+                    enabled: false,
+                    ttl: 30s,
+                    operations: {},
+                    max_entries: 1000,
+                    max_entry_bytes: 1048576,
                 },
                 schema: Uplink,
+                subscriptions: SubscriptionConfig {
+                    protocol: GraphqlWs,
+                    endpoint: None,
+                    max_events: 10,
+                    timeout: 30s,
+                },
+                token_refresh: None,
                 transport: Stdio,
+                upstream: UpstreamConfig {
+                    timeout: 30s,
+                    connect_timeout: 10s,
+                    pool_idle_timeout: 90s,
+                    pool_max_idle_per_host: None,
+                    user_agent: "curl/8.4.0",
+                    http2_prior_knowledge: false,
+                    proxy: None,
+                    tls: UpstreamTlsConfig {
+                        ca_bundle: None,
+                        client_cert: None,
+                        client_key: None,
+                        danger_accept_invalid_certs: false,
+                        danger_accept_invalid_hostnames: false,
+                    },
+                    retry: RetryConfig {
+                        max_retries: 0,
+                        initial_backoff: 100ms,
+                        max_backoff: 2s,
+                        status_codes: [
+                            502,
+                            503,
+                            504,
+                        ],
+                    },
+                    circuit_breaker: CircuitBreakerConfig {
+                        enabled: false,
+                        failure_threshold: 5,
+                        open_duration: 30s,
+                    },
+                },
             }
             "#);
             Ok(())
@@ -1,20 +1,37 @@
-use std::path::PathBuf;
-
-use dc_mcp_server::{cors::CorsConfig, health::HealthCheckConfig, server::Transport};
+use std::{collections::HashMap, path::PathBuf};
+
+use dc_mcp_server::{
+    audit::AuditConfig, confirmation::MutationConfirmationConfig, contract::ContractConfig,
+    cors::CorsConfig, error_mapping::ErrorMappingConfig, health::HealthCheckConfig,
+    operations::ResponseProjection, rate_limit::RateLimitConfig, redaction::RedactionConfig,
+    response_cache::ResponseCacheConfig, server::Transport, subscription::SubscriptionConfig,
+    token_provider::TokenRefreshConfig, upstream::UpstreamConfig,
+};
 use reqwest::header::HeaderMap;
 use schemars::JsonSchema;
 use serde::Deserialize;
 use url::Url;
 
 use super::{
-    OperationSource, SchemaSource, endpoint::Endpoint, graphos::GraphOSConfig,
-    introspection::Introspection, logging::Logging, overrides::Overrides, telemetry::Telemetry,
+    OperationSource, SchemaSource,
+    endpoint::{Endpoint, TargetEndpoint},
+    graphos::GraphOSConfig,
+    introspection::Introspection,
+    logging::Logging,
+    overrides::Overrides,
+    telemetry::Telemetry,
 };
 
 /// Configuration for the MCP server
 #[derive(Debug, Default, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct Config {
+    /// Audit log of tool calls
+    pub audit: AuditConfig,
+
+    /// Contract of the schema, filtering the supergraph by its `@tag` directives
+    pub contract: ContractConfig,
+
     /// CORS configuration
     pub cors: CorsConfig,
 
@@ -25,6 +42,12 @@ pub struct Config {
     #[schemars(schema_with = "Url::json_schema")]
     pub endpoint: Endpoint,
 
+    /// Named GraphQL endpoints that operations can target, in addition to `endpoint`
+    pub endpoints: HashMap<String, TargetEndpoint>,
+
+    /// Mapping of GraphQL errors into tool results
+    pub error_mapping: ErrorMappingConfig,
+
     /// Apollo-specific credential overrides
     pub graphos: GraphOSConfig,
 
@@ -46,26 +69,50 @@ pub struct Config {
     /// Telemetry configuration
     pub telemetry: Telemetry,
 
+    /// Confirmation of mutations by the user before they are run
+    pub mutation_confirmation: MutationConfirmationConfig,
+
     /// Operations
     pub operations: OperationSource,
 
     /// Overrides for server behaviour
     pub overrides: Overrides,
 
+    /// Projections to apply to the responses of operation tools, keyed by operation name
+    pub projections: HashMap<String, ResponseProjection>,
+
+    /// Rate limits for tool calls
+    pub rate_limits: RateLimitConfig,
+
+    /// Redaction of secrets from logs, spans, errors and the audit log
+    pub redaction: RedactionConfig,
+
+    /// Response cache configuration for query tools
+    pub response_cache: ResponseCacheConfig,
+
     /// The schema to load for operations
     pub schema: SchemaSource,
 
+    /// Subscription configuration
+    pub subscriptions: SubscriptionConfig,
+
+    /// Refresh of the access token sent to the GraphQL endpoint
+    pub token_refresh: Option<TokenRefreshConfig>,
+
     /// The type of server transport to use
     pub transport: Transport,
+
+    /// Configuration for the HTTP client used to reach the GraphQL endpoints
+    pub upstream: UpstreamConfig,
 }
 
-mod parsers {
+pub(super) mod parsers {
     use std::str::FromStr;
 
     use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
     use serde::Deserializer;
 
-    pub(super) fn map_from_str<'de, D>(deserializer: D) -> Result<HeaderMap, D::Error>
+    pub(in crate::runtime) fn map_from_str<'de, D>(deserializer: D) -> Result<HeaderMap, D::Error>
     where
         D: Deserializer<'de>,
     {
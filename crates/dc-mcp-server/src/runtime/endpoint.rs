@@ -4,8 +4,10 @@
 //! endpoint. This allows overlaying validation and default behaviour on top
 //! of the wrapped URL.
 
-use std::ops::Deref;
+use std::{ops::Deref, path::PathBuf};
 
+use reqwest::header::HeaderMap;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use url::Url;
 
@@ -45,6 +47,22 @@ impl Deref for Endpoint {
     }
 }
 
+/// A named GraphQL endpoint that operations can target
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TargetEndpoint {
+    /// The URL of the GraphQL endpoint
+    pub url: Url,
+
+    /// List of hard-coded headers to include in GraphQL requests to the endpoint
+    #[serde(default, deserialize_with = "super::config::parsers::map_from_str")]
+    #[schemars(schema_with = "super::schemas::header_map")]
+    pub headers: HeaderMap,
+
+    /// Path to the schema of the endpoint. Operations targeting the endpoint are validated
+    /// against the top-level schema when unset.
+    pub schema: Option<PathBuf>,
+}
+
 mod defaults {
     use url::Url;
 
@@ -1,7 +1,9 @@
-use opentelemetry::{Key, KeyValue};
+use dc_mcp_server::redaction::redactor;
+use opentelemetry::{Key, KeyValue, Value};
 use opentelemetry_sdk::Resource;
 use opentelemetry_sdk::error::OTelSdkResult;
 use opentelemetry_sdk::trace::{SpanData, SpanExporter};
+use std::borrow::Cow;
 use std::collections::HashSet;
 use std::fmt::Debug;
 
@@ -28,6 +30,13 @@ where
         for span in &mut batch {
             span.attributes
                 .retain(|kv| filter_omitted_apollo_attributes(kv, &self.omitted));
+            span.attributes.iter_mut().for_each(redact_attribute);
+            for event in span.events.events.iter_mut() {
+                if let Cow::Owned(name) = redactor().redact_str(&event.name) {
+                    event.name = name.into();
+                }
+                event.attributes.iter_mut().for_each(redact_attribute);
+            }
         }
 
         self.inner.export(batch)
@@ -48,10 +57,18 @@ fn filter_omitted_apollo_attributes(kv: &KeyValue, omitted_attributes: &HashSet<
     !kv.key.as_str().starts_with("apollo.") || !omitted_attributes.contains(&kv.key)
 }
 
+fn redact_attribute(kv: &mut KeyValue) {
+    if let Value::String(value) = &kv.value
+        && let Cow::Owned(redacted) = redactor().redact_str(value.as_str())
+    {
+        kv.value = Value::String(redacted.into());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::runtime::filtering_exporter::FilteringExporter;
-    use opentelemetry::trace::{SpanContext, SpanKind, Status, TraceState};
+    use opentelemetry::trace::{Event, SpanContext, SpanKind, Status, TraceState};
     use opentelemetry::{InstrumentationScope, Key, KeyValue, SpanId, TraceFlags, TraceId};
     use opentelemetry_sdk::Resource;
     use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
@@ -136,6 +153,67 @@ mod tests {
             .expect("Export error");
     }
 
+    #[tokio::test]
+    async fn filtering_exporter_redacts_secrets() {
+        #[derive(Debug)]
+        struct TestExporter {}
+
+        #[cfg_attr(coverage_nightly, coverage(off))]
+        impl SpanExporter for TestExporter {
+            fn export(&self, batch: Vec<SpanData>) -> impl Future<Output = OTelSdkResult> + Send {
+                batch.into_iter().for_each(|span| {
+                    assert!(span.attributes.contains(&KeyValue::new(
+                        "http.request.header.authorization",
+                        "authorization=[REDACTED]"
+                    )));
+                    assert!(
+                        span.attributes
+                            .contains(&KeyValue::new("http.method", "GET"))
+                    );
+                    let event = span.events.events.first().unwrap();
+                    assert_eq!(event.name, "Upstream rejected Bearer [REDACTED]");
+                    assert_eq!(
+                        event.attributes,
+                        vec![KeyValue::new("error", "token [REDACTED] expired")]
+                    );
+                });
+
+                ready(Ok(()))
+            }
+
+            fn shutdown(&mut self) -> OTelSdkResult {
+                Ok(())
+            }
+
+            fn force_flush(&mut self) -> OTelSdkResult {
+                Ok(())
+            }
+
+            fn set_resource(&mut self, _resource: &Resource) {}
+        }
+
+        let mut mock_span_data = create_mock_span_data();
+        mock_span_data.attributes.push(KeyValue::new(
+            "http.request.header.authorization",
+            "authorization=Bearer abc123",
+        ));
+        mock_span_data.events.events.push(Event::new(
+            "Upstream rejected Bearer abc123",
+            SystemTime::UNIX_EPOCH,
+            vec![KeyValue::new(
+                "error",
+                "token eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiIxIn0.sig expired",
+            )],
+            0,
+        ));
+
+        let filtering_exporter = FilteringExporter::new(TestExporter {}, HashSet::new());
+        filtering_exporter
+            .export(vec![mock_span_data])
+            .await
+            .expect("Export error");
+    }
+
     #[tokio::test]
     async fn filtering_exporter_calls_inner_exporter_on_shutdown() {
         #[derive(Debug)]
@@ -1,3 +1,5 @@
+use dc_mcp_server::query_limits::QueryLimitsConfig;
+use dc_mcp_server::schema_filter::SchemaFilterConfig;
 use schemars::JsonSchema;
 use serde::Deserialize;
 
@@ -8,6 +10,9 @@ pub struct Introspection {
     /// Execution configuration for introspection
     pub execute: ExecuteConfig,
 
+    /// Filter for the types and fields exposed by the introspection tools
+    pub filter: SchemaFilterConfig,
+
     /// Introspect configuration for allowing clients to run introspection
     pub introspect: IntrospectConfig,
 
@@ -24,6 +29,9 @@ pub struct Introspection {
 pub struct ExecuteConfig {
     /// Enable introspection for execution
     pub enabled: bool,
+
+    /// Limits for operations run by the execute tool
+    pub limits: QueryLimitsConfig,
 }
 
 /// Introspect-specific introspection configuration
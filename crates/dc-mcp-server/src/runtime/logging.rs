@@ -4,10 +4,11 @@
 //! helper functions
 
 mod defaults;
-mod log_rotation_kind;
 mod parsers;
+mod redacting_writer;
 
-use log_rotation_kind::LogRotationKind;
+use dc_mcp_server::log_rotation_kind::LogRotationKind;
+use redacting_writer::RedactingMakeWriter;
 use schemars::JsonSchema;
 use serde::Deserialize;
 use std::path::PathBuf;
@@ -123,7 +124,7 @@ impl Logging {
 
         Ok((
             tracing_subscriber::fmt::layer()
-                .with_writer(writer)
+                .with_writer(BoxMakeWriter::new(RedactingMakeWriter(writer)))
                 .with_ansi(with_ansi)
                 .with_target(false),
             guard,
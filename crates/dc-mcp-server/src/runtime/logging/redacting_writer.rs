@@ -0,0 +1,56 @@
+use std::io::{self, Write};
+
+use dc_mcp_server::redaction::redactor;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Makes writers that redact secrets from formatted log events before writing them
+pub(super) struct RedactingMakeWriter<M>(pub(super) M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingMakeWriter<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(self.0.make_writer())
+    }
+
+    fn make_writer_for(&'a self, meta: &tracing::Metadata<'_>) -> Self::Writer {
+        RedactingWriter(self.0.make_writer_for(meta))
+    }
+}
+
+/// Redacts each write, which holds a whole formatted event
+pub(super) struct RedactingWriter<W>(W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match std::str::from_utf8(buf) {
+            Ok(event) => {
+                self.0.write_all(redactor().redact_str(event).as_bytes())?;
+                Ok(buf.len())
+            }
+            Err(_) => self.0.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_redacts_log_events() {
+        let mut output = Vec::new();
+        RedactingWriter(&mut output)
+            .write_all(b"DEBUG Sending request with Authorization: Bearer abc123\n")
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "DEBUG Sending request with Authorization: [REDACTED]\n"
+        );
+    }
+}
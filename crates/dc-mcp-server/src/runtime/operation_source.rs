@@ -3,6 +3,8 @@ use std::path::PathBuf;
 use schemars::JsonSchema;
 use serde::Deserialize;
 
+use dc_mcp_server::operations::DEFAULT_INCLUDE;
+
 /// Source for loaded operations
 #[derive(Debug, Default, Deserialize, JsonSchema)]
 #[serde(tag = "source", rename_all = "snake_case")]
@@ -26,7 +28,26 @@ pub enum OperationSource {
     Introspect,
 
     /// Load operations from local GraphQL files / folders
-    Local { paths: Vec<PathBuf> },
+    Local {
+        paths: Vec<PathBuf>,
+
+        /// Globs of the files to load from folders and their subfolders, relative to each folder
+        #[serde(default = "default_include")]
+        include: Vec<String>,
+
+        /// Globs of the files to skip in folders, relative to each folder
+        #[serde(default)]
+        exclude: Vec<String>,
+
+        /// Prefix tool names with the path of the subfolder their file is in
+        #[serde(default)]
+        prefix_tool_names: bool,
+
+        /// The name of the endpoint in `endpoints` that the operations target. Operations
+        /// target the top-level endpoint when unset.
+        #[serde(default)]
+        endpoint: Option<String>,
+    },
 
     /// Load operations from a persisted queries manifest file
     Manifest { path: PathBuf },
@@ -35,6 +56,10 @@ pub enum OperationSource {
     Uplink,
 }
 
+fn default_include() -> Vec<String> {
+    DEFAULT_INCLUDE.map(String::from).to_vec()
+}
+
 /// Either a custom ID or the default variant
 #[derive(Debug, PartialEq, Eq)]
 pub enum IdOrDefault {
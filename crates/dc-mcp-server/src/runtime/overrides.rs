@@ -17,4 +17,10 @@ pub struct Overrides {
 
     /// Set the mutation mode access level for the MCP server
     pub mutation_mode: MutationMode,
+
+    /// Send only the persisted query ID for operations that have one, falling back to the full query when the endpoint doesn't recognize it
+    pub persisted_queries: bool,
+
+    /// The number of tools to return per page when listing tools (default: all tools in one page)
+    pub tool_page_size: Option<usize>,
 }
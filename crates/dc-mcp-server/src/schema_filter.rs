@@ -0,0 +1,415 @@
+//! Filtering of the schema exposed by the introspection tools
+//!
+//! The `introspect`, `search`, `validate` and `execute` tools work with a filtered copy of the
+//! schema, so that sensitive types and fields can neither be discovered nor queried by the
+//! model. Operation tools are still built from the full schema.
+//!
+//! Types are matched by name, and fields, input fields and enum values by `Type.name`, using
+//! glob patterns (`Admin*`, `*.internalNotes`). If any `allow` patterns are configured, only
+//! the types and members they match are kept, and `deny` patterns then hide elements from what
+//! is left. Hiding an element also hides everything that can no longer be used without it:
+//! fields returning hidden types or taking required arguments of hidden types, types left
+//! without fields, members or values, and root operation types that are removed.
+
+use std::collections::{HashMap, HashSet};
+
+use apollo_compiler::{
+    Name, Schema,
+    collections::IndexMap,
+    schema::{Component, ExtendedType, FieldDefinition, InputValueDefinition},
+    validation::Valid,
+};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::errors::ServerError;
+
+/// Configuration for the schema exposed by the introspection tools
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct SchemaFilterConfig {
+    /// Patterns of the types (`Type`) and fields (`Type.field`) to keep. If empty, all
+    /// elements are kept.
+    pub allow: Vec<String>,
+
+    /// Patterns of the types (`Type`) and fields (`Type.field`) to hide
+    pub deny: Vec<String>,
+}
+
+/// A compiled schema filter
+#[derive(Debug, Clone, Default)]
+pub struct SchemaFilter {
+    allow: Option<GlobSet>,
+    deny: GlobSet,
+}
+
+impl SchemaFilter {
+    pub fn new(config: &SchemaFilterConfig) -> Result<Self, globset::Error> {
+        Ok(Self {
+            allow: (!config.allow.is_empty())
+                .then(|| glob_set(&config.allow))
+                .transpose()?,
+            deny: glob_set(&config.deny)?,
+        })
+    }
+
+    /// Whether the filter hides anything
+    pub fn is_enabled(&self) -> bool {
+        self.allow.is_some() || !self.deny.is_empty()
+    }
+
+    /// Filter a schema, failing if what is left isn't a valid schema
+    #[allow(clippy::result_large_err)]
+    pub fn apply(&self, schema: &Valid<Schema>) -> Result<Valid<Schema>, ServerError> {
+        if !self.is_enabled() {
+            return Ok(schema.clone());
+        }
+
+        filter_schema(
+            schema,
+            |name, ty| self.allows_type(name, ty) && !self.deny.is_match(name.as_str()),
+            |type_name, name| self.is_visible_member(type_name, name),
+        )
+    }
+
+    /// Whether a type is allowed, either by name or because some of its members are
+    fn allows_type(&self, name: &Name, ty: &ExtendedType) -> bool {
+        let Some(allow) = &self.allow else {
+            return true;
+        };
+        allow.is_match(name.as_str())
+            || member_names(ty).any(|member| allow.is_match(format!("{name}.{member}")))
+    }
+
+    fn is_visible_member(&self, type_name: &Name, name: &Name) -> bool {
+        let path = format!("{type_name}.{name}");
+        let allowed = self
+            .allow
+            .as_ref()
+            .is_none_or(|allow| allow.is_match(type_name.as_str()) || allow.is_match(&path));
+        allowed && !self.deny.is_match(&path)
+    }
+}
+
+/// Remove the types and members that aren't visible from a schema, along with everything that
+/// can no longer be used without them, failing if what is left isn't a valid schema
+#[allow(clippy::result_large_err)]
+pub(crate) fn filter_schema(
+    schema: &Valid<Schema>,
+    is_visible_type: impl Fn(&Name, &ExtendedType) -> bool,
+    is_visible_member: impl Fn(&Name, &Name) -> bool,
+) -> Result<Valid<Schema>, ServerError> {
+    let mut schema = schema.clone().into_inner();
+    schema
+        .types
+        .retain(|name, ty| ty.is_built_in() || is_visible_type(name, ty));
+    for (type_name, ty) in schema.types.iter_mut() {
+        if ty.is_built_in() {
+            continue;
+        }
+        let is_visible = |name: &Name| is_visible_member(type_name, name);
+        match ty {
+            ExtendedType::Object(object) => {
+                object.make_mut().fields.retain(|name, _| is_visible(name))
+            }
+            ExtendedType::Interface(interface) => interface
+                .make_mut()
+                .fields
+                .retain(|name, _| is_visible(name)),
+            ExtendedType::InputObject(input) => {
+                input.make_mut().fields.retain(|name, _| is_visible(name))
+            }
+            ExtendedType::Enum(enum_type) => enum_type
+                .make_mut()
+                .values
+                .retain(|name, _| is_visible(name)),
+            ExtendedType::Scalar(_) | ExtendedType::Union(_) => {}
+        }
+    }
+
+    // Removing an element can leave others unusable, so repeat until nothing changes
+    loop {
+        let before = size(&schema);
+        remove_dangling_references(&mut schema);
+        if size(&schema) == before {
+            break;
+        }
+    }
+
+    let types = &schema.types;
+    let definition = schema.schema_definition.make_mut();
+    for root in [
+        &mut definition.query,
+        &mut definition.mutation,
+        &mut definition.subscription,
+    ] {
+        if root
+            .as_ref()
+            .is_some_and(|name| !types.contains_key(name.as_str()))
+        {
+            *root = None;
+        }
+    }
+
+    schema
+        .validate()
+        .map_err(|e| ServerError::GraphQLSchema(Box::new(e)))
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet, globset::Error> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    builder.build()
+}
+
+pub(crate) fn member_names(ty: &ExtendedType) -> Box<dyn Iterator<Item = &Name> + '_> {
+    match ty {
+        ExtendedType::Object(object) => Box::new(object.fields.keys()),
+        ExtendedType::Interface(interface) => Box::new(interface.fields.keys()),
+        ExtendedType::InputObject(input) => Box::new(input.fields.keys()),
+        ExtendedType::Enum(enum_type) => Box::new(enum_type.values.keys()),
+        ExtendedType::Scalar(_) | ExtendedType::Union(_) => Box::new(std::iter::empty()),
+    }
+}
+
+/// The number of types and members in a schema, to tell when filtering is done
+fn size(schema: &Schema) -> usize {
+    schema
+        .types
+        .values()
+        .map(|ty| {
+            1 + match ty {
+                ExtendedType::Object(object) => {
+                    object.fields.len() + object.implements_interfaces.len()
+                }
+                ExtendedType::Interface(interface) => {
+                    interface.fields.len() + interface.implements_interfaces.len()
+                }
+                ExtendedType::Union(union_type) => union_type.members.len(),
+                ExtendedType::InputObject(input) => input.fields.len(),
+                ExtendedType::Enum(enum_type) => enum_type.values.len(),
+                ExtendedType::Scalar(_) => 0,
+            }
+        })
+        .sum()
+}
+
+/// Remove the members that refer to removed types, and the types left empty
+fn remove_dangling_references(schema: &mut Schema) {
+    let types: HashSet<Name> = schema.types.keys().cloned().collect();
+    let interface_fields: HashMap<Name, Vec<Name>> = schema
+        .types
+        .iter()
+        .filter_map(|(name, ty)| match ty {
+            ExtendedType::Interface(interface) => {
+                Some((name.clone(), interface.fields.keys().cloned().collect()))
+            }
+            _ => None,
+        })
+        .collect();
+    // Implementations must keep all the fields of their interfaces
+    let implements = |interface: &Name, fields: &IndexMap<Name, Component<FieldDefinition>>| {
+        interface_fields
+            .get(interface)
+            .is_some_and(|interface_fields| interface_fields.iter().all(|f| fields.contains_key(f)))
+    };
+
+    let mut unusable = HashSet::new();
+    for (name, ty) in schema.types.iter_mut() {
+        let is_usable = match ty {
+            ExtendedType::Object(object) => {
+                let object = object.make_mut();
+                retain_usable_fields(&mut object.fields, &types);
+                let fields = &object.fields;
+                object
+                    .implements_interfaces
+                    .retain(|interface| implements(interface, fields));
+                !object.fields.is_empty()
+            }
+            ExtendedType::Interface(interface) => {
+                let interface = interface.make_mut();
+                retain_usable_fields(&mut interface.fields, &types);
+                let fields = &interface.fields;
+                interface
+                    .implements_interfaces
+                    .retain(|other| implements(other, fields));
+                !interface.fields.is_empty()
+            }
+            ExtendedType::Union(union_type) => {
+                let union_type = union_type.make_mut();
+                union_type
+                    .members
+                    .retain(|member| types.contains(member.as_str()));
+                !union_type.members.is_empty()
+            }
+            // An input object can't be built without its required fields
+            ExtendedType::InputObject(input) => {
+                let input = input.make_mut();
+                let is_buildable = input
+                    .fields
+                    .values()
+                    .all(|field| has_type(field, &types) || !is_required(field));
+                input.fields.retain(|_, field| has_type(field, &types));
+                is_buildable && !input.fields.is_empty()
+            }
+            ExtendedType::Enum(enum_type) => !enum_type.values.is_empty(),
+            ExtendedType::Scalar(_) => true,
+        };
+        if !is_usable {
+            unusable.insert(name.clone());
+        }
+    }
+    schema.types.retain(|name, _| !unusable.contains(name));
+}
+
+/// Keep the fields that return a kept type and can be called without removed arguments,
+/// dropping their optional arguments of removed types
+fn retain_usable_fields(
+    fields: &mut IndexMap<Name, Component<FieldDefinition>>,
+    types: &HashSet<Name>,
+) {
+    fields.retain(|_, field| {
+        types.contains(field.ty.inner_named_type())
+            && field
+                .arguments
+                .iter()
+                .all(|argument| has_type(argument, types) || !is_required(argument))
+    });
+    for field in fields.values_mut() {
+        if !field
+            .arguments
+            .iter()
+            .all(|argument| has_type(argument, types))
+        {
+            field
+                .make_mut()
+                .arguments
+                .retain(|argument| has_type(argument, types));
+        }
+    }
+}
+
+fn is_required(input: &InputValueDefinition) -> bool {
+    input.ty.is_non_null() && input.default_value.is_none()
+}
+
+fn has_type(input: &InputValueDefinition, types: &HashSet<Name>) -> bool {
+    types.contains(input.ty.inner_named_type())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+        type Query {
+            user(id: ID!): User
+            invoices(filter: SearchFilter): [Invoice]
+            admin: AdminPanel
+            node(id: ID!): Node
+        }
+        type Mutation {
+            refund(invoice: ID!): Invoice
+        }
+        interface Node {
+            id: ID!
+        }
+        type User implements Node {
+            id: ID!
+            name: String
+            ssn: String
+            invoices: [Invoice]
+            friends(filter: SearchFilter): [User]
+        }
+        type Invoice implements Node {
+            id: ID!
+            amount: Int
+        }
+        input SearchFilter {
+            minAmount: Int
+        }
+        type AdminPanel {
+            users: [User]
+        }
+        union SearchResult = User | Invoice
+    "#;
+
+    fn filter(allow: &[&str], deny: &[&str]) -> Result<Valid<Schema>, ServerError> {
+        let schema = Schema::parse_and_validate(SCHEMA, "schema.graphql").unwrap();
+        SchemaFilter::new(&SchemaFilterConfig {
+            allow: allow.iter().map(ToString::to_string).collect(),
+            deny: deny.iter().map(ToString::to_string).collect(),
+        })
+        .unwrap()
+        .apply(&schema)
+    }
+
+    fn fields(schema: &Schema, type_name: &str) -> Vec<String> {
+        match schema.types.get(type_name) {
+            Some(ExtendedType::Object(object)) => {
+                object.fields.keys().map(ToString::to_string).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn it_hides_denied_types_and_fields() {
+        let schema = filter(&[], &["Invoice", "*.ssn", "Admin*", "SearchFilter"]).unwrap();
+
+        assert_eq!(fields(&schema, "Query"), ["user", "node"]);
+        assert_eq!(fields(&schema, "User"), ["id", "name", "friends"]);
+        assert!(!schema.types.contains_key("Invoice"));
+        assert!(!schema.types.contains_key("AdminPanel"));
+        // Only the optional argument of a hidden type is dropped
+        let ExtendedType::Object(user) = &schema.types["User"] else {
+            panic!("User is not an object");
+        };
+        assert!(user.fields["friends"].arguments.is_empty());
+        // The mutation type is left empty
+        assert!(!schema.types.contains_key("Mutation"));
+        assert!(schema.schema_definition.mutation.is_none());
+        let ExtendedType::Union(search_result) = &schema.types["SearchResult"] else {
+            panic!("SearchResult is not a union");
+        };
+        assert_eq!(search_result.members.len(), 1);
+    }
+
+    #[test]
+    fn it_keeps_only_allowed_types_and_fields() {
+        let schema = filter(&["Query.user", "User", "Node"], &["User.name"]).unwrap();
+
+        assert_eq!(fields(&schema, "Query"), ["user"]);
+        assert_eq!(fields(&schema, "User"), ["id", "ssn", "friends"]);
+        assert!(!schema.types.contains_key("Invoice"));
+        assert!(!schema.types.contains_key("SearchFilter"));
+        assert!(schema.types.contains_key("Node"));
+        assert!(schema.types["String"].is_built_in());
+    }
+
+    #[test]
+    fn it_drops_interfaces_missing_from_implementations() {
+        let schema = filter(&[], &["User.id"]).unwrap();
+
+        let ExtendedType::Object(user) = &schema.types["User"] else {
+            panic!("User is not an object");
+        };
+        assert!(user.implements_interfaces.is_empty());
+    }
+
+    #[test]
+    fn it_fails_when_no_query_is_left() {
+        assert!(filter(&[], &["Query.*"]).is_err());
+    }
+
+    #[test]
+    fn it_leaves_the_schema_alone_when_disabled() {
+        let schema = Schema::parse_and_validate(SCHEMA, "schema.graphql").unwrap();
+
+        assert_eq!(SchemaFilter::default().apply(&schema).unwrap(), schema);
+    }
+}
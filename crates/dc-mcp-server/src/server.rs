@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr};
 use std::sync::Arc;
 
@@ -9,14 +10,24 @@ use serde::Deserialize;
 use tokio::sync::{Mutex, RwLock};
 use url::Url;
 
+use crate::audit::AuditConfig;
 use crate::auth;
+use crate::confirmation::MutationConfirmationConfig;
+use crate::contract::ContractConfig;
 use crate::cors::CorsConfig;
 use crate::custom_scalar_map::CustomScalarMap;
+use crate::error_mapping::ErrorMappingConfig;
 use crate::errors::ServerError;
 use crate::event::Event as ServerEvent;
 use crate::health::HealthCheckConfig;
-use crate::operations::{MutationMode, OperationSource};
+use crate::operations::{MutationMode, OperationSource, ResponseProjection, TargetEndpoint};
+use crate::query_limits::QueryLimitsConfig;
+use crate::rate_limit::RateLimitConfig;
+use crate::response_cache::ResponseCacheConfig;
+use crate::schema_filter::SchemaFilter;
+use crate::subscription::SubscriptionConfig;
 use crate::token_manager::TokenManager;
+use crate::upstream::UpstreamClient;
 
 mod states;
 
@@ -29,8 +40,12 @@ pub struct Server {
     operation_source: OperationSource,
     endpoint: Url,
     headers: HeaderMap,
+    http_client: UpstreamClient,
     shared_headers: Option<Arc<RwLock<HeaderMap>>>,
     execute_introspection: bool,
+    execute_limits: QueryLimitsConfig,
+    schema_filter: SchemaFilter,
+    contract: ContractConfig,
     validate_introspection: bool,
     introspect_introspection: bool,
     introspect_minify: bool,
@@ -39,6 +54,7 @@ pub struct Server {
     explorer_graph_ref: Option<String>,
     custom_scalar_map: Option<CustomScalarMap>,
     mutation_mode: MutationMode,
+    persisted_queries: bool,
     disable_type_description: bool,
     disable_schema_description: bool,
     disable_auth_token_passthrough: bool,
@@ -46,6 +62,15 @@ pub struct Server {
     index_memory_bytes: usize,
     health_check: HealthCheckConfig,
     cors: CorsConfig,
+    projections: HashMap<String, ResponseProjection>,
+    endpoints: HashMap<String, TargetEndpoint>,
+    subscriptions: SubscriptionConfig,
+    response_cache: ResponseCacheConfig,
+    rate_limits: RateLimitConfig,
+    mutation_confirmation: MutationConfirmationConfig,
+    audit: AuditConfig,
+    error_mapping: ErrorMappingConfig,
+    tool_page_size: Option<usize>,
     token_manager: Option<Arc<Mutex<TokenManager>>>,
 }
 
@@ -116,8 +141,12 @@ impl Server {
         operation_source: OperationSource,
         endpoint: Url,
         headers: HeaderMap,
+        http_client: UpstreamClient,
         #[builder(into)] shared_headers: Option<Arc<RwLock<HeaderMap>>>,
         execute_introspection: bool,
+        execute_limits: QueryLimitsConfig,
+        schema_filter: SchemaFilter,
+        contract: ContractConfig,
         validate_introspection: bool,
         introspect_introspection: bool,
         search_introspection: bool,
@@ -126,6 +155,7 @@ impl Server {
         explorer_graph_ref: Option<String>,
         #[builder(required)] custom_scalar_map: Option<CustomScalarMap>,
         mutation_mode: MutationMode,
+        persisted_queries: bool,
         disable_type_description: bool,
         disable_schema_description: bool,
         disable_auth_token_passthrough: bool,
@@ -133,6 +163,15 @@ impl Server {
         index_memory_bytes: usize,
         health_check: HealthCheckConfig,
         cors: CorsConfig,
+        projections: HashMap<String, ResponseProjection>,
+        endpoints: HashMap<String, TargetEndpoint>,
+        subscriptions: SubscriptionConfig,
+        response_cache: ResponseCacheConfig,
+        rate_limits: RateLimitConfig,
+        mutation_confirmation: MutationConfirmationConfig,
+        audit: AuditConfig,
+        error_mapping: ErrorMappingConfig,
+        tool_page_size: Option<usize>,
         token_manager: Option<Arc<Mutex<TokenManager>>>,
     ) -> Self {
         let headers = {
@@ -146,8 +185,12 @@ impl Server {
             operation_source,
             endpoint,
             headers,
+            http_client,
             shared_headers,
             execute_introspection,
+            execute_limits,
+            schema_filter,
+            contract,
             validate_introspection,
             introspect_introspection,
             search_introspection,
@@ -156,6 +199,7 @@ impl Server {
             explorer_graph_ref,
             custom_scalar_map,
             mutation_mode,
+            persisted_queries,
             disable_type_description,
             disable_schema_description,
             disable_auth_token_passthrough,
@@ -163,6 +207,15 @@ impl Server {
             index_memory_bytes,
             health_check,
             cors,
+            projections,
+            endpoints,
+            subscriptions,
+            response_cache,
+            rate_limits,
+            mutation_confirmation,
+            audit,
+            error_mapping,
+            tool_page_size,
             token_manager,
         }
     }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use apollo_compiler::{Schema, validation::Valid};
@@ -9,12 +10,22 @@ use tokio::sync::{Mutex, RwLock};
 use url::Url;
 
 use crate::{
+    audit::AuditConfig,
+    confirmation::MutationConfirmationConfig,
+    contract::ContractConfig,
     cors::CorsConfig,
     custom_scalar_map::CustomScalarMap,
+    error_mapping::ErrorMappingConfig,
     errors::{OperationError, ServerError},
     health::HealthCheckConfig,
-    operations::MutationMode,
+    operations::{MutationMode, ResponseProjection, TargetEndpoint},
+    query_limits::QueryLimitsConfig,
+    rate_limit::RateLimitConfig,
+    response_cache::ResponseCacheConfig,
+    schema_filter::SchemaFilter,
+    subscription::SubscriptionConfig,
     token_manager::TokenManager,
+    upstream::UpstreamClient,
 };
 
 use super::{Server, ServerEvent, Transport};
@@ -38,8 +49,12 @@ struct Config {
     transport: Transport,
     endpoint: Url,
     headers: HeaderMap,
+    http_client: UpstreamClient,
     shared_headers: Option<Arc<RwLock<HeaderMap>>>,
     execute_introspection: bool,
+    execute_limits: QueryLimitsConfig,
+    schema_filter: SchemaFilter,
+    contract: ContractConfig,
     validate_introspection: bool,
     introspect_introspection: bool,
     search_introspection: bool,
@@ -48,6 +63,7 @@ struct Config {
     explorer_graph_ref: Option<String>,
     custom_scalar_map: Option<CustomScalarMap>,
     mutation_mode: MutationMode,
+    persisted_queries: bool,
     disable_type_description: bool,
     disable_schema_description: bool,
     disable_auth_token_passthrough: bool,
@@ -55,6 +71,15 @@ struct Config {
     index_memory_bytes: usize,
     health_check: HealthCheckConfig,
     cors: CorsConfig,
+    projections: HashMap<String, ResponseProjection>,
+    endpoints: HashMap<String, TargetEndpoint>,
+    subscriptions: SubscriptionConfig,
+    response_cache: ResponseCacheConfig,
+    rate_limits: RateLimitConfig,
+    mutation_confirmation: MutationConfirmationConfig,
+    audit: AuditConfig,
+    error_mapping: ErrorMappingConfig,
+    tool_page_size: Option<usize>,
     token_manager: Option<Arc<Mutex<TokenManager>>>,
 }
 
@@ -74,8 +99,12 @@ impl StateMachine {
                 transport: server.transport,
                 endpoint: server.endpoint,
                 headers: server.headers,
+                http_client: server.http_client,
                 shared_headers: server.shared_headers,
                 execute_introspection: server.execute_introspection,
+                execute_limits: server.execute_limits,
+                schema_filter: server.schema_filter,
+                contract: server.contract.clone(),
                 validate_introspection: server.validate_introspection,
                 introspect_introspection: server.introspect_introspection,
                 search_introspection: server.search_introspection,
@@ -84,6 +113,7 @@ impl StateMachine {
                 explorer_graph_ref: server.explorer_graph_ref,
                 custom_scalar_map: server.custom_scalar_map,
                 mutation_mode: server.mutation_mode,
+                persisted_queries: server.persisted_queries,
                 disable_type_description: server.disable_type_description,
                 disable_schema_description: server.disable_schema_description,
                 disable_auth_token_passthrough: server.disable_auth_token_passthrough,
@@ -91,6 +121,15 @@ impl StateMachine {
                 index_memory_bytes: server.index_memory_bytes,
                 health_check: server.health_check,
                 cors: server.cors,
+                projections: server.projections,
+                endpoints: server.endpoints,
+                subscriptions: server.subscriptions,
+                response_cache: server.response_cache,
+                rate_limits: server.rate_limits,
+                mutation_confirmation: server.mutation_confirmation,
+                audit: server.audit,
+                error_mapping: server.error_mapping,
+                tool_page_size: server.tool_page_size,
                 token_manager: server.token_manager,
             },
         });
@@ -99,7 +138,7 @@ impl StateMachine {
             state = match event {
                 ServerEvent::SchemaUpdated(registry_event) => match registry_event {
                     SchemaEvent::UpdateSchema(schema_state) => {
-                        let schema = Self::sdl_to_api_schema(schema_state)?;
+                        let schema = Self::sdl_to_api_schema(schema_state, &server.contract)?;
                         match state {
                             State::Configuring(configuring) => {
                                 configuring.set_schema(schema).await.into()
@@ -162,16 +201,25 @@ impl StateMachine {
         }
     }
 
+    /// Make the API schema of a supergraph, or validate a plain schema, and apply the contract
+    /// to it with the tags of the source schema
     #[allow(clippy::result_large_err)]
-    fn sdl_to_api_schema(schema_state: SchemaState) -> Result<Valid<Schema>, ServerError> {
+    fn sdl_to_api_schema(
+        schema_state: SchemaState,
+        contract: &ContractConfig,
+    ) -> Result<Valid<Schema>, ServerError> {
         match Supergraph::new_with_router_specs(&schema_state.sdl) {
-            Ok(supergraph) => Ok(supergraph
-                .to_api_schema(ApiSchemaOptions::default())
-                .map_err(|e| ServerError::Federation(Box::new(e)))?
-                .schema()
-                .clone()),
-            Err(_) => Schema::parse_and_validate(schema_state.sdl, "schema.graphql")
-                .map_err(|e| ServerError::GraphQLSchema(e.into())),
+            Ok(supergraph) => {
+                let api_schema = supergraph
+                    .to_api_schema(ApiSchemaOptions::default())
+                    .map_err(|e| ServerError::Federation(Box::new(e)))?;
+                contract.apply(supergraph.schema.schema(), api_schema.schema())
+            }
+            Err(_) => {
+                let schema = Schema::parse_and_validate(schema_state.sdl, "schema.graphql")
+                    .map_err(|e| ServerError::GraphQLSchema(e.into()))?;
+                contract.apply(&schema, &schema)
+            }
         }
     }
 
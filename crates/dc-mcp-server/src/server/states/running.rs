@@ -1,7 +1,9 @@
+use std::collections::HashMap;
 use std::ops::Deref as _;
 use std::sync::Arc;
 
-use apollo_compiler::{Schema, validation::Valid};
+use apollo_compiler::{Schema, ast::OperationType, validation::Valid};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
 use headers::HeaderMapExt as _;
 use opentelemetry::trace::FutureExt;
 use opentelemetry::{Context, KeyValue};
@@ -11,7 +13,7 @@ use rmcp::{
     Peer, RoleServer, ServerHandler, ServiceError,
     model::{
         CallToolRequestParam, CallToolResult, ErrorCode, InitializeRequestParam, InitializeResult,
-        ListToolsResult, PaginatedRequestParam, ServerCapabilities, ServerInfo,
+        ListToolsResult, PaginatedRequestParam, ServerCapabilities, ServerInfo, Tool,
     },
     service::RequestContext,
 };
@@ -24,11 +26,15 @@ use url::Url;
 use crate::generated::telemetry::{TelemetryAttribute, TelemetryMetric};
 use crate::meter;
 use crate::{
+    audit::{AuditClient, AuditLog, AuditRecord},
     auth::ValidToken,
+    confirmation::MutationConfirmationConfig,
+    contract::ContractConfig,
     custom_scalar_map::CustomScalarMap,
+    error_mapping::ErrorMappingConfig,
     errors::{McpError, ServerError},
     explorer::{EXPLORER_TOOL_NAME, Explorer},
-    graphql::{self, Executable as _},
+    graphql::{self, Executable as _, StreamingExecutable as _},
     health::HealthCheck,
     introspection::tools::{
         execute::{EXECUTE_TOOL_NAME, Execute},
@@ -36,16 +42,25 @@ use crate::{
         search::{SEARCH_TOOL_NAME, Search},
         validate::{VALIDATE_TOOL_NAME, Validate},
     },
-    operations::{MutationMode, Operation, RawOperation},
+    operations::{MutationMode, Operation, RawOperation, ResponseProjection, TargetEndpoint},
+    rate_limit::RateLimiter,
+    response_cache::{CacheKey, ResponseCache},
+    schema_filter::SchemaFilter,
+    subscription::SubscriptionConfig,
     token_manager::TokenManager,
+    upstream::UpstreamClient,
 };
 
 #[derive(Clone)]
 pub(super) struct Running {
     pub(super) schema: Arc<Mutex<Valid<Schema>>>,
+    pub(super) introspection_schema: Arc<Mutex<Valid<Schema>>>,
+    pub(super) schema_filter: SchemaFilter,
+    pub(super) contract: ContractConfig,
     pub(super) operations: Arc<Mutex<Vec<Operation>>>,
     pub(super) headers: Arc<RwLock<HeaderMap>>,
     pub(super) endpoint: Url,
+    pub(super) http_client: UpstreamClient,
     pub(super) execute_tool: Option<Execute>,
     pub(super) introspect_tool: Option<Introspect>,
     pub(super) search_tool: Option<Search>,
@@ -55,10 +70,20 @@ pub(super) struct Running {
     pub(super) peers: Arc<RwLock<Vec<Peer<RoleServer>>>>,
     pub(super) cancellation_token: CancellationToken,
     pub(super) mutation_mode: MutationMode,
+    pub(super) persisted_queries: bool,
     pub(super) disable_type_description: bool,
     pub(super) disable_schema_description: bool,
     pub(super) disable_auth_token_passthrough: bool,
     pub(super) health_check: Option<HealthCheck>,
+    pub(super) projections: HashMap<String, ResponseProjection>,
+    pub(super) endpoints: HashMap<String, TargetEndpoint>,
+    pub(super) subscriptions: SubscriptionConfig,
+    pub(super) response_cache: Option<ResponseCache>,
+    pub(super) rate_limiter: Option<RateLimiter>,
+    pub(super) mutation_confirmation: MutationConfirmationConfig,
+    pub(super) audit_log: Option<AuditLog>,
+    pub(super) error_mapping: ErrorMappingConfig,
+    pub(super) tool_page_size: Option<usize>,
     pub(super) token_manager: Option<Arc<Mutex<TokenManager>>>,
 }
 
@@ -82,14 +107,18 @@ impl Running {
                         &schema,
                         self.custom_scalar_map.as_ref(),
                         self.mutation_mode,
+                        self.persisted_queries,
                         self.disable_type_description,
                         self.disable_schema_description,
+                        &self.projections,
+                        &self.endpoints,
                     )
                     .unwrap_or_else(|error| {
                         error!("Invalid operation: {}", error);
                         None
                     })
             })
+            .filter(|operation| self.contract.allows(operation, &schema))
             .collect();
 
         debug!(
@@ -98,8 +127,16 @@ impl Running {
             serde_json::to_string_pretty(&operations)?
         );
         *self.operations.lock().await = operations;
+        self.clear_response_cache();
 
-        // Update the schema itself
+        // Update the schema itself, and the filtered schema of the introspection tools
+        let introspection_schema = self.schema_filter.apply(&schema)?;
+        if let Some(execute_tool) = &self.execute_tool {
+            execute_tool.set_schema(introspection_schema.clone());
+        }
+        if self.schema_filter.is_enabled() {
+            *self.introspection_schema.lock().await = introspection_schema;
+        }
         *self.schema.lock().await = schema;
 
         // Notify MCP clients that tools have changed
@@ -112,7 +149,10 @@ impl Running {
         self,
         operations: Vec<RawOperation>,
     ) -> Result<Running, ServerError> {
-        debug!("Operations updated:\n{:?}", operations);
+        debug!(
+            "Operations updated:\n{}",
+            serde_json::to_string_pretty(&operations)?
+        );
 
         // Update the operations based on the current schema
         {
@@ -125,14 +165,18 @@ impl Running {
                             schema,
                             self.custom_scalar_map.as_ref(),
                             self.mutation_mode,
+                            self.persisted_queries,
                             self.disable_type_description,
                             self.disable_schema_description,
+                            &self.projections,
+                            &self.endpoints,
                         )
                         .unwrap_or_else(|error| {
                             error!("Invalid operation: {}", error);
                             None
                         })
                 })
+                .filter(|operation| self.contract.allows(operation, schema))
                 .collect();
 
             debug!(
@@ -142,12 +186,20 @@ impl Running {
             );
             *self.operations.lock().await = updated_operations;
         }
+        self.clear_response_cache();
 
         // Notify MCP clients that tools have changed
         Self::notify_tool_list_changed(self.peers.clone()).await;
         Ok(self)
     }
 
+    /// Drop cached results, which may no longer match the schema or operations
+    fn clear_response_cache(&self) {
+        if let Some(response_cache) = &self.response_cache {
+            response_cache.clear();
+        }
+    }
+
     /// Notify any peers that tools have changed. Drops unreachable peers from the list.
     #[tracing::instrument(skip_all)]
     async fn notify_tool_list_changed(peers: Arc<RwLock<Vec<Peer<RoleServer>>>>) {
@@ -175,45 +227,19 @@ impl Running {
         }
         *peers = retained_peers;
     }
-}
-
-impl ServerHandler for Running {
-    #[tracing::instrument(skip_all, fields(apollo.mcp.client_name = request.client_info.name, apollo.mcp.client_version = request.client_info.version))]
-    async fn initialize(
-        &self,
-        request: InitializeRequestParam,
-        context: RequestContext<RoleServer>,
-    ) -> Result<InitializeResult, McpError> {
-        // Token refresh is deferred to first tool call to avoid blocking initialization
-        // The token will be refreshed automatically when needed before any request
-
-        let meter = &meter::METER;
-        let attributes = vec![
-            KeyValue::new(
-                TelemetryAttribute::ClientName.to_key(),
-                request.client_info.name.clone(),
-            ),
-            KeyValue::new(
-                TelemetryAttribute::ClientVersion.to_key(),
-                request.client_info.version.clone(),
-            ),
-        ];
-        meter
-            .u64_counter(TelemetryMetric::InitializeCount.as_str())
-            .build()
-            .add(1, &attributes);
-        // TODO: how to remove these?
-        let mut peers = self.peers.write().await;
-        peers.push(context.peer);
-        Ok(self.get_info())
-    }
 
-    #[tracing::instrument(skip_all, fields(apollo.mcp.tool_name = request.name.as_ref(), apollo.mcp.request_id = %context.id.clone()))]
-    async fn call_tool(
+    /// Run a tool call, without recording it in the audit log
+    async fn run_tool(
         &self,
         request: CallToolRequestParam,
-        context: RequestContext<RoleServer>,
+        context: &RequestContext<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter
+                .check(&request.name, client_identity(context).as_deref())
+                .map_err(|rejection| rejection.into_error(&request.name))?;
+        }
+
         // Proactively refresh token if needed before executing any tool
         if let Some(token_manager) = &self.token_manager {
             let mut tm = token_manager.lock().await;
@@ -250,12 +276,15 @@ impl ServerHandler for Running {
             }
             EXECUTE_TOOL_NAME => {
                 let mut headers = self.headers.read().await.clone();
+                let mut token_manager = self.token_manager.as_deref();
                 if let Some(axum_parts) = context.extensions.get::<axum::http::request::Parts>() {
                     // Optionally extract the validated token and propagate it to upstream servers if present
                     if !self.disable_auth_token_passthrough
                         && let Some(token) = axum_parts.extensions.get::<ValidToken>()
                     {
                         headers.typed_insert(token.deref().clone());
+                        // The client's token can't be refreshed by the server
+                        token_manager = None;
                     }
 
                     // Forward the mcp-session-id header if present
@@ -264,15 +293,30 @@ impl ServerHandler for Running {
                     }
                 }
 
-                self.execute_tool
+                let execute_tool = self
+                    .execute_tool
                     .as_ref()
-                    .ok_or(tool_not_found(&tool_name))?
-                    .execute(graphql::Request {
-                        input: Value::from(request.arguments.clone()),
-                        endpoint: &self.endpoint,
-                        headers,
-                    })
-                    .await
+                    .ok_or(tool_not_found(&tool_name))?;
+                let input = Value::from(request.arguments.clone());
+                if execute_tool.is_mutation(&input)
+                    && let Some(cancelled) = self
+                        .mutation_confirmation
+                        .confirm(&context.peer, &tool_name, execute_tool, &input)
+                        .await?
+                {
+                    Ok(cancelled)
+                } else {
+                    execute_tool
+                        .execute(graphql::Request {
+                            input,
+                            endpoint: &self.endpoint,
+                            headers,
+                            client: &self.http_client,
+                            error_mapping: &self.error_mapping,
+                            token_manager,
+                        })
+                        .await
+                }
             }
             VALIDATE_TOOL_NAME => {
                 self.validate_tool
@@ -282,13 +326,39 @@ impl ServerHandler for Running {
                     .await
             }
             _ => {
-                let mut headers = self.headers.read().await.clone();
+                let operation = self
+                    .operations
+                    .lock()
+                    .await
+                    .iter()
+                    .find(|op| op.as_ref().name == tool_name)
+                    .cloned()
+                    .ok_or(tool_not_found(&tool_name))?;
+
+                // Operations targeting a named endpoint are sent with its URL and headers, which
+                // don't have the managed access token
+                let mut token_manager = self.token_manager.as_deref();
+                let (endpoint, mut headers) = match operation.endpoint() {
+                    Some(name) => {
+                        let endpoint = self.endpoints.get(name).ok_or_else(|| {
+                            McpError::new(
+                                ErrorCode::INTERNAL_ERROR,
+                                format!("Unknown endpoint: {name}"),
+                                None,
+                            )
+                        })?;
+                        token_manager = None;
+                        (&endpoint.url, endpoint.headers.clone())
+                    }
+                    None => (&self.endpoint, self.headers.read().await.clone()),
+                };
                 if let Some(axum_parts) = context.extensions.get::<axum::http::request::Parts>() {
                     // Optionally extract the validated token and propagate it to upstream servers if present
                     if !self.disable_auth_token_passthrough
                         && let Some(token) = axum_parts.extensions.get::<ValidToken>()
                     {
                         headers.typed_insert(token.deref().clone());
+                        token_manager = None;
                     }
 
                     // Also forward the mcp-session-id header if present
@@ -299,18 +369,79 @@ impl ServerHandler for Running {
 
                 let graphql_request = graphql::Request {
                     input: Value::from(request.arguments.clone()),
-                    endpoint: &self.endpoint,
+                    endpoint,
                     headers,
+                    client: &self.http_client,
+                    error_mapping: &self.error_mapping,
+                    token_manager,
                 };
-                self.operations
-                    .lock()
-                    .await
-                    .iter()
-                    .find(|op| op.as_ref().name == tool_name)
-                    .ok_or(tool_not_found(&tool_name))?
-                    .execute(graphql_request)
-                    .with_context(Context::current())
-                    .await
+                // Dry runs don't reach the endpoint, so they don't need confirming
+                let dry_run = operation.is_dry_run(&graphql_request.input)?;
+                let cancelled = if !dry_run && operation.operation_type() == OperationType::Mutation
+                {
+                    self.mutation_confirmation
+                        .confirm(
+                            &context.peer,
+                            &tool_name,
+                            &operation,
+                            &graphql_request.input,
+                        )
+                        .await?
+                } else {
+                    None
+                };
+
+                if dry_run {
+                    let schema = self.schema.lock().await;
+                    operation.dry_run(graphql_request.input, &schema)
+                } else if let Some(cancelled) = cancelled {
+                    Ok(cancelled)
+                } else if operation.operation_type() == OperationType::Subscription {
+                    // Subscriptions can run for a while, so the operations lock must not be held
+                    let progress =
+                        context
+                            .meta
+                            .get_progress_token()
+                            .map(|token| graphql::Progress {
+                                peer: context.peer.clone(),
+                                token,
+                            });
+                    operation
+                        .execute_stream(
+                            graphql_request,
+                            &self.subscriptions,
+                            progress,
+                            context.ct.clone(),
+                        )
+                        .with_context(Context::current())
+                        .await
+                } else {
+                    // Only queries are cached, so mutations always reach the endpoint
+                    let cache = self
+                        .response_cache
+                        .as_ref()
+                        .filter(|_| operation.operation_type() == OperationType::Query)
+                        .map(|cache| {
+                            let key = CacheKey::new(
+                                &tool_name,
+                                &graphql_request.input,
+                                &graphql_request.headers,
+                            );
+                            (cache, key)
+                        });
+                    if let Some(result) = cache.as_ref().and_then(|(cache, key)| cache.get(key)) {
+                        Ok(result)
+                    } else {
+                        let result = operation
+                            .execute(graphql_request)
+                            .with_context(Context::current())
+                            .await;
+                        if let (Some((cache, key)), Ok(result)) = (cache, &result) {
+                            cache.insert(key, operation.operation_name(), result);
+                        }
+                        result
+                    }
+                }
             }
         };
 
@@ -338,11 +469,67 @@ impl ServerHandler for Running {
 
         result
     }
+}
+
+impl ServerHandler for Running {
+    #[tracing::instrument(skip_all, fields(apollo.mcp.client_name = request.client_info.name, apollo.mcp.client_version = request.client_info.version))]
+    async fn initialize(
+        &self,
+        request: InitializeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<InitializeResult, McpError> {
+        // Token refresh is deferred to first tool call to avoid blocking initialization
+        // The token will be refreshed automatically when needed before any request
+
+        let meter = &meter::METER;
+        let attributes = vec![
+            KeyValue::new(
+                TelemetryAttribute::ClientName.to_key(),
+                request.client_info.name.clone(),
+            ),
+            KeyValue::new(
+                TelemetryAttribute::ClientVersion.to_key(),
+                request.client_info.version.clone(),
+            ),
+        ];
+        meter
+            .u64_counter(TelemetryMetric::InitializeCount.as_str())
+            .build()
+            .add(1, &attributes);
+        // TODO: how to remove these?
+        let mut peers = self.peers.write().await;
+        peers.push(context.peer);
+        Ok(self.get_info())
+    }
+
+    #[tracing::instrument(skip_all, fields(apollo.mcp.tool_name = request.name.as_ref(), apollo.mcp.request_id = %context.id.clone()))]
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let Some(audit_log) = &self.audit_log else {
+            return self.run_tool(request, &context).await;
+        };
+
+        let start = std::time::Instant::now();
+        let tool_name = request.name.to_string();
+        let arguments = Value::from(request.arguments.clone());
+        let result = self.run_tool(request, &context).await;
+        audit_log.record(AuditRecord::new(
+            audit_client(&context),
+            tool_name,
+            arguments,
+            &result,
+            start.elapsed(),
+        ));
+        result
+    }
 
     #[tracing::instrument(skip_all)]
     async fn list_tools(
         &self,
-        _request: Option<PaginatedRequestParam>,
+        request: Option<PaginatedRequestParam>,
         _context: RequestContext<RoleServer>,
     ) -> Result<ListToolsResult, McpError> {
         let meter = &meter::METER;
@@ -350,21 +537,26 @@ impl ServerHandler for Running {
             .u64_counter(TelemetryMetric::ListToolsCount.as_str())
             .build()
             .add(1, &[]);
-        Ok(ListToolsResult {
-            next_cursor: None,
-            tools: self
-                .operations
-                .lock()
-                .await
-                .iter()
-                .map(|op| op.as_ref().clone())
-                .chain(self.execute_tool.as_ref().iter().map(|e| e.tool.clone()))
-                .chain(self.introspect_tool.as_ref().iter().map(|e| e.tool.clone()))
-                .chain(self.search_tool.as_ref().iter().map(|e| e.tool.clone()))
-                .chain(self.explorer_tool.as_ref().iter().map(|e| e.tool.clone()))
-                .chain(self.validate_tool.as_ref().iter().map(|e| e.tool.clone()))
-                .collect(),
-        })
+        let tools = self
+            .operations
+            .lock()
+            .await
+            .iter()
+            .map(|op| op.as_ref().clone())
+            .chain(self.execute_tool.as_ref().iter().map(|e| e.tool.clone()))
+            .chain(self.introspect_tool.as_ref().iter().map(|e| e.tool.clone()))
+            .chain(self.search_tool.as_ref().iter().map(|e| e.tool.clone()))
+            .chain(self.explorer_tool.as_ref().iter().map(|e| e.tool.clone()))
+            .chain(self.validate_tool.as_ref().iter().map(|e| e.tool.clone()))
+            .collect();
+
+        match self.tool_page_size.filter(|page_size| *page_size > 0) {
+            Some(page_size) => paginate_tools(tools, request.and_then(|r| r.cursor), page_size),
+            None => Ok(ListToolsResult {
+                next_cursor: None,
+                tools,
+            }),
+        }
     }
 
     fn get_info(&self) -> ServerInfo {
@@ -412,6 +604,85 @@ fn tool_not_found(name: &str) -> McpError {
     )
 }
 
+/// Identify the client making a request, for per-client rate limits. The subject of a validated
+/// token is preferred, then the MCP session, then the client name sent when initializing.
+fn client_identity(context: &RequestContext<RoleServer>) -> Option<String> {
+    let parts = context.extensions.get::<axum::http::request::Parts>();
+    parts
+        .and_then(|parts| parts.extensions.get::<ValidToken>())
+        .map(|token| format!("sub:{}", token.subject()))
+        .or_else(|| {
+            parts
+                .and_then(|parts| parts.headers.get("mcp-session-id"))
+                .and_then(|session_id| session_id.to_str().ok())
+                .map(|session_id| format!("session:{session_id}"))
+        })
+        .or_else(|| {
+            context
+                .peer
+                .peer_info()
+                .map(|info| format!("client:{}", info.client_info.name))
+        })
+}
+
+/// The client making a tool call, as recorded in the audit log
+fn audit_client(context: &RequestContext<RoleServer>) -> AuditClient {
+    let client_info = context.peer.peer_info().map(|info| &info.client_info);
+    AuditClient {
+        name: client_info.map(|info| info.name.clone()),
+        version: client_info.map(|info| info.version.clone()),
+        subject: context
+            .extensions
+            .get::<axum::http::request::Parts>()
+            .and_then(|parts| parts.extensions.get::<ValidToken>())
+            .map(|token| token.subject().to_string()),
+    }
+}
+
+/// Return a page of tools, ordered by name.
+///
+/// The cursor is the name of the last tool on the previous page, so that a page picks up
+/// where the previous one left off even if tools were added or removed in between.
+fn paginate_tools(
+    mut tools: Vec<Tool>,
+    cursor: Option<String>,
+    page_size: usize,
+) -> Result<ListToolsResult, McpError> {
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let start = match cursor {
+        Some(cursor) => {
+            let after = URL_SAFE_NO_PAD
+                .decode(cursor)
+                .ok()
+                .and_then(|name| String::from_utf8(name).ok())
+                .ok_or_else(|| {
+                    McpError::new(
+                        ErrorCode::INVALID_PARAMS,
+                        "Invalid cursor".to_string(),
+                        None,
+                    )
+                })?;
+            tools.partition_point(|tool| *tool.name <= *after)
+        }
+        None => 0,
+    };
+
+    let tools: Vec<Tool> = tools.into_iter().skip(start).collect();
+    let next_cursor = if tools.len() > page_size {
+        tools
+            .get(page_size - 1)
+            .map(|tool| URL_SAFE_NO_PAD.encode(tool.name.as_bytes()))
+    } else {
+        None
+    };
+
+    Ok(ListToolsResult {
+        next_cursor,
+        tools: tools.into_iter().take(page_size).collect(),
+    })
+}
+
 fn convert_arguments<T: serde::de::DeserializeOwned>(
     arguments: CallToolRequestParam,
 ) -> Result<T, McpError> {
@@ -430,11 +701,16 @@ mod tests {
             .validate()
             .unwrap();
 
+        let schema = Arc::new(Mutex::new(schema));
         let running = Running {
-            schema: Arc::new(Mutex::new(schema)),
+            schema: schema.clone(),
+            introspection_schema: schema,
+            schema_filter: SchemaFilter::default(),
+            contract: ContractConfig::default(),
             operations: Arc::new(Mutex::new(vec![])),
             headers: Arc::new(RwLock::new(HeaderMap::new())),
             endpoint: "http://localhost:4000".parse().unwrap(),
+            http_client: UpstreamClient::default(),
             execute_tool: None,
             introspect_tool: None,
             search_tool: None,
@@ -444,10 +720,20 @@ mod tests {
             peers: Arc::new(RwLock::new(vec![])),
             cancellation_token: CancellationToken::new(),
             mutation_mode: MutationMode::None,
+            persisted_queries: false,
             disable_type_description: false,
             disable_schema_description: false,
             disable_auth_token_passthrough: false,
             health_check: None,
+            projections: HashMap::new(),
+            endpoints: HashMap::new(),
+            subscriptions: Default::default(),
+            response_cache: None,
+            rate_limiter: None,
+            mutation_confirmation: MutationConfirmationConfig::default(),
+            audit_log: None,
+            error_mapping: ErrorMappingConfig::default(),
+            tool_page_size: None,
             token_manager: None,
         };
 
@@ -472,4 +758,47 @@ mod tests {
         assert_eq!(updated_operations.len(), 1);
         assert_eq!(updated_operations.first().unwrap().as_ref().name, "Valid");
     }
+
+    fn tools(names: &[&'static str]) -> Vec<Tool> {
+        names
+            .iter()
+            .map(|name| Tool::new(*name, "", serde_json::Map::new()))
+            .collect()
+    }
+
+    fn names(result: &ListToolsResult) -> Vec<&str> {
+        result.tools.iter().map(|tool| tool.name.as_ref()).collect()
+    }
+
+    #[test]
+    fn paginate_tools_returns_pages_in_name_order() {
+        let all = tools(&["e", "c", "a", "d", "b"]);
+
+        let first = paginate_tools(all.clone(), None, 2).unwrap();
+        assert_eq!(names(&first), vec!["a", "b"]);
+
+        let second = paginate_tools(all.clone(), first.next_cursor, 2).unwrap();
+        assert_eq!(names(&second), vec!["c", "d"]);
+
+        let last = paginate_tools(all, second.next_cursor, 2).unwrap();
+        assert_eq!(names(&last), vec!["e"]);
+        assert_eq!(last.next_cursor, None);
+    }
+
+    #[test]
+    fn paginate_tools_cursor_survives_tool_list_changes() {
+        let first = paginate_tools(tools(&["a", "b", "c", "d"]), None, 2).unwrap();
+        assert_eq!(names(&first), vec!["a", "b"]);
+
+        // The last tool on the page is removed, and tools are added on either side of it
+        let second =
+            paginate_tools(tools(&["a", "aa", "bb", "c", "d"]), first.next_cursor, 2).unwrap();
+        assert_eq!(names(&second), vec!["bb", "c"]);
+    }
+
+    #[test]
+    fn paginate_tools_rejects_invalid_cursor() {
+        let result = paginate_tools(tools(&["a"]), Some("not a cursor!".to_string()), 2);
+        assert_eq!(result.unwrap_err().code, ErrorCode::INVALID_PARAMS);
+    }
 }
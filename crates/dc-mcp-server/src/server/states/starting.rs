@@ -17,6 +17,7 @@ use tower_http::trace::TraceLayer;
 use tracing::{Instrument as _, debug, error, info, trace};
 
 use crate::{
+    audit::AuditLog,
     auth,
     errors::ServerError,
     explorer::Explorer,
@@ -25,6 +26,8 @@ use crate::{
         execute::Execute, introspect::Introspect, search::Search, validate::Validate,
     },
     operations::{MutationMode, RawOperation},
+    rate_limit::RateLimiter,
+    response_cache::ResponseCache,
     server::Transport,
 };
 
@@ -49,14 +52,18 @@ impl Starting {
                         &self.schema,
                         self.config.custom_scalar_map.as_ref(),
                         self.config.mutation_mode,
+                        self.config.persisted_queries,
                         self.config.disable_type_description,
                         self.config.disable_schema_description,
+                        &self.config.projections,
+                        &self.config.endpoints,
                     )
                     .unwrap_or_else(|error| {
                         error!("Invalid operation: {}", error);
                         None
                     })
             })
+            .filter(|operation| self.config.contract.allows(operation, &self.schema))
             .collect();
 
         debug!(
@@ -65,16 +72,26 @@ impl Starting {
             serde_json::to_string_pretty(&operations)?
         );
 
-        let execute_tool = self
-            .config
-            .execute_introspection
-            .then(|| Execute::new(self.config.mutation_mode));
+        // The introspection tools only see the filtered schema
+        let introspection_schema = self.config.schema_filter.apply(&self.schema)?;
+
+        let execute_tool = self.config.execute_introspection.then(|| {
+            let execute = Execute::new(self.config.mutation_mode).with_limits(
+                self.config.execute_limits.clone(),
+                introspection_schema.clone(),
+            );
+            if self.config.schema_filter.is_enabled() {
+                execute.with_validation(introspection_schema.clone())
+            } else {
+                execute
+            }
+        });
 
         let root_query_type = self
             .config
             .introspect_introspection
             .then(|| {
-                self.schema
+                introspection_schema
                     .root_operation(OperationType::Query)
                     .map(Name::as_str)
                     .map(|s| s.to_string())
@@ -86,7 +103,7 @@ impl Starting {
             .then(|| {
                 matches!(self.config.mutation_mode, MutationMode::All)
                     .then(|| {
-                        self.schema
+                        introspection_schema
                             .root_operation(OperationType::Mutation)
                             .map(Name::as_str)
                             .map(|s| s.to_string())
@@ -95,9 +112,14 @@ impl Starting {
             })
             .flatten();
         let schema = Arc::new(Mutex::new(self.schema));
+        let introspection_schema = if self.config.schema_filter.is_enabled() {
+            Arc::new(Mutex::new(introspection_schema))
+        } else {
+            schema.clone()
+        };
         let introspect_tool = self.config.introspect_introspection.then(|| {
             Introspect::new(
-                schema.clone(),
+                introspection_schema.clone(),
                 root_query_type,
                 root_mutation_type,
                 self.config.introspect_minify,
@@ -106,10 +128,10 @@ impl Starting {
         let validate_tool = self
             .config
             .validate_introspection
-            .then(|| Validate::new(schema.clone()));
+            .then(|| Validate::new(introspection_schema.clone()));
         let search_tool = if self.config.search_introspection {
             Some(Search::new(
-                schema.clone(),
+                introspection_schema.clone(),
                 matches!(self.config.mutation_mode, MutationMode::All),
                 self.config.search_leaf_depth,
                 self.config.index_memory_bytes,
@@ -133,18 +155,44 @@ impl Starting {
                     stateful_mode: _,
                 },
                 true,
-            ) => Some(HealthCheck::new(self.config.health_check.clone())),
+            ) => Some(
+                HealthCheck::new(self.config.health_check.clone())
+                    .with_circuit_breaker(self.config.http_client.circuit_breaker().clone()),
+            ),
             _ => None, // No health check for SSE, Stdio, or when disabled
         };
 
+        let response_cache = self
+            .config
+            .response_cache
+            .enabled
+            .then(|| ResponseCache::new(self.config.response_cache.clone()));
+
+        let rate_limiter = self
+            .config
+            .rate_limits
+            .is_enabled()
+            .then(|| RateLimiter::new(self.config.rate_limits.clone()));
+
+        let audit_log = self
+            .config
+            .audit
+            .is_enabled()
+            .then(|| AuditLog::new(&self.config.audit))
+            .transpose()?;
+
         let running = Running {
             schema,
+            introspection_schema,
+            schema_filter: self.config.schema_filter,
+            contract: self.config.contract,
             operations: Arc::new(Mutex::new(operations)),
             headers: self
                 .config
                 .shared_headers
                 .unwrap_or_else(|| Arc::new(RwLock::new(self.config.headers))),
             endpoint: self.config.endpoint,
+            http_client: self.config.http_client,
             execute_tool,
             introspect_tool,
             search_tool,
@@ -154,10 +202,20 @@ impl Starting {
             peers,
             cancellation_token: cancellation_token.clone(),
             mutation_mode: self.config.mutation_mode,
+            persisted_queries: self.config.persisted_queries,
             disable_type_description: self.config.disable_type_description,
             disable_schema_description: self.config.disable_schema_description,
             disable_auth_token_passthrough: self.config.disable_auth_token_passthrough,
             health_check: health_check.clone(),
+            projections: self.config.projections,
+            endpoints: self.config.endpoints,
+            subscriptions: self.config.subscriptions,
+            response_cache,
+            rate_limiter,
+            mutation_confirmation: self.config.mutation_confirmation,
+            audit_log,
+            error_mapping: self.config.error_mapping,
+            tool_page_size: self.config.tool_page_size,
             token_manager: self.config.token_manager.clone(),
         };
 
@@ -353,6 +411,7 @@ mod tests {
     use url::Url;
 
     use crate::health::HealthCheckConfig;
+    use crate::upstream::UpstreamClient;
 
     use super::*;
 
@@ -368,8 +427,13 @@ mod tests {
                 },
                 endpoint: Url::parse("http://localhost:4000").expect("valid url"),
                 mutation_mode: MutationMode::All,
+                persisted_queries: false,
                 execute_introspection: true,
+                execute_limits: Default::default(),
+                schema_filter: Default::default(),
+                contract: Default::default(),
                 headers: HeaderMap::new(),
+                http_client: UpstreamClient::default(),
                 shared_headers: None,
                 validate_introspection: true,
                 introspect_introspection: true,
@@ -388,6 +452,15 @@ mod tests {
                     ..Default::default()
                 },
                 cors: Default::default(),
+                projections: Default::default(),
+                endpoints: Default::default(),
+                subscriptions: Default::default(),
+                response_cache: Default::default(),
+                rate_limits: Default::default(),
+                mutation_confirmation: Default::default(),
+                audit: Default::default(),
+                error_mapping: Default::default(),
+                tool_page_size: None,
                 token_manager: None,
             },
             schema: Schema::parse_and_validate("type Query { hello: String }", "test.graphql")
@@ -1,9 +1,11 @@
 //! Startup and initialization functions for Apollo MCP Server
 
-use crate::config_manager::ConfigManager;
 use crate::errors::McpError;
 use crate::token_manager::TokenManager;
+use crate::token_provider::{DoControlConfig, TokenProviderConfig, TokenRefreshConfig};
+use crate::token_store::{TokenStore, TokenStoreConfig};
 use reqwest::header::HeaderMap;
+use rmcp::model::ErrorCode;
 use std::env;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -13,65 +15,74 @@ use tracing::{info, warn};
 /// Returns the TokenManager which will refresh tokens when needed before requests
 /// This function proactively refreshes the token at startup to ensure headers are populated
 pub async fn create_token_manager(
-    config_path: String,
-    refresh_token: String,
-    refresh_url: String,
-    _graphql_endpoint: String,
+    provider: &TokenProviderConfig,
+    token_store: Arc<dyn TokenStore>,
     shared_headers: Arc<RwLock<HeaderMap>>,
+    http_client: reqwest::Client,
 ) -> Result<TokenManager, McpError> {
     info!("🎯 Apollo MCP Server initializing with token refresh...");
-    info!("📝 Config path: {}", config_path);
-    info!("🔗 Refresh URL: {}", refresh_url);
+    info!("🔗 Token provider: {}", provider.name());
 
-    // Step 1: Create shared config manager
-    info!("Step 1: Creating config manager...");
-    let config_manager = Arc::new(ConfigManager::new(config_path.clone()));
-
-    // Config verification is optional - don't block startup if it fails or hangs
-    // The config will be verified when actually needed (during token refresh)
+    // Step 1: Verify the token store can be read, and load the refresh token it keeps
+    // Verification is optional unless the provider rotates refresh tokens - don't block
+    // startup if it fails or hangs otherwise
     // Use tokio::spawn with timeout to prevent hanging on slow file systems
-    info!("Step 1a: Verifying config (non-blocking with timeout)...");
-    let config_path_clone = config_path.clone();
+    info!("Step 1: Verifying token store (non-blocking with timeout)...");
+    let store = Arc::clone(&token_store);
     let verify_result = tokio::time::timeout(
         tokio::time::Duration::from_secs(2),
         tokio::task::spawn_blocking(move || {
-            let cm = ConfigManager::new(config_path_clone);
-            cm.verify_config()
+            store.load()?;
+            store.load_refresh_token()
         }),
     )
     .await;
 
-    match verify_result {
-        Ok(Ok(Ok(()))) => {
-            info!("✅ Config verified");
+    let verified = match verify_result {
+        Ok(Ok(result)) => result.map_err(|e| e.to_string()),
+        Ok(Err(_)) => Err(String::from("the verification task was cancelled")),
+        Err(_) => Err(String::from("the verification timed out after 2s")),
+    };
+    let rotated_refresh_token = match verified {
+        Ok(refresh_token) => {
+            info!("✅ Token store verified");
+            refresh_token
+        }
+        // The authorization server may have revoked the configured refresh token after
+        // rotating it, so it can't stand in for a rotated one that couldn't be loaded
+        Err(e) if provider.rotates_refresh_tokens() => {
+            return Err(McpError::new(
+                ErrorCode::INTERNAL_ERROR,
+                format!("Failed to load the rotated refresh token from the token store: {e}"),
+                None,
+            ));
         }
-        Ok(Ok(Err(e))) => {
+        Err(e) => {
             warn!(
-                "⚠️  Config verification failed: {} (will retry when needed)",
+                "⚠️  Token store verification failed: {} (will retry when saving tokens)",
                 e
             );
+            None
         }
-        Ok(Err(_)) => {
-            warn!("⚠️  Config verification task was cancelled (will retry when needed)");
-        }
-        Err(_) => {
-            warn!("⚠️  Config verification timed out after 2s (will retry when needed)");
-        }
-    }
+    };
 
-    // Step 2: Initialize token manager with injected config manager and headers
+    // Step 2: Initialize token manager with injected token store and headers
     info!("Step 2: Creating token manager...");
-    let mut token_manager = TokenManager::new(refresh_token, refresh_url)?;
+    let mut token_manager = TokenManager::new(provider.provider(rotated_refresh_token)?);
     info!("✅ Token manager created");
 
-    info!("Step 2a: Setting config manager...");
-    token_manager.set_config_manager(Arc::clone(&config_manager));
-    info!("✅ Config manager set");
+    info!("Step 2a: Setting token store...");
+    token_manager.set_token_store(token_store);
+    info!("✅ Token store set");
 
     info!("Step 2b: Setting headers...");
     token_manager.set_headers(Arc::clone(&shared_headers));
     info!("✅ Headers set");
 
+    info!("Step 2c: Setting HTTP client...");
+    token_manager.set_client(http_client);
+    info!("✅ HTTP client set");
+
     // Token refresh is deferred to first tool call to avoid blocking startup
     // The token will be refreshed automatically when needed before any request
     info!("✅ Apollo MCP Server token manager ready for on-demand refresh");
@@ -96,6 +107,61 @@ pub fn get_refresh_url() -> Option<String> {
     env::var("DC_REFRESH_URL").ok()
 }
 
+/// Configure token refresh with environment variables, for servers without a `token_refresh`
+/// section in their config. When `DC_TOKEN_REFRESH_ENABLED` is `true`, tokens are refreshed
+/// with DoControl's refresh endpoint at `DC_REFRESH_URL` using `DC_REFRESH_TOKEN`. They are kept
+/// in the store selected by `DC_TOKEN_STORE`: `config` (the default) to keep the token in the
+/// config file, `file` to keep it in the file at `DC_TOKEN_FILE`, or `memory` to not keep it
+/// anywhere else.
+pub fn token_refresh_from_env() -> Result<Option<TokenRefreshConfig>, McpError> {
+    if !is_token_refresh_enabled() {
+        return Ok(None);
+    }
+    let (Some(refresh_token), Some(refresh_url)) = (get_refresh_token(), get_refresh_url()) else {
+        warn!(
+            "Token refresh enabled but missing required environment variables (DC_REFRESH_TOKEN, DC_REFRESH_URL)"
+        );
+        return Ok(None);
+    };
+    let refresh_url = refresh_url.parse().map_err(|e| {
+        McpError::new(
+            ErrorCode::INVALID_PARAMS,
+            format!("Invalid DC_REFRESH_URL: {e}"),
+            None,
+        )
+    })?;
+
+    let store = match env::var("DC_TOKEN_STORE").ok().as_deref() {
+        None | Some("config") => TokenStoreConfig::Config,
+        Some("file") => {
+            let path = env::var("DC_TOKEN_FILE").map_err(|_| {
+                McpError::new(
+                    ErrorCode::INVALID_PARAMS,
+                    "DC_TOKEN_FILE must be set when DC_TOKEN_STORE is \"file\"".to_string(),
+                    None,
+                )
+            })?;
+            TokenStoreConfig::File { path: path.into() }
+        }
+        Some("memory") => TokenStoreConfig::Memory,
+        Some(other) => {
+            return Err(McpError::new(
+                ErrorCode::INVALID_PARAMS,
+                format!("Unknown token store: {other}. Supported stores are: config, file, memory"),
+                None,
+            ));
+        }
+    };
+
+    Ok(Some(TokenRefreshConfig {
+        provider: TokenProviderConfig::DoControl(DoControlConfig {
+            refresh_url,
+            refresh_token,
+        }),
+        store,
+    }))
+}
+
 /// Get GraphQL endpoint from environment
 pub fn get_graphql_endpoint() -> Option<String> {
     env::var("DC_GRAPHQL_ENDPOINT").ok()
@@ -104,8 +170,8 @@ pub fn get_graphql_endpoint() -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config_manager::ConfigManager;
-    use crate::token_manager::TokenManager;
+    use crate::token_provider::RefreshTokenConfig;
+    use crate::token_store::{ConfigFileTokenStore, RotatedRefreshToken};
     use std::fs;
     use tempfile::TempDir;
 
@@ -123,7 +189,7 @@ headers:
 "#;
         fs::write(&config_path, initial_config).unwrap();
 
-        let _config_manager = ConfigManager::new(config_path.to_string_lossy().to_string());
+        let _token_store = ConfigFileTokenStore::new(&config_path);
 
         // Test environment variable setup
         unsafe {
@@ -179,15 +245,70 @@ headers:
     /// Test token manager integration
     #[tokio::test]
     async fn test_token_manager_integration() {
-        let refresh_token = "test_refresh_token";
-        let refresh_url = "https://api.example.com/refresh";
+        let temp_dir = TempDir::new().unwrap();
+        let token_path = temp_dir.path().join("token");
+        let store = TokenStoreConfig::File {
+            path: token_path.clone(),
+        }
+        .store(None);
+        store
+            .save_refresh_token(&RotatedRefreshToken::new(
+                "rotated_refresh_token",
+                "test_refresh_token",
+            ))
+            .unwrap();
 
-        // Test creating token manager
-        let token_manager = TokenManager::new(refresh_token.to_string(), refresh_url.to_string());
-        assert!(token_manager.is_ok());
+        let mut server = mockito::Server::new_async().await;
+        let refresh = server
+            .mock("POST", "/token")
+            .match_body(mockito::Matcher::UrlEncoded(
+                "refresh_token".into(),
+                "rotated_refresh_token".into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"access_token":"new_token","token_type":"Bearer","expires_in":300}"#)
+            .create_async()
+            .await;
+        let provider = TokenProviderConfig::RefreshToken(RefreshTokenConfig {
+            token_endpoint: Some(format!("{}/token", server.url()).parse().unwrap()),
+            issuer: None,
+            client_id: None,
+            client_secret: None,
+            client_auth: Default::default(),
+            refresh_token: "test_refresh_token".to_string(),
+            scopes: Vec::new(),
+        });
+        let headers = Arc::new(RwLock::new(HeaderMap::new()));
 
-        let token_manager = token_manager.unwrap();
-        assert_eq!(token_manager.refresh_token(), refresh_token);
-        assert_eq!(token_manager.refresh_url(), refresh_url);
+        // The refresh token rotated before the restart replaces the configured one
+        let mut token_manager = create_token_manager(
+            &provider,
+            Arc::clone(&store),
+            Arc::clone(&headers),
+            reqwest::Client::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(token_manager.get_valid_token().await.unwrap(), "new_token");
+        assert_eq!(
+            headers.read().await.get("authorization").unwrap(),
+            "Bearer new_token"
+        );
+        assert_eq!(fs::read_to_string(&token_path).unwrap(), "new_token\n");
+        refresh.assert_async().await;
+
+        // A rotated refresh token that can't be loaded isn't replaced by the configured one
+        fs::write(temp_dir.path().join("token.refresh"), "not json").unwrap();
+        let error = create_token_manager(&provider, store, headers, reqwest::Client::new())
+            .await
+            .err()
+            .unwrap();
+        assert!(
+            error
+                .message
+                .starts_with("Failed to load the rotated refresh token from the token store")
+        );
     }
 }
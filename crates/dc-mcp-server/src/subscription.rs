@@ -0,0 +1,189 @@
+//! GraphQL subscriptions
+//!
+//! Subscription operations are exposed as tools that open a subscription against
+//! the upstream GraphQL endpoint, collect events until a configured limit is reached,
+//! and stream each event back to the client as it arrives.
+//!
+//! Two transport protocols are supported:
+//! - [graphql-ws](https://github.com/enisdenjo/graphql-ws/blob/master/PROTOCOL.md) (`graphql-transport-ws`)
+//! - [graphql-sse](https://github.com/enisdenjo/graphql-sse/blob/master/PROTOCOL.md) in distinct connections mode
+
+use std::time::Duration;
+
+use futures::StreamExt as _;
+use futures::stream::{self, BoxStream};
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use rmcp::model::ErrorCode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use url::Url;
+
+use crate::error_mapping::UNAUTHENTICATED_CODE;
+use crate::errors::McpError;
+use crate::upstream::UpstreamClient;
+
+mod graphql_sse;
+mod graphql_ws;
+
+/// A stream of subscription events, each being a GraphQL response
+pub(crate) type EventStream = BoxStream<'static, Result<Value, McpError>>;
+
+/// Why a subscription tool call stopped collecting events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionCompletion {
+    /// The configured timeout elapsed
+    Timeout,
+
+    /// The tool call was cancelled
+    Cancelled,
+
+    /// The configured number of events was collected
+    MaxEvents,
+
+    /// The subscription failed after events were collected
+    Error,
+
+    /// The server completed the subscription
+    Complete,
+}
+
+/// The protocol used to open subscriptions against the upstream endpoint
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionProtocol {
+    /// The `graphql-transport-ws` WebSocket protocol
+    #[default]
+    GraphqlWs,
+
+    /// The GraphQL over Server-Sent Events protocol, using distinct connections
+    GraphqlSse,
+}
+
+/// Configuration for tools backed by subscription operations
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct SubscriptionConfig {
+    /// The protocol used to open subscriptions
+    pub protocol: SubscriptionProtocol,
+
+    /// The endpoint to open subscriptions against. Defaults to the GraphQL endpoint,
+    /// using a `ws`/`wss` scheme for graphql-ws.
+    pub endpoint: Option<Url>,
+
+    /// The number of events to collect before the tool call completes (default: 10)
+    pub max_events: usize,
+
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// How long to wait for events before the tool call completes (default: 30s)
+    pub timeout: Duration,
+}
+
+impl Default for SubscriptionConfig {
+    fn default() -> Self {
+        Self {
+            protocol: SubscriptionProtocol::default(),
+            endpoint: None,
+            max_events: 10,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl SubscriptionConfig {
+    /// Open a subscription with the given GraphQL request body
+    pub(crate) async fn subscribe(
+        &self,
+        client: &UpstreamClient,
+        graphql_endpoint: &Url,
+        headers: HeaderMap,
+        body: Value,
+    ) -> Result<EventStream, McpError> {
+        let endpoint = self
+            .endpoint
+            .clone()
+            .unwrap_or_else(|| graphql_endpoint.clone());
+        match self.protocol {
+            SubscriptionProtocol::GraphqlWs => {
+                graphql_ws::subscribe(
+                    client.websocket(),
+                    websocket_endpoint(endpoint),
+                    headers,
+                    body,
+                )
+                .await
+            }
+            SubscriptionProtocol::GraphqlSse => {
+                graphql_sse::subscribe(client.http(), endpoint, self.timeout, headers, body).await
+            }
+        }
+    }
+}
+
+/// Swap an HTTP(S) endpoint for its WebSocket equivalent
+fn websocket_endpoint(mut endpoint: Url) -> Url {
+    let scheme = match endpoint.scheme() {
+        "http" => "ws",
+        "https" => "wss",
+        _ => return endpoint,
+    };
+
+    // Both schemes are special, so swapping between them cannot fail
+    let _ = endpoint.set_scheme(scheme);
+    endpoint
+}
+
+/// A subscription the endpoint refused to open because it wasn't authenticated, as a single
+/// GraphQL error, so it's handled like a subscription rejected in its first event
+fn unauthenticated(status: StatusCode) -> EventStream {
+    let response = json!({
+        "data": null,
+        "errors": [{
+            "message": format!("The subscription endpoint responded with HTTP status {status}"),
+            "extensions": { "code": UNAUTHENTICATED_CODE },
+        }],
+    });
+    stream::once(async move { Ok(response) }).boxed()
+}
+
+fn subscription_error(message: impl std::fmt::Display) -> McpError {
+    McpError::new(
+        ErrorCode::INTERNAL_ERROR,
+        format!("Failed to subscribe: {message}"),
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("http://localhost:4000/graphql", "ws://localhost:4000/graphql")]
+    #[case("https://api.example.com/graphql", "wss://api.example.com/graphql")]
+    #[case("wss://api.example.com/ws", "wss://api.example.com/ws")]
+    fn websocket_endpoint_swaps_scheme(#[case] endpoint: &str, #[case] expected: &str) {
+        let actual = websocket_endpoint(Url::parse(endpoint).unwrap());
+        assert_eq!(actual.as_str(), expected);
+    }
+
+    #[test]
+    fn it_parses_config() {
+        let config: SubscriptionConfig = serde_json::from_value(serde_json::json!({
+            "protocol": "graphql_sse",
+            "max_events": 3,
+            "timeout": "5s"
+        }))
+        .unwrap();
+
+        assert_eq!(config.protocol, SubscriptionProtocol::GraphqlSse);
+        assert_eq!(config.max_events, 3);
+        assert_eq!(config.timeout, Duration::from_secs(5));
+        assert!(config.endpoint.is_none());
+    }
+}
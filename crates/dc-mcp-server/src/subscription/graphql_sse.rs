@@ -0,0 +1,265 @@
+//! Client for the GraphQL over Server-Sent Events protocol, in distinct connections mode
+//!
+//! See: https://github.com/enisdenjo/graphql-sse/blob/master/PROTOCOL.md#distinct-connections-mode
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use futures::{StreamExt as _, stream};
+use reqwest::StatusCode;
+use reqwest::header::{ACCEPT, CONTENT_TYPE, HeaderMap};
+use serde_json::Value;
+use tracing::debug;
+use url::Url;
+
+use crate::errors::McpError;
+
+use super::{EventStream, subscription_error, unauthenticated};
+
+/// A single server-sent event
+#[derive(Debug, Default, PartialEq)]
+struct Event {
+    event: Option<String>,
+    data: String,
+}
+
+/// Incrementally decodes server-sent events from chunks of a response body
+#[derive(Default)]
+struct Decoder {
+    buffer: String,
+}
+
+impl Decoder {
+    /// Append a chunk of the body, returning every event that is now complete
+    fn push(&mut self, chunk: &str) -> Vec<Event> {
+        self.buffer.push_str(&chunk.replace("\r\n", "\n"));
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.find("\n\n") {
+            let block: String = self.buffer.drain(..end + 2).collect();
+            let mut event = Event::default();
+            let mut data = Vec::new();
+            for line in block.lines() {
+                let (field, value) = line.split_once(':').unwrap_or((line, ""));
+                let value = value.strip_prefix(' ').unwrap_or(value);
+                match field {
+                    "event" => event.event = Some(value.to_string()),
+                    "data" => data.push(value),
+                    // Comments (keep-alives) and unknown fields are ignored
+                    _ => {}
+                }
+            }
+            if event.event.is_some() || !data.is_empty() {
+                event.data = data.join("\n");
+                events.push(event);
+            }
+        }
+        events
+    }
+}
+
+/// Open a subscription over a new event stream
+///
+/// The stream is bounded by the given timeout rather than the client's, which is meant
+/// for requests that complete with a single response.
+pub(super) async fn subscribe(
+    client: &reqwest::Client,
+    endpoint: Url,
+    timeout: Duration,
+    headers: HeaderMap,
+    body: Value,
+) -> Result<EventStream, McpError> {
+    let response = client
+        .post(endpoint.as_str())
+        .headers(headers)
+        .header(CONTENT_TYPE, "application/json")
+        .header(ACCEPT, "text/event-stream")
+        .body(body.to_string())
+        .timeout(timeout)
+        .send()
+        .await
+        .map_err(subscription_error)?;
+
+    let status = response.status();
+    if status == StatusCode::UNAUTHORIZED {
+        return Ok(unauthenticated(status));
+    }
+    if !status.is_success() {
+        return Err(subscription_error(format!(
+            "upstream responded with status {status}"
+        )));
+    }
+
+    let state = (
+        response.bytes_stream().boxed(),
+        Decoder::default(),
+        VecDeque::<Event>::new(),
+    );
+    Ok(stream::unfold(Some(state), |state| async move {
+        let (mut body, mut decoder, mut pending) = state?;
+        loop {
+            if let Some(event) = pending.pop_front() {
+                match event.event.as_deref() {
+                    Some("complete") => {
+                        debug!("Subscription completed");
+                        return None;
+                    }
+                    Some("next") | None => {
+                        let next = serde_json::from_str(&event.data).map_err(|e| {
+                            subscription_error(format!("invalid event from server: {e}"))
+                        });
+                        return Some((next, Some((body, decoder, pending))));
+                    }
+                    Some(_) => continue,
+                }
+            }
+
+            match body.next().await {
+                Some(Ok(chunk)) => pending.extend(decoder.push(&String::from_utf8_lossy(&chunk))),
+                Some(Err(e)) => return Some((Err(subscription_error(e)), None)),
+                None => return None,
+            }
+        }
+    })
+    .boxed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn it_decodes_events_split_across_chunks() {
+        let mut decoder = Decoder::default();
+
+        assert!(
+            decoder
+                .push(": keep-alive\n\nevent: next\ndata: {\"da")
+                .is_empty()
+        );
+        assert_eq!(
+            decoder.push("ta\":{}}\n\nevent: complete\r\ndata:\r\n\r\n"),
+            vec![
+                Event {
+                    event: Some("next".to_string()),
+                    data: r#"{"data":{}}"#.to_string(),
+                },
+                Event {
+                    event: Some("complete".to_string()),
+                    data: String::new(),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn it_streams_events_until_complete() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/")
+            .match_header("accept", "text/event-stream")
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(
+                "event: next\ndata: {\"data\":{\"id\":1}}\n\n\
+                 event: next\ndata: {\"data\":{\"id\":2}}\n\n\
+                 event: complete\ndata:\n\n",
+            )
+            .create_async()
+            .await;
+
+        let events: Vec<_> = subscribe(
+            &reqwest::Client::new(),
+            Url::parse(&server.url()).unwrap(),
+            Duration::from_secs(30),
+            HeaderMap::new(),
+            json!({"query": "subscription OnEvent { id }"}),
+        )
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events.last().unwrap().as_ref().unwrap(),
+            &json!({"data": {"id": 2}})
+        );
+    }
+
+    #[tokio::test]
+    async fn it_errors_on_unsuccessful_status() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/")
+            .with_status(400)
+            .create_async()
+            .await;
+
+        let result = subscribe(
+            &reqwest::Client::new(),
+            Url::parse(&server.url()).unwrap(),
+            Duration::from_secs(30),
+            HeaderMap::new(),
+            json!({}),
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn it_outlives_the_client_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"{}") {
+                let mut buffer = [0; 1024];
+                let read = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(buffer.get(..read).unwrap());
+            }
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n\
+                      event: next\ndata: {\"data\":{\"id\":1}}\n\n",
+                )
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            stream
+                .write_all(
+                    b"event: next\ndata: {\"data\":{\"id\":2}}\n\nevent: complete\ndata:\n\n",
+                )
+                .await
+                .unwrap();
+        });
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let events: Vec<_> = subscribe(
+            &client,
+            endpoint,
+            Duration::from_secs(5),
+            HeaderMap::new(),
+            json!({}),
+        )
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events.last().unwrap().as_ref().unwrap(),
+            &json!({"data": {"id": 2}})
+        );
+    }
+}
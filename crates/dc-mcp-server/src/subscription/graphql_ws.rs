@@ -0,0 +1,254 @@
+//! Client for the `graphql-transport-ws` protocol
+//!
+//! See: https://github.com/enisdenjo/graphql-ws/blob/master/PROTOCOL.md
+
+use futures::{SinkExt as _, StreamExt as _, stream};
+use reqwest::StatusCode;
+use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderValue, SEC_WEBSOCKET_PROTOCOL};
+use serde_json::{Map, Value, json};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest as _;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tracing::debug;
+use url::Url;
+
+use crate::errors::McpError;
+use crate::upstream::websocket::{WebSocket as Socket, WebSocketConnector};
+
+use super::{EventStream, subscription_error, unauthenticated};
+
+const SUBPROTOCOL: &str = "graphql-transport-ws";
+
+/// The ID of the single subscription opened on each connection
+const SUBSCRIPTION_ID: &str = "1";
+
+/// A message received from the server, reduced to what the client acts on
+#[derive(Debug, PartialEq)]
+enum ServerMessage {
+    ConnectionAck,
+    Ping,
+    Next(Value),
+    Error(Value),
+    Complete,
+    Other,
+}
+
+impl ServerMessage {
+    fn parse(text: &str) -> Result<Self, McpError> {
+        let mut message: Map<String, Value> = serde_json::from_str(text)
+            .map_err(|e| subscription_error(format!("invalid message from server: {e}")))?;
+        let payload = message.remove("payload").unwrap_or(Value::Null);
+        Ok(
+            match message
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+            {
+                "connection_ack" => Self::ConnectionAck,
+                "ping" => Self::Ping,
+                "next" => Self::Next(payload),
+                "error" => Self::Error(payload),
+                "complete" => Self::Complete,
+                _ => Self::Other,
+            },
+        )
+    }
+}
+
+/// Open a subscription over a new WebSocket connection
+pub(super) async fn subscribe(
+    connector: &WebSocketConnector,
+    endpoint: Url,
+    headers: HeaderMap,
+    body: Value,
+) -> Result<EventStream, McpError> {
+    let mut request = endpoint
+        .as_str()
+        .into_client_request()
+        .map_err(subscription_error)?;
+    request.headers_mut().insert(
+        SEC_WEBSOCKET_PROTOCOL,
+        HeaderValue::from_static(SUBPROTOCOL),
+    );
+
+    // Headers are sent both on the upgrade request and as the connection_init payload,
+    // since servers differ in where they read authentication from.
+    let mut init_payload = Map::new();
+    for (name, value) in headers.iter().filter(|(name, _)| *name != CONTENT_TYPE) {
+        request.headers_mut().insert(name.clone(), value.clone());
+        if let Ok(value) = value.to_str() {
+            init_payload.insert(name.to_string(), Value::String(value.to_string()));
+        }
+    }
+
+    let mut socket = match connector.connect(request).await {
+        Ok(socket) => socket,
+        Err(tungstenite::Error::Http(response))
+            if response.status() == StatusCode::UNAUTHORIZED =>
+        {
+            return Ok(unauthenticated(response.status()));
+        }
+        Err(e) => return Err(subscription_error(e)),
+    };
+
+    send(
+        &mut socket,
+        json!({ "type": "connection_init", "payload": init_payload }),
+    )
+    .await?;
+    loop {
+        match receive(&mut socket).await? {
+            Some(ServerMessage::ConnectionAck) => break,
+            Some(ServerMessage::Ping) => send(&mut socket, json!({ "type": "pong" })).await?,
+            Some(_) => continue,
+            None => {
+                return Err(subscription_error(
+                    "connection closed before it was acknowledged",
+                ));
+            }
+        }
+    }
+
+    send(
+        &mut socket,
+        json!({ "type": "subscribe", "id": SUBSCRIPTION_ID, "payload": body }),
+    )
+    .await?;
+
+    Ok(stream::unfold(Some(socket), |socket| async move {
+        let mut socket = socket?;
+        loop {
+            match receive(&mut socket).await {
+                Ok(Some(ServerMessage::Next(payload))) => return Some((Ok(payload), Some(socket))),
+                Ok(Some(ServerMessage::Ping)) => {
+                    if let Err(e) = send(&mut socket, json!({ "type": "pong" })).await {
+                        return Some((Err(e), None));
+                    }
+                }
+                // The server rejected the subscription before executing it, with the errors of
+                // a GraphQL response, so they are handled like the errors of an event
+                Ok(Some(ServerMessage::Error(errors))) => {
+                    debug!("Subscription rejected");
+                    let _ = socket.close(None).await;
+                    return Some((Ok(json!({ "errors": errors })), None));
+                }
+                Ok(Some(ServerMessage::Complete)) | Ok(None) => {
+                    debug!("Subscription completed");
+                    let _ = socket.close(None).await;
+                    return None;
+                }
+                Ok(Some(ServerMessage::ConnectionAck | ServerMessage::Other)) => continue,
+                Err(e) => return Some((Err(e), None)),
+            }
+        }
+    })
+    .boxed())
+}
+
+async fn send(socket: &mut Socket, message: Value) -> Result<(), McpError> {
+    socket
+        .send(Message::text(message.to_string()))
+        .await
+        .map_err(subscription_error)
+}
+
+/// Receive the next protocol message, or `None` once the connection is closed
+async fn receive(socket: &mut Socket) -> Result<Option<ServerMessage>, McpError> {
+    loop {
+        match socket.next().await {
+            Some(Ok(Message::Text(text))) => return ServerMessage::parse(&text).map(Some),
+            Some(Ok(Message::Close(_))) | None => return Ok(None),
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(subscription_error(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_hdr_async;
+    use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+
+    #[test]
+    fn it_parses_server_messages() {
+        assert_eq!(
+            ServerMessage::parse(r#"{"type":"connection_ack"}"#).unwrap(),
+            ServerMessage::ConnectionAck
+        );
+        assert_eq!(
+            ServerMessage::parse(r#"{"id":"1","type":"next","payload":{"data":{"id":1}}}"#)
+                .unwrap(),
+            ServerMessage::Next(json!({"data": {"id": 1}}))
+        );
+        assert_eq!(
+            ServerMessage::parse(r#"{"id":"1","type":"complete"}"#).unwrap(),
+            ServerMessage::Complete
+        );
+        assert!(ServerMessage::parse("not json").is_err());
+    }
+
+    #[tokio::test]
+    async fn it_streams_events_until_complete() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = Url::parse(&format!("ws://{}", listener.local_addr().unwrap())).unwrap();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = accept_hdr_async(stream, |_: &Request, mut response: Response| {
+                response.headers_mut().insert(
+                    SEC_WEBSOCKET_PROTOCOL,
+                    HeaderValue::from_static(SUBPROTOCOL),
+                );
+                Ok(response)
+            })
+            .await
+            .unwrap();
+
+            let init = socket.next().await.unwrap().unwrap();
+            assert!(init.to_text().unwrap().contains("connection_init"));
+            socket
+                .send(Message::text(r#"{"type":"connection_ack"}"#))
+                .await
+                .unwrap();
+
+            let subscribe = socket.next().await.unwrap().unwrap();
+            assert!(
+                subscribe
+                    .to_text()
+                    .unwrap()
+                    .contains("subscription OnEvent")
+            );
+            for id in 0..2 {
+                socket
+                    .send(Message::text(
+                        json!({"id": "1", "type": "next", "payload": {"data": {"id": id}}})
+                            .to_string(),
+                    ))
+                    .await
+                    .unwrap();
+            }
+            socket
+                .send(Message::text(r#"{"id":"1","type":"complete"}"#))
+                .await
+                .unwrap();
+        });
+
+        let events: Vec<_> = subscribe(
+            &WebSocketConnector::default(),
+            endpoint,
+            HeaderMap::new(),
+            json!({"query": "subscription OnEvent { id }"}),
+        )
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events.first().unwrap().as_ref().unwrap(),
+            &json!({"data": {"id": 0}})
+        );
+    }
+}
@@ -29,6 +29,9 @@ impl TelemetryAttribute {
             TelemetryAttribute::ClientVersion => {
                 Key::from_static_str(TelemetryAttribute::ClientVersion.as_str())
             }
+            TelemetryAttribute::RateLimitScope => {
+                Key::from_static_str(TelemetryAttribute::RateLimitScope.as_str())
+            }
         }
     }
 
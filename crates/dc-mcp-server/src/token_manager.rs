@@ -1,89 +1,48 @@
 //! Token refresh functionality for Apollo MCP Server
 
-use crate::config_manager::ConfigManager;
 use crate::errors::McpError;
+use crate::redaction::redactor;
+use crate::token_provider::TokenProvider;
+use crate::token_store::{MemoryTokenStore, TokenStore};
 use reqwest::Client;
 use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
 use rmcp::model::ErrorCode;
-use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 
-#[derive(Debug, Serialize)]
-struct RefreshTokenRequest {
-    #[serde(rename = "refreshToken")]
-    refresh_token: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct RefreshTokenResponse {
-    #[serde(rename = "accessToken")]
-    access_token: String,
-    #[serde(rename = "expiresIn")]
-    expires_in: Option<u64>,
-}
-
 pub struct TokenManager {
-    refresh_token: String,
-    refresh_url: String,
+    provider: Arc<dyn TokenProvider>,
     access_token: Option<String>,
     token_expires_at: Option<Instant>,
     client: Client,
-    config_manager: Option<Arc<ConfigManager>>,
+    store: Arc<dyn TokenStore>,
     headers: Option<Arc<RwLock<HeaderMap>>>,
 }
 
 impl TokenManager {
-    pub fn new(refresh_token: String, refresh_url: String) -> Result<Self, McpError> {
-        // Validate input parameters
-        if refresh_token.trim().is_empty() {
-            return Err(McpError::new(
-                ErrorCode::INVALID_PARAMS,
-                "Refresh token cannot be empty".to_string(),
-                None,
-            ));
-        }
-
-        if refresh_url.trim().is_empty() {
-            return Err(McpError::new(
-                ErrorCode::INVALID_PARAMS,
-                "Refresh URL cannot be empty".to_string(),
-                None,
-            ));
-        }
-
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .connect_timeout(Duration::from_secs(10))
-            .user_agent("curl/8.4.0")
-            .danger_accept_invalid_certs(false)
-            .danger_accept_invalid_hostnames(false)
-            .build()
-            .map_err(|e| {
-                McpError::new(
-                    ErrorCode::INTERNAL_ERROR,
-                    format!("Failed to create HTTP client: {}", e),
-                    None,
-                )
-            })?;
-
-        Ok(Self {
-            refresh_token,
-            refresh_url,
+    pub fn new(provider: Arc<dyn TokenProvider>) -> Self {
+        Self {
+            provider,
             access_token: None,
             token_expires_at: None,
-            client,
-            config_manager: None,
+            client: Client::new(),
+            store: Arc::new(MemoryTokenStore::default()),
             headers: None,
-        })
+        }
     }
 
-    /// Inject the config manager for automatic token persistence
-    pub fn set_config_manager(&mut self, config_manager: Arc<ConfigManager>) {
-        self.config_manager = Some(config_manager);
+    /// Inject the store that refreshed tokens are saved to. Tokens are only kept in memory
+    /// otherwise.
+    pub fn set_token_store(&mut self, store: Arc<dyn TokenStore>) {
+        self.store = store;
+    }
+
+    /// Inject the shared upstream HTTP client, so refresh requests reuse its connections
+    pub fn set_client(&mut self, client: Client) {
+        self.client = client;
     }
 
     /// Inject the shared headers for automatic token updates
@@ -120,91 +79,35 @@ impl TokenManager {
         self.refresh_access_token().await
     }
 
-    /// Refresh the access token
-    async fn refresh_access_token(&mut self) -> Result<String, McpError> {
-        let request_body = RefreshTokenRequest {
-            refresh_token: self.refresh_token.clone(),
-        };
-
-        debug!("Making token refresh request to: {}", self.refresh_url);
-
-        // Wrap the entire refresh operation in a timeout to prevent indefinite hangs
-        let refresh_result = tokio::time::timeout(
-            Duration::from_secs(30),
-            self.client
-                .post(&self.refresh_url)
-                .header("Content-Type", "application/json")
-                .json(&request_body)
-                .send(),
-        )
-        .await;
-
-        let response = match refresh_result {
-            Ok(Ok(resp)) => resp,
-            Ok(Err(e)) => {
-                error!("Failed to send token refresh request: {}", e);
-                return Err(McpError::new(
-                    ErrorCode::INTERNAL_ERROR,
-                    format!("Failed to refresh token: {}", e),
-                    None,
-                ));
-            }
-            Err(_) => {
-                error!("Token refresh request timed out after 30s");
-                return Err(McpError::new(
-                    ErrorCode::INTERNAL_ERROR,
-                    "Token refresh request timed out after 30 seconds".to_string(),
-                    None,
-                ));
-            }
-        };
-
-        let status = response.status();
-        // Add timeout for reading response body to prevent hanging
-        let response_text = tokio::time::timeout(Duration::from_secs(10), response.text())
-            .await
-            .map_err(|_| {
-                error!("Token refresh response read timed out after 10s");
-                McpError::new(
-                    ErrorCode::INTERNAL_ERROR,
-                    "Token refresh response read timed out after 10 seconds".to_string(),
-                    None,
-                )
-            })?
-            .map_err(|e| {
-                error!("Failed to read token refresh response: {}", e);
-                McpError::new(
-                    ErrorCode::INTERNAL_ERROR,
-                    format!("Failed to read token refresh response: {}", e),
-                    None,
-                )
-            })?;
+    /// Refresh the access token after the endpoint rejected it. Concurrent calls rejected with
+    /// the same token only refresh it once: if the rejected token was already replaced, the
+    /// current one is returned instead.
+    pub async fn refresh_rejected_token(
+        &mut self,
+        rejected: Option<&HeaderValue>,
+    ) -> Result<String, McpError> {
+        if let Some(token) = &self.access_token
+            && rejected.is_none_or(|rejected| rejected.as_bytes() != bearer(token).as_bytes())
+        {
+            debug!("Rejected access token was already replaced");
+            return Ok(token.clone());
+        }
 
-        debug!(
-            "Token refresh response (status: {}): {}",
-            status, response_text
-        );
+        info!("🔄 Access token was rejected, refreshing...");
+        self.refresh_access_token().await
+    }
 
-        let token_response: RefreshTokenResponse =
-            serde_json::from_str(&response_text).map_err(|e| {
-                error!("Failed to parse token refresh response: {}", e);
-                McpError::new(
-                    ErrorCode::INTERNAL_ERROR,
-                    format!(
-                        "Failed to parse token refresh response (status: {}, body: {}): {}",
-                        status, response_text, e
-                    ),
-                    None,
-                )
-            })?;
+    /// Refresh the access token
+    async fn refresh_access_token(&mut self) -> Result<String, McpError> {
+        let token = self.provider.fetch(&self.client).await?;
 
         // Update token and expiry
-        self.access_token = Some(token_response.access_token.clone());
-        if let Some(expires_in) = token_response.expires_in {
-            self.token_expires_at = Some(Instant::now() + Duration::from_secs(expires_in));
+        self.access_token = Some(token.access_token.clone());
+        if let Some(expires_in) = token.expires_in {
+            self.token_expires_at = Some(Instant::now() + expires_in);
             info!(
                 "✅ Successfully refreshed access token (expires in {}s)",
-                expires_in
+                expires_in.as_secs()
             );
         } else {
             // Default to 1 hour if no expiry provided
@@ -213,49 +116,48 @@ impl TokenManager {
         }
 
         // Create the header value first to ensure it's valid
-        let header_value =
-            HeaderValue::from_str(&format!("Bearer {}", token_response.access_token)).map_err(
-                |e| {
-                    McpError::new(
-                        ErrorCode::INTERNAL_ERROR,
-                        format!("Failed to create header value from token: {}", e),
-                        None,
-                    )
-                },
-            )?;
-
-        // Write the token to config file if config manager is set
-        // Use spawn_blocking with timeout to prevent hanging on slow filesystems
-        // Note: We skip config file write if it would hang - token is still updated in memory
-        if let Some(config_manager) = &self.config_manager {
-            let token = token_response.access_token.clone();
-            let config_manager = Arc::clone(config_manager);
-            let write_result = tokio::time::timeout(
-                Duration::from_secs(5),
-                tokio::task::spawn_blocking(move || config_manager.update_auth_token(&token)),
+        let header_value = HeaderValue::from_str(&bearer(&token.access_token)).map_err(|e| {
+            McpError::new(
+                ErrorCode::INTERNAL_ERROR,
+                format!("Failed to create header value from token: {}", e),
+                None,
             )
-            .await;
+        })?;
 
-            match write_result {
-                Ok(Ok(Ok(()))) => {
-                    info!("✅ Refreshed token written to config file");
-                }
-                Ok(Ok(Err(e))) => {
-                    warn!(
-                        "⚠️  Failed to write refreshed token to config file: {} (token still updated in memory)",
-                        e
-                    );
-                }
-                Ok(Err(_)) => {
-                    warn!(
-                        "⚠️  Config file write task was cancelled (token still updated in memory)"
-                    );
-                }
-                Err(_) => {
-                    warn!(
-                        "⚠️  Config file write timed out after 5s (token still updated in memory)"
-                    );
+        // Save the tokens to the store, including a rotated refresh token which is needed for
+        // the next refresh after a restart
+        // Use spawn_blocking with timeout to prevent hanging on slow filesystems
+        // Note: We skip the store if it would hang - token is still updated in memory
+        let access_token = token.access_token.clone();
+        let refresh_token = token.refresh_token;
+        let store = Arc::clone(&self.store);
+        let write_result = tokio::time::timeout(
+            Duration::from_secs(5),
+            tokio::task::spawn_blocking(move || {
+                store.save(&access_token)?;
+                match refresh_token {
+                    Some(refresh_token) => store.save_refresh_token(&refresh_token),
+                    None => Ok(()),
                 }
+            }),
+        )
+        .await;
+
+        match write_result {
+            Ok(Ok(Ok(()))) => {
+                info!("✅ Refreshed token saved to token store");
+            }
+            Ok(Ok(Err(e))) => {
+                warn!(
+                    "⚠️  Failed to save refreshed token to token store: {} (token still updated in memory)",
+                    e
+                );
+            }
+            Ok(Err(_)) => {
+                warn!("⚠️  Token store write task was cancelled (token still updated in memory)");
+            }
+            Err(_) => {
+                warn!("⚠️  Token store write timed out after 5s (token still updated in memory)");
             }
         }
 
@@ -266,7 +168,7 @@ impl TokenManager {
             info!("✅ Refreshed token updated in shared headers");
         }
 
-        Ok(token_response.access_token)
+        Ok(token.access_token)
     }
 
     /// Verify token by making a test API call
@@ -314,7 +216,7 @@ impl TokenManager {
         } else {
             warn!(
                 "❌ Token verification failed. API response: {}",
-                response_text
+                redactor().redact_str(&response_text)
             );
         }
 
@@ -348,15 +250,28 @@ impl TokenManager {
     }
 }
 
+/// The authorization header value of an access token
+pub(crate) fn bearer(token: &str) -> String {
+    format!("Bearer {token}")
+}
+
+// The tokens are secrets, so they're left out
+impl std::fmt::Debug for TokenManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenManager")
+            .field("token_expires_at", &self.token_expires_at)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Clone for TokenManager {
     fn clone(&self) -> Self {
         Self {
-            refresh_token: self.refresh_token.clone(),
-            refresh_url: self.refresh_url.clone(),
+            provider: Arc::clone(&self.provider),
             access_token: self.access_token.clone(),
             token_expires_at: self.token_expires_at,
             client: self.client.clone(),
-            config_manager: self.config_manager.clone(),
+            store: Arc::clone(&self.store),
             headers: self.headers.clone(),
         }
     }
@@ -365,68 +280,48 @@ impl Clone for TokenManager {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config_manager::ConfigManager;
-    use std::fs;
+    use crate::token_provider::{DoControlConfig, RefreshTokenConfig, TokenProviderConfig};
+    use crate::token_store::RotatedRefreshToken;
     use std::time::Instant;
-    use tempfile::TempDir;
     use tokio::time::Duration;
 
-    /// Test that token refresh stores token in memory
+    fn docontrol(refresh_url: String) -> Arc<dyn TokenProvider> {
+        TokenProviderConfig::DoControl(DoControlConfig {
+            refresh_url: refresh_url.parse().unwrap(),
+            refresh_token: "refresh_token_123".to_string(),
+        })
+        .provider(None)
+        .unwrap()
+    }
+
+    /// Test that a new token manager has no token in memory
     #[tokio::test]
     async fn test_token_refresh_stores_in_memory() {
-        let temp_dir = TempDir::new().unwrap();
-        let config_path = temp_dir.path().join("test_config.yaml");
-
-        // Create initial config
-        let initial_config = r#"
-endpoint: "https://api.example.com/graphql"
-headers:
-  Authorization: "Bearer old_token"
-"#;
-        fs::write(&config_path, initial_config).unwrap();
-
-        let _config_manager = ConfigManager::new(config_path.to_string_lossy().to_string());
-
-        // Mock refresh URL (this would normally be a real endpoint)
-        let refresh_url = "https://api.example.com/refresh";
-        let refresh_token = "refresh_token_123";
-
         let token_manager =
-            TokenManager::new(refresh_token.to_string(), refresh_url.to_string()).unwrap();
+            TokenManager::new(docontrol("https://api.example.com/refresh".to_string()));
 
         // Initially no token in memory
         assert!(token_manager.access_token.is_none());
         assert!(token_manager.token_expires_at.is_none());
-
-        // Note: This test would need a mock server to actually test token refresh
-        // For now, we test the structure and that it can be created
-        assert_eq!(token_manager.refresh_token, refresh_token);
-        assert_eq!(token_manager.refresh_url, refresh_url);
     }
 
-    /// Test token manager creation with invalid parameters
+    /// Test provider creation with invalid parameters
     #[test]
     fn test_token_manager_creation_error() {
         // Test with empty refresh token
-        let result = TokenManager::new(
-            "".to_string(),
-            "https://api.example.com/refresh".to_string(),
-        );
-        assert!(result.is_err());
-
-        // Test with empty refresh URL
-        let result = TokenManager::new("refresh_token".to_string(), "".to_string());
+        let result = TokenProviderConfig::DoControl(DoControlConfig {
+            refresh_url: "https://api.example.com/refresh".parse().unwrap(),
+            refresh_token: "".to_string(),
+        })
+        .provider(None);
         assert!(result.is_err());
     }
 
     /// Test token expiry logic
     #[tokio::test]
     async fn test_token_expiry_logic() {
-        let refresh_url = "https://api.example.com/refresh";
-        let refresh_token = "refresh_token_123";
-
         let mut token_manager =
-            TokenManager::new(refresh_token.to_string(), refresh_url.to_string()).unwrap();
+            TokenManager::new(docontrol("https://api.example.com/refresh".to_string()));
 
         // Set a token that expires in the past
         // Use checked_sub to avoid overflow on Windows where Instant::now() - duration can panic
@@ -450,24 +345,109 @@ headers:
         }
     }
 
+    /// Test that a rejected token is only refreshed if it wasn't already replaced
+    #[tokio::test]
+    async fn test_refresh_rejected_token() {
+        let mut server = mockito::Server::new_async().await;
+        let refresh = server
+            .mock("POST", "/refresh")
+            .match_body(mockito::Matcher::Json(
+                serde_json::json!({ "refreshToken": "refresh_token_123" }),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"accessToken":"newer_token","expiresIn":300}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let headers = Arc::new(RwLock::new(HeaderMap::new()));
+        let mut token_manager = TokenManager::new(docontrol(format!("{}/refresh", server.url())));
+        let store = Arc::new(MemoryTokenStore::default());
+        token_manager.set_headers(Arc::clone(&headers));
+        token_manager.set_token_store(store.clone());
+        token_manager.access_token = Some("new_token".to_string());
+
+        // Another call already replaced the rejected token
+        let rejected = HeaderValue::from_static("Bearer old_token");
+        assert_eq!(
+            token_manager
+                .refresh_rejected_token(Some(&rejected))
+                .await
+                .unwrap(),
+            "new_token"
+        );
+
+        // The current token was rejected
+        let rejected = HeaderValue::from_static("Bearer new_token");
+        assert_eq!(
+            token_manager
+                .refresh_rejected_token(Some(&rejected))
+                .await
+                .unwrap(),
+            "newer_token"
+        );
+        assert_eq!(
+            headers.read().await.get(AUTHORIZATION).unwrap(),
+            "Bearer newer_token"
+        );
+        assert_eq!(store.load().unwrap().as_deref(), Some("newer_token"));
+        assert_eq!(store.load_refresh_token().unwrap(), None);
+        refresh.assert_async().await;
+    }
+
+    /// Test that rotated refresh tokens are saved to the token store
+    #[tokio::test]
+    async fn test_rotated_refresh_token_is_stored() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/token")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"access_token":"new_token","token_type":"Bearer","refresh_token":"rotated_refresh_token"}"#,
+            )
+            .create_async()
+            .await;
+        let provider = TokenProviderConfig::RefreshToken(RefreshTokenConfig {
+            token_endpoint: Some(format!("{}/token", server.url()).parse().unwrap()),
+            issuer: None,
+            client_id: None,
+            client_secret: None,
+            client_auth: Default::default(),
+            refresh_token: "refresh_token_123".to_string(),
+            scopes: Vec::new(),
+        })
+        .provider(None)
+        .unwrap();
+        let mut token_manager = TokenManager::new(provider);
+        let store = Arc::new(MemoryTokenStore::default());
+        token_manager.set_token_store(store.clone());
+
+        assert_eq!(token_manager.get_valid_token().await.unwrap(), "new_token");
+        assert_eq!(store.load().unwrap().as_deref(), Some("new_token"));
+        assert_eq!(
+            store.load_refresh_token().unwrap(),
+            Some(RotatedRefreshToken::new(
+                "rotated_refresh_token",
+                "refresh_token_123"
+            ))
+        );
+    }
+
     /// Test token manager clone
     #[test]
     fn test_token_manager_clone() {
-        let refresh_url = "https://api.example.com/refresh";
-        let refresh_token = "refresh_token_123";
-
         let mut token_manager =
-            TokenManager::new(refresh_token.to_string(), refresh_url.to_string()).unwrap();
+            TokenManager::new(docontrol("https://api.example.com/refresh".to_string()));
         token_manager.access_token = Some("test_token".to_string());
         token_manager.token_expires_at = Some(Instant::now() + Duration::from_secs(3600));
 
         let cloned_manager = token_manager.clone();
 
-        assert_eq!(
-            cloned_manager.refresh_token(),
-            token_manager.refresh_token()
-        );
-        assert_eq!(cloned_manager.refresh_url(), token_manager.refresh_url());
+        assert!(Arc::ptr_eq(
+            &cloned_manager.provider,
+            &token_manager.provider
+        ));
         assert_eq!(cloned_manager.access_token(), token_manager.access_token());
         assert_eq!(
             cloned_manager.token_expires_at(),
@@ -477,16 +457,6 @@ headers:
 
     // Test helper methods for TokenManager
     impl TokenManager {
-        /// Get the refresh token (for testing)
-        pub fn refresh_token(&self) -> &str {
-            &self.refresh_token
-        }
-
-        /// Get the refresh URL (for testing)
-        pub fn refresh_url(&self) -> &str {
-            &self.refresh_url
-        }
-
         /// Get the current access token (for testing)
         pub fn access_token(&self) -> &Option<String> {
             &self.access_token
@@ -0,0 +1,200 @@
+//! Providers of access tokens for the GraphQL endpoint
+//!
+//! A provider gets a new access token whenever the token manager refreshes it. DoControl's
+//! refresh endpoint exchanges a refresh token for an access token, while the OAuth 2.0 providers
+//! use the refresh token or client credentials grants of a token endpoint, which can be found
+//! with OpenID Connect discovery.
+
+mod docontrol;
+mod oauth2;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use rmcp::model::ErrorCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tracing::{debug, error};
+
+use crate::errors::McpError;
+use crate::redaction::redactor;
+use crate::token_store::{RotatedRefreshToken, TokenStoreConfig};
+
+pub use docontrol::DoControlConfig;
+pub use oauth2::{ClientAuth, ClientCredentialsConfig, RefreshTokenConfig};
+
+/// Configuration for refreshing the access token sent to the GraphQL endpoint
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TokenRefreshConfig {
+    /// Where access tokens come from
+    pub provider: TokenProviderConfig,
+
+    /// Where refreshed tokens are kept
+    #[serde(default)]
+    pub store: TokenStoreConfig,
+}
+
+/// The provider of access tokens
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TokenProviderConfig {
+    /// DoControl's refresh endpoint
+    #[serde(rename = "docontrol")]
+    DoControl(DoControlConfig),
+
+    /// The OAuth 2.0 refresh token grant
+    RefreshToken(RefreshTokenConfig),
+
+    /// The OAuth 2.0 client credentials grant
+    ClientCredentials(ClientCredentialsConfig),
+}
+
+impl TokenProviderConfig {
+    /// The name of the provider, as written in the config file
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenProviderConfig::DoControl(_) => "docontrol",
+            TokenProviderConfig::RefreshToken(_) => "refresh_token",
+            TokenProviderConfig::ClientCredentials(_) => "client_credentials",
+        }
+    }
+
+    /// Whether the provider gets new refresh tokens from the authorization server, which must
+    /// be used instead of the configured refresh token
+    pub fn rotates_refresh_tokens(&self) -> bool {
+        matches!(self, TokenProviderConfig::RefreshToken(_))
+    }
+
+    /// Create the provider. A stored refresh token, which was rotated since the server last
+    /// started, replaces the configured one it derives from.
+    pub fn provider(
+        &self,
+        rotated_refresh_token: Option<RotatedRefreshToken>,
+    ) -> Result<Arc<dyn TokenProvider>, McpError> {
+        Ok(match self {
+            TokenProviderConfig::DoControl(config) => Arc::new(config.provider()?),
+            TokenProviderConfig::RefreshToken(config) => {
+                Arc::new(config.provider(rotated_refresh_token)?)
+            }
+            TokenProviderConfig::ClientCredentials(config) => Arc::new(config.provider()?),
+        })
+    }
+}
+
+/// An access token from a provider
+pub struct Token {
+    pub access_token: String,
+
+    /// How long the access token is valid, if the provider said so
+    pub expires_in: Option<Duration>,
+
+    /// A new refresh token replacing the one that was used, which must be kept for the next
+    /// refresh
+    pub refresh_token: Option<RotatedRefreshToken>,
+}
+
+/// Gets new access tokens
+#[async_trait]
+pub trait TokenProvider: Send + Sync {
+    /// Get a new access token, using the given HTTP client
+    async fn fetch(&self, client: &Client) -> Result<Token, McpError>;
+}
+
+/// Send a request for a token, returning the status and body of the response
+async fn send(request: RequestBuilder) -> Result<(StatusCode, String), McpError> {
+    // Time out rather than hang on an unresponsive token endpoint
+    let response = match tokio::time::timeout(Duration::from_secs(30), request.send()).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => {
+            error!("Failed to send token refresh request: {}", e);
+            return Err(internal_error(format!("Failed to refresh token: {}", e)));
+        }
+        Err(_) => {
+            error!("Token refresh request timed out after 30s");
+            return Err(internal_error(
+                "Token refresh request timed out after 30 seconds",
+            ));
+        }
+    };
+
+    let status = response.status();
+    let body = tokio::time::timeout(Duration::from_secs(10), response.text())
+        .await
+        .map_err(|_| {
+            error!("Token refresh response read timed out after 10s");
+            internal_error("Token refresh response read timed out after 10 seconds")
+        })?
+        .map_err(|e| {
+            error!("Failed to read token refresh response: {}", e);
+            internal_error(format!("Failed to read token refresh response: {}", e))
+        })?;
+
+    debug!(
+        "Token refresh response (status: {}): {}",
+        status,
+        redactor().redact_str(&body)
+    );
+    Ok((status, body))
+}
+
+/// Parse the JSON body of a token response
+fn parse<'a, T: Deserialize<'a>>(status: StatusCode, body: &'a str) -> Result<T, McpError> {
+    serde_json::from_str(body).map_err(|e| {
+        error!("Failed to parse token refresh response: {}", e);
+        internal_error(format!(
+            "Failed to parse token refresh response (status: {}, body: {}): {}",
+            status,
+            redactor().redact_str(body),
+            e
+        ))
+    })
+}
+
+fn internal_error(message: impl Into<String>) -> McpError {
+    McpError::new(ErrorCode::INTERNAL_ERROR, message.into(), None)
+}
+
+fn invalid_config(message: impl Into<String>) -> McpError {
+    McpError::new(ErrorCode::INVALID_PARAMS, message.into(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_provider_config() {
+        let config: TokenRefreshConfig = serde_yaml::from_str(
+            r#"
+provider:
+  type: client_credentials
+  issuer: https://auth.example.com/realms/acme
+  client_id: mcp
+  client_secret: secret
+  scopes: [graphql]
+store:
+  type: file
+  path: /var/lib/mcp/token
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.provider.name(), "client_credentials");
+        assert!(matches!(config.store, TokenStoreConfig::File { .. }));
+        assert!(
+            serde_yaml::from_str::<TokenRefreshConfig>(
+                r#"
+provider:
+  type: docontrol
+  refresh_url: https://api.example.com/refresh
+  refresh_token: token
+  client_id: mcp
+"#,
+            )
+            .is_err()
+        );
+    }
+}
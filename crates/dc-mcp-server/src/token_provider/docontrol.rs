@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use url::Url;
+
+use super::{Token, TokenProvider, internal_error, invalid_config, parse, send};
+use crate::errors::McpError;
+
+/// Configuration for DoControl's refresh endpoint, which exchanges a refresh token for an
+/// access token
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DoControlConfig {
+    /// The URL of the refresh endpoint
+    pub refresh_url: Url,
+
+    /// The refresh token
+    pub refresh_token: String,
+}
+
+impl DoControlConfig {
+    pub(super) fn provider(&self) -> Result<DoControlProvider, McpError> {
+        if self.refresh_token.trim().is_empty() {
+            return Err(invalid_config("Refresh token cannot be empty"));
+        }
+        Ok(DoControlProvider {
+            refresh_url: self.refresh_url.clone(),
+            refresh_token: self.refresh_token.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RefreshTokenRequest<'a> {
+    #[serde(rename = "refreshToken")]
+    refresh_token: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct RefreshTokenResponse {
+    #[serde(rename = "accessToken")]
+    access_token: String,
+    #[serde(rename = "expiresIn")]
+    expires_in: Option<u64>,
+}
+
+/// Posts the refresh token as JSON to DoControl's refresh endpoint
+pub(super) struct DoControlProvider {
+    refresh_url: Url,
+    refresh_token: String,
+}
+
+#[async_trait]
+impl TokenProvider for DoControlProvider {
+    async fn fetch(&self, client: &Client) -> Result<Token, McpError> {
+        debug!("Making token refresh request to: {}", self.refresh_url);
+
+        let (status, body) = send(client.post(self.refresh_url.clone()).json(
+            &RefreshTokenRequest {
+                refresh_token: &self.refresh_token,
+            },
+        ))
+        .await?;
+        if !status.is_success() {
+            return Err(internal_error(format!(
+                "Token refresh failed with status {status}"
+            )));
+        }
+
+        let response: RefreshTokenResponse = parse(status, &body)?;
+        Ok(Token {
+            access_token: response.access_token,
+            expires_in: response.expires_in.map(Duration::from_secs),
+            refresh_token: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_exchanges_the_refresh_token() {
+        let mut server = mockito::Server::new_async().await;
+        let refresh = server
+            .mock("POST", "/refresh")
+            .match_body(mockito::Matcher::Json(
+                serde_json::json!({ "refreshToken": "refresh_token_123" }),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"accessToken":"new_token","expiresIn":300}"#)
+            .create_async()
+            .await;
+        let config = DoControlConfig {
+            refresh_url: format!("{}/refresh", server.url()).parse().unwrap(),
+            refresh_token: String::from("refresh_token_123"),
+        };
+
+        let token = config
+            .provider()
+            .unwrap()
+            .fetch(&Client::new())
+            .await
+            .unwrap();
+
+        assert_eq!(token.access_token, "new_token");
+        assert_eq!(token.expires_in, Some(Duration::from_secs(300)));
+        refresh.assert_async().await;
+        assert!(
+            DoControlConfig {
+                refresh_token: String::from(" "),
+                ..config
+            }
+            .provider()
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,523 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use super::{Token, TokenProvider, internal_error, invalid_config, parse, send};
+use crate::errors::McpError;
+use crate::token_store::RotatedRefreshToken;
+
+/// How the client authenticates to the token endpoint
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientAuth {
+    /// HTTP basic authentication with the client ID and secret
+    #[default]
+    ClientSecretBasic,
+
+    /// The client ID and secret in the form parameters of the request
+    ClientSecretPost,
+}
+
+/// Configuration for the OAuth 2.0 refresh token grant
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RefreshTokenConfig {
+    /// The URL of the token endpoint
+    pub token_endpoint: Option<Url>,
+
+    /// The issuer whose token endpoint is found with OpenID Connect discovery, instead of
+    /// setting `token_endpoint`
+    pub issuer: Option<Url>,
+
+    /// The client ID, if the authorization server requires it
+    pub client_id: Option<String>,
+
+    /// The client secret, for confidential clients
+    pub client_secret: Option<String>,
+
+    /// How the client authenticates to the token endpoint
+    #[serde(default)]
+    pub client_auth: ClientAuth,
+
+    /// The refresh token. Refresh tokens rotated by the authorization server are saved to the
+    /// token store.
+    pub refresh_token: String,
+
+    /// The scopes to request, which default to the scopes of the refresh token
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl RefreshTokenConfig {
+    pub(super) fn provider(
+        &self,
+        rotated_refresh_token: Option<RotatedRefreshToken>,
+    ) -> Result<OAuth2Provider, McpError> {
+        if self.refresh_token.trim().is_empty() {
+            return Err(invalid_config("Refresh token cannot be empty"));
+        }
+        let refresh_token = match rotated_refresh_token {
+            Some(rotated) if rotated.derives_from(&self.refresh_token) => {
+                info!("Using the refresh token rotated from the configured one");
+                rotated.refresh_token
+            }
+            Some(_) => {
+                warn!(
+                    "The configured refresh token changed since a refresh token was rotated from it, using the configured one"
+                );
+                self.refresh_token.clone()
+            }
+            None => {
+                info!("Using the configured refresh token");
+                self.refresh_token.clone()
+            }
+        };
+        Ok(OAuth2Provider {
+            token_endpoint: TokenEndpoint::new(&self.token_endpoint, &self.issuer)?,
+            client_id: self.client_id.clone(),
+            client_secret: self.client_secret.clone(),
+            client_auth: self.client_auth,
+            scopes: self.scopes.clone(),
+            grant: Grant::RefreshToken {
+                refresh_token: Mutex::new(refresh_token),
+                configured: self.refresh_token.clone(),
+            },
+        })
+    }
+}
+
+/// Configuration for the OAuth 2.0 client credentials grant
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ClientCredentialsConfig {
+    /// The URL of the token endpoint
+    pub token_endpoint: Option<Url>,
+
+    /// The issuer whose token endpoint is found with OpenID Connect discovery, instead of
+    /// setting `token_endpoint`
+    pub issuer: Option<Url>,
+
+    /// The client ID
+    pub client_id: String,
+
+    /// The client secret
+    pub client_secret: String,
+
+    /// How the client authenticates to the token endpoint
+    #[serde(default)]
+    pub client_auth: ClientAuth,
+
+    /// The scopes to request
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl ClientCredentialsConfig {
+    pub(super) fn provider(&self) -> Result<OAuth2Provider, McpError> {
+        Ok(OAuth2Provider {
+            token_endpoint: TokenEndpoint::new(&self.token_endpoint, &self.issuer)?,
+            client_id: Some(self.client_id.clone()),
+            client_secret: Some(self.client_secret.clone()),
+            client_auth: self.client_auth,
+            scopes: self.scopes.clone(),
+            grant: Grant::ClientCredentials,
+        })
+    }
+}
+
+/// The token endpoint, either configured or discovered from the issuer
+enum TokenEndpoint {
+    Configured(Url),
+    Discovered {
+        issuer: Url,
+        token_endpoint: OnceCell<Url>,
+    },
+}
+
+/// The part of the OpenID Connect discovery document used to get tokens
+#[derive(Deserialize)]
+struct ProviderMetadata {
+    token_endpoint: Url,
+}
+
+impl TokenEndpoint {
+    fn new(token_endpoint: &Option<Url>, issuer: &Option<Url>) -> Result<Self, McpError> {
+        match (token_endpoint, issuer) {
+            (Some(token_endpoint), None) => Ok(TokenEndpoint::Configured(token_endpoint.clone())),
+            (None, Some(issuer)) => Ok(TokenEndpoint::Discovered {
+                issuer: issuer.clone(),
+                token_endpoint: OnceCell::new(),
+            }),
+            (Some(_), Some(_)) => Err(invalid_config(
+                "Only one of token_endpoint and issuer can be set",
+            )),
+            (None, None) => Err(invalid_config(
+                "Either token_endpoint or issuer must be set",
+            )),
+        }
+    }
+
+    /// The URL of the token endpoint, which is discovered the first time it's needed
+    async fn url(&self, client: &Client) -> Result<&Url, McpError> {
+        match self {
+            TokenEndpoint::Configured(url) => Ok(url),
+            TokenEndpoint::Discovered {
+                issuer,
+                token_endpoint,
+            } => {
+                token_endpoint
+                    .get_or_try_init(|| discover(client, issuer))
+                    .await
+            }
+        }
+    }
+}
+
+/// Find the token endpoint of an issuer in its OpenID Connect discovery document
+async fn discover(client: &Client, issuer: &Url) -> Result<Url, McpError> {
+    let mut discovery_url = issuer.clone();
+    // Issuers can have a path, such as Keycloak realms
+    let path = discovery_url.path().trim_end_matches('/').to_string();
+    discovery_url.set_path(&format!("{path}/.well-known/openid-configuration"));
+    debug!("Discovering token endpoint from: {}", discovery_url);
+
+    let (status, body) = send(client.get(discovery_url)).await?;
+    if !status.is_success() {
+        return Err(internal_error(format!(
+            "OpenID Connect discovery for {issuer} failed with status {status}"
+        )));
+    }
+    let metadata: ProviderMetadata = parse(status, &body)?;
+    info!("Discovered token endpoint: {}", metadata.token_endpoint);
+    Ok(metadata.token_endpoint)
+}
+
+/// The grant used to get access tokens
+enum Grant {
+    /// The refresh token grant, holding the latest refresh token and the configured one it
+    /// derives from
+    RefreshToken {
+        refresh_token: Mutex<String>,
+        configured: String,
+    },
+    ClientCredentials,
+}
+
+/// A successful response of the token endpoint
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+    refresh_token: Option<String>,
+}
+
+/// An error response of the token endpoint
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+/// Gets access tokens from an OAuth 2.0 token endpoint
+pub(super) struct OAuth2Provider {
+    token_endpoint: TokenEndpoint,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    client_auth: ClientAuth,
+    scopes: Vec<String>,
+    grant: Grant,
+}
+
+impl OAuth2Provider {
+    /// The form parameters of the grant
+    fn grant_params(&self) -> Result<Vec<(&'static str, String)>, McpError> {
+        Ok(match &self.grant {
+            Grant::RefreshToken { refresh_token, .. } => vec![
+                ("grant_type", String::from("refresh_token")),
+                (
+                    "refresh_token",
+                    refresh_token
+                        .lock()
+                        .map_err(|_| internal_error("Refresh token lock poisoned"))?
+                        .clone(),
+                ),
+            ],
+            Grant::ClientCredentials => vec![("grant_type", String::from("client_credentials"))],
+        })
+    }
+
+    /// Keep a rotated refresh token for the next refresh, returning it to be stored
+    fn rotate(&self, new_refresh_token: String) -> Result<Option<RotatedRefreshToken>, McpError> {
+        let Grant::RefreshToken {
+            refresh_token,
+            configured,
+        } = &self.grant
+        else {
+            return Ok(None);
+        };
+        *refresh_token
+            .lock()
+            .map_err(|_| internal_error("Refresh token lock poisoned"))? =
+            new_refresh_token.clone();
+        Ok(Some(RotatedRefreshToken::new(
+            new_refresh_token,
+            configured,
+        )))
+    }
+}
+
+#[async_trait]
+impl TokenProvider for OAuth2Provider {
+    async fn fetch(&self, client: &Client) -> Result<Token, McpError> {
+        let token_endpoint = self.token_endpoint.url(client).await?;
+        debug!("Making token request to: {}", token_endpoint);
+
+        let mut params = self.grant_params()?;
+        if !self.scopes.is_empty() {
+            params.push(("scope", self.scopes.join(" ")));
+        }
+        let mut request = client.post(token_endpoint.clone());
+        match (&self.client_id, &self.client_secret, self.client_auth) {
+            (Some(client_id), Some(client_secret), ClientAuth::ClientSecretBasic) => {
+                request = request.basic_auth(client_id, Some(client_secret));
+            }
+            (client_id, client_secret, _) => {
+                if let Some(client_id) = client_id {
+                    params.push(("client_id", client_id.clone()));
+                }
+                if let Some(client_secret) = client_secret {
+                    params.push(("client_secret", client_secret.clone()));
+                }
+            }
+        }
+
+        let (status, body) = send(request.form(&params)).await?;
+        if !status.is_success() {
+            return Err(token_error(status, &body));
+        }
+
+        let response: TokenResponse = parse(status, &body)?;
+        let refresh_token = match response.refresh_token {
+            Some(refresh_token) => self.rotate(refresh_token)?,
+            None => None,
+        };
+        Ok(Token {
+            access_token: response.access_token,
+            expires_in: response.expires_in.map(Duration::from_secs),
+            refresh_token,
+        })
+    }
+}
+
+/// The error of a failed token request, with the OAuth error code if the endpoint sent one
+fn token_error(status: StatusCode, body: &str) -> McpError {
+    match serde_json::from_str::<ErrorResponse>(body) {
+        Ok(ErrorResponse {
+            error,
+            error_description: Some(description),
+        }) => internal_error(format!(
+            "Token request failed with status {status}: {error}: {description}"
+        )),
+        Ok(ErrorResponse { error, .. }) => internal_error(format!(
+            "Token request failed with status {status}: {error}"
+        )),
+        Err(_) => internal_error(format!("Token request failed with status {status}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::Matcher;
+
+    use super::*;
+
+    fn refresh_token_config(token_endpoint: String) -> RefreshTokenConfig {
+        RefreshTokenConfig {
+            token_endpoint: Some(token_endpoint.parse().unwrap()),
+            issuer: None,
+            client_id: Some(String::from("mcp")),
+            client_secret: None,
+            client_auth: ClientAuth::default(),
+            refresh_token: String::from("first_refresh_token"),
+            scopes: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_uses_rotated_refresh_tokens() {
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("POST", "/token")
+            .match_header("content-type", "application/x-www-form-urlencoded")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("grant_type".into(), "refresh_token".into()),
+                Matcher::UrlEncoded("refresh_token".into(), "first_refresh_token".into()),
+                Matcher::UrlEncoded("client_id".into(), "mcp".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"access_token":"first_token","token_type":"Bearer","expires_in":300,"refresh_token":"second_refresh_token"}"#,
+            )
+            .create_async()
+            .await;
+        let second = server
+            .mock("POST", "/token")
+            .match_body(Matcher::UrlEncoded(
+                "refresh_token".into(),
+                "second_refresh_token".into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"access_token":"second_token","token_type":"Bearer"}"#)
+            .create_async()
+            .await;
+        let provider = refresh_token_config(format!("{}/token", server.url()))
+            .provider(None)
+            .unwrap();
+        let client = Client::new();
+
+        let token = provider.fetch(&client).await.unwrap();
+        assert_eq!(token.access_token, "first_token");
+        assert_eq!(token.expires_in, Some(Duration::from_secs(300)));
+        assert_eq!(
+            token.refresh_token,
+            Some(RotatedRefreshToken::new(
+                "second_refresh_token",
+                "first_refresh_token"
+            ))
+        );
+
+        let token = provider.fetch(&client).await.unwrap();
+        assert_eq!(token.access_token, "second_token");
+        assert_eq!(token.refresh_token, None);
+
+        first.assert_async().await;
+        second.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn it_discovers_the_token_endpoint_for_client_credentials() {
+        let mut server = mockito::Server::new_async().await;
+        let discovery = server
+            .mock("GET", "/realms/acme/.well-known/openid-configuration")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({
+                    "issuer": format!("{}/realms/acme", server.url()),
+                    "token_endpoint": format!("{}/realms/acme/token", server.url()),
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let token = server
+            .mock("POST", "/realms/acme/token")
+            // mcp:secret
+            .match_header("authorization", "Basic bWNwOnNlY3JldA==")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("grant_type".into(), "client_credentials".into()),
+                Matcher::UrlEncoded("scope".into(), "graphql admin".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"access_token":"new_token","token_type":"Bearer","expires_in":60}"#)
+            .expect(2)
+            .create_async()
+            .await;
+        let provider = ClientCredentialsConfig {
+            token_endpoint: None,
+            issuer: Some(format!("{}/realms/acme/", server.url()).parse().unwrap()),
+            client_id: String::from("mcp"),
+            client_secret: String::from("secret"),
+            client_auth: ClientAuth::ClientSecretBasic,
+            scopes: vec![String::from("graphql"), String::from("admin")],
+        }
+        .provider()
+        .unwrap();
+        let client = Client::new();
+
+        for _ in 0..2 {
+            assert_eq!(
+                provider.fetch(&client).await.unwrap().access_token,
+                "new_token"
+            );
+        }
+        discovery.assert_async().await;
+        token.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn it_reports_oauth_errors() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/token")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"error":"invalid_grant","error_description":"Refresh token expired"}"#)
+            .create_async()
+            .await;
+        let provider = refresh_token_config(format!("{}/token", server.url()))
+            .provider(None)
+            .unwrap();
+
+        let error = provider.fetch(&Client::new()).await.err().unwrap();
+
+        assert_eq!(
+            error.message,
+            "Token request failed with status 400 Bad Request: invalid_grant: Refresh token expired"
+        );
+    }
+
+    #[test]
+    fn it_requires_one_token_endpoint() {
+        let mut config = refresh_token_config(String::from("https://auth.example.com/token"));
+        config.issuer = Some("https://auth.example.com".parse().unwrap());
+        assert!(config.provider(None).is_err());
+
+        config.token_endpoint = None;
+        config.issuer = None;
+        assert!(config.provider(None).is_err());
+    }
+
+    #[test]
+    fn it_only_uses_rotated_refresh_tokens_of_the_configured_one() {
+        let config = refresh_token_config(String::from("https://auth.example.com/token"));
+        let refresh_token = |rotated| {
+            config
+                .provider(rotated)
+                .unwrap()
+                .grant_params()
+                .unwrap()
+                .into_iter()
+                .find(|(name, _)| *name == "refresh_token")
+                .map(|(_, value)| value)
+                .unwrap()
+        };
+
+        assert_eq!(refresh_token(None), "first_refresh_token");
+        assert_eq!(
+            refresh_token(Some(RotatedRefreshToken::new(
+                "rotated_refresh_token",
+                "first_refresh_token"
+            ))),
+            "rotated_refresh_token"
+        );
+        assert_eq!(
+            refresh_token(Some(RotatedRefreshToken::new(
+                "rotated_refresh_token",
+                "old_refresh_token"
+            ))),
+            "first_refresh_token"
+        );
+    }
+}
@@ -0,0 +1,526 @@
+//! Storage of refreshed tokens
+//!
+//! The token manager saves each access token it gets to a store, along with refresh tokens
+//! rotated by the token provider. A rotated refresh token is only used while the configured
+//! refresh token it derives from is unchanged, so a new refresh token in the config takes
+//! effect. The tokens can be kept in the config file, in separate token files, or only in
+//! memory. Files are replaced atomically by writing a temporary file and
+//! renaming it over the original, so readers never see a partially written file.
+
+use std::fs;
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use sha2::{Digest as _, Sha256};
+use tempfile::NamedTempFile;
+use tracing::warn;
+
+use crate::token_manager::bearer;
+
+/// The key of the headers in the config file
+const HEADERS_KEY: &str = "headers";
+
+/// The header holding the access token in the config file
+const AUTHORIZATION_HEADER: &str = "Authorization";
+
+/// The keys of the token provider holding the refresh token in the config file
+const PROVIDER_KEYS: [&str; 2] = ["token_refresh", "provider"];
+
+/// The key of the refresh token in the token provider
+const REFRESH_TOKEN_KEY: &str = "refresh_token";
+
+/// Where refreshed tokens are kept
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TokenStoreConfig {
+    /// Keep the access token in the `Authorization` header of the config file, and rotated
+    /// refresh tokens in the token provider. Without a config file, tokens are only kept in
+    /// memory.
+    #[default]
+    Config,
+
+    /// Keep the access token in a separate file, and rotated refresh tokens in the same file
+    /// with a `.refresh` extension added
+    File { path: PathBuf },
+
+    /// Keep tokens in memory only
+    Memory,
+}
+
+impl TokenStoreConfig {
+    /// Create the store, for a server started with the given config file
+    pub fn store(&self, config_path: Option<&Path>) -> Arc<dyn TokenStore> {
+        match (self, config_path) {
+            (TokenStoreConfig::Config, Some(config_path)) => {
+                Arc::new(ConfigFileTokenStore::new(config_path))
+            }
+            (TokenStoreConfig::Config, None) => {
+                warn!("No config file to store refreshed tokens in, keeping them in memory");
+                Arc::new(MemoryTokenStore::default())
+            }
+            (TokenStoreConfig::File { path }, _) => Arc::new(SidecarTokenStore::new(path)),
+            (TokenStoreConfig::Memory, _) => Arc::new(MemoryTokenStore::default()),
+        }
+    }
+}
+
+/// A refresh token rotated by the token provider
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RotatedRefreshToken {
+    pub refresh_token: String,
+
+    /// The SHA-256 hash of the configured refresh token it derives from, through one or more
+    /// rotations
+    pub configured: String,
+}
+
+impl RotatedRefreshToken {
+    pub fn new(refresh_token: impl Into<String>, configured_refresh_token: &str) -> Self {
+        Self {
+            refresh_token: refresh_token.into(),
+            configured: format!("{:x}", Sha256::digest(configured_refresh_token.as_bytes())),
+        }
+    }
+
+    /// Whether the token derives from the given configured refresh token
+    pub fn derives_from(&self, configured_refresh_token: &str) -> bool {
+        self.configured == format!("{:x}", Sha256::digest(configured_refresh_token.as_bytes()))
+    }
+}
+
+/// An error loading or saving an access token
+#[derive(Debug, thiserror::Error)]
+pub enum TokenStoreError {
+    #[error("Failed to access token file: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Invalid YAML in config file: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    #[error("Invalid refresh token file: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid config file: {0}")]
+    InvalidConfig(String),
+}
+
+/// Where refreshed access tokens are kept
+pub trait TokenStore: Send + Sync {
+    /// Load the stored access token, if there is one
+    fn load(&self) -> Result<Option<String>, TokenStoreError>;
+
+    /// Save an access token, replacing the stored one
+    fn save(&self, token: &str) -> Result<(), TokenStoreError>;
+
+    /// Load the stored refresh token, if one was rotated
+    fn load_refresh_token(&self) -> Result<Option<RotatedRefreshToken>, TokenStoreError>;
+
+    /// Save a rotated refresh token, replacing the stored one
+    fn save_refresh_token(&self, token: &RotatedRefreshToken) -> Result<(), TokenStoreError>;
+}
+
+/// Keeps the access token in the `Authorization` header of a YAML config file, and rotated
+/// refresh tokens in `token_refresh.provider.refresh_token`. A rotated refresh token replaces
+/// the configured one, so it derives from itself.
+///
+/// The file is parsed and serialized again, so the header is found whatever its quoting or case,
+/// but comments in the file aren't kept.
+#[derive(Debug, Clone)]
+pub struct ConfigFileTokenStore {
+    path: PathBuf,
+}
+
+impl ConfigFileTokenStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn read(&self) -> Result<Mapping, TokenStoreError> {
+        match serde_yaml::from_str(&fs::read_to_string(&self.path)?)? {
+            Value::Mapping(config) => Ok(config),
+            // An empty file
+            Value::Null => Ok(Mapping::new()),
+            _ => Err(TokenStoreError::InvalidConfig(String::from(
+                "the config file is not a YAML mapping",
+            ))),
+        }
+    }
+}
+
+/// Find a header by name, ignoring case
+fn header_key(headers: &Mapping, name: &str) -> Option<Value> {
+    headers
+        .keys()
+        .find(|key| {
+            key.as_str()
+                .is_some_and(|key| key.eq_ignore_ascii_case(name))
+        })
+        .cloned()
+}
+
+impl TokenStore for ConfigFileTokenStore {
+    fn load(&self) -> Result<Option<String>, TokenStoreError> {
+        let config = self.read()?;
+        let Some(Value::Mapping(headers)) = config.get(HEADERS_KEY) else {
+            return Ok(None);
+        };
+        Ok(header_key(headers, AUTHORIZATION_HEADER)
+            .and_then(|key| headers.get(&key))
+            .and_then(Value::as_str)
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| token.trim().to_string()))
+    }
+
+    fn save(&self, token: &str) -> Result<(), TokenStoreError> {
+        let mut config = self.read()?;
+        let headers = config
+            .entry(Value::from(HEADERS_KEY))
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        let Value::Mapping(headers) = headers else {
+            return Err(TokenStoreError::InvalidConfig(String::from(
+                "`headers` is not a YAML mapping",
+            )));
+        };
+        let key = header_key(headers, AUTHORIZATION_HEADER)
+            .unwrap_or_else(|| Value::from(AUTHORIZATION_HEADER));
+        headers.insert(key, Value::from(bearer(token)));
+
+        write_atomically(&self.path, serde_yaml::to_string(&config)?.as_bytes())?;
+        Ok(())
+    }
+
+    fn load_refresh_token(&self) -> Result<Option<RotatedRefreshToken>, TokenStoreError> {
+        let config = self.read()?;
+        let provider =
+            PROVIDER_KEYS
+                .iter()
+                .try_fold(&config, |mapping, key| match mapping.get(key) {
+                    Some(Value::Mapping(mapping)) => Some(mapping),
+                    _ => None,
+                });
+        Ok(provider
+            .and_then(|provider| provider.get(REFRESH_TOKEN_KEY))
+            .and_then(Value::as_str)
+            .map(|token| RotatedRefreshToken::new(token, token)))
+    }
+
+    fn save_refresh_token(&self, token: &RotatedRefreshToken) -> Result<(), TokenStoreError> {
+        let mut config = self.read()?;
+        let mut provider = &mut config;
+        for key in PROVIDER_KEYS {
+            provider = match provider.get_mut(key) {
+                Some(Value::Mapping(mapping)) => mapping,
+                _ => {
+                    return Err(TokenStoreError::InvalidConfig(String::from(
+                        "`token_refresh.provider` is not a YAML mapping",
+                    )));
+                }
+            };
+        }
+        provider.insert(
+            Value::from(REFRESH_TOKEN_KEY),
+            Value::from(token.refresh_token.as_str()),
+        );
+
+        write_atomically(&self.path, serde_yaml::to_string(&config)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Keeps the access token alone in a separate file, so the config file is never written.
+/// Rotated refresh tokens are kept as JSON in another file beside it, with a `.refresh`
+/// extension added.
+#[derive(Debug, Clone)]
+pub struct SidecarTokenStore {
+    path: PathBuf,
+}
+
+impl SidecarTokenStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn refresh_token_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".refresh");
+        path.into()
+    }
+}
+
+/// Read a token alone in a file
+fn read_token_file(path: &Path) -> Result<Option<String>, TokenStoreError> {
+    match fs::read_to_string(path) {
+        Ok(token) => Ok(Some(token.trim().to_string()).filter(|token| !token.is_empty())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl TokenStore for SidecarTokenStore {
+    fn load(&self) -> Result<Option<String>, TokenStoreError> {
+        read_token_file(&self.path)
+    }
+
+    fn save(&self, token: &str) -> Result<(), TokenStoreError> {
+        write_atomically(&self.path, format!("{token}\n").as_bytes())?;
+        Ok(())
+    }
+
+    fn load_refresh_token(&self) -> Result<Option<RotatedRefreshToken>, TokenStoreError> {
+        Ok(match read_token_file(&self.refresh_token_path())? {
+            Some(contents) => Some(serde_json::from_str(&contents)?),
+            None => None,
+        })
+    }
+
+    fn save_refresh_token(&self, token: &RotatedRefreshToken) -> Result<(), TokenStoreError> {
+        write_atomically(
+            &self.refresh_token_path(),
+            format!("{}\n", serde_json::to_string(token)?).as_bytes(),
+        )?;
+        Ok(())
+    }
+}
+
+/// Keeps the tokens in memory only, so they're lost when the server stops
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore {
+    token: Mutex<Option<String>>,
+    refresh_token: Mutex<Option<RotatedRefreshToken>>,
+}
+
+fn lock<T>(token: &Mutex<Option<T>>) -> io::Result<std::sync::MutexGuard<'_, Option<T>>> {
+    token
+        .lock()
+        .map_err(|_| io::Error::other("token store lock poisoned"))
+}
+
+impl TokenStore for MemoryTokenStore {
+    fn load(&self) -> Result<Option<String>, TokenStoreError> {
+        Ok(lock(&self.token)?.clone())
+    }
+
+    fn save(&self, token: &str) -> Result<(), TokenStoreError> {
+        *lock(&self.token)? = Some(token.to_string());
+        Ok(())
+    }
+
+    fn load_refresh_token(&self) -> Result<Option<RotatedRefreshToken>, TokenStoreError> {
+        Ok(lock(&self.refresh_token)?.clone())
+    }
+
+    fn save_refresh_token(&self, token: &RotatedRefreshToken) -> Result<(), TokenStoreError> {
+        *lock(&self.refresh_token)? = Some(token.clone());
+        Ok(())
+    }
+}
+
+/// Replace the contents of a file by renaming a temporary file over it. The file keeps its
+/// permissions, and new files are only readable by their owner since they hold a secret.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut file = NamedTempFile::new_in(directory)?;
+    file.write_all(contents)?;
+    file.as_file().sync_all()?;
+
+    match fs::metadata(path) {
+        Ok(metadata) => fs::set_permissions(file.path(), metadata.permissions())?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            // Temporary files are already only readable by their owner on Unix
+        }
+        Err(e) => return Err(e),
+    }
+
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn config_file_store_round_trips_the_token() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(
+            &config_path,
+            r#"
+endpoint: "https://api.example.com/graphql"
+headers:
+  authorization: "Bearer old_token"
+  x-tenant: acme
+"#,
+        )
+        .unwrap();
+        let store = ConfigFileTokenStore::new(&config_path);
+
+        assert_eq!(store.load().unwrap().as_deref(), Some("old_token"));
+        store.save("new_token").unwrap();
+        assert_eq!(store.load().unwrap().as_deref(), Some("new_token"));
+
+        insta::assert_snapshot!(fs::read_to_string(&config_path).unwrap(), @r"
+        endpoint: https://api.example.com/graphql
+        headers:
+          authorization: Bearer new_token
+          x-tenant: acme
+        ");
+    }
+
+    #[test]
+    fn config_file_store_adds_the_header() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(&config_path, "endpoint: https://api.example.com/graphql\n").unwrap();
+        let store = ConfigFileTokenStore::new(&config_path);
+
+        assert_eq!(store.load().unwrap(), None);
+        store.save("new_token").unwrap();
+
+        insta::assert_snapshot!(fs::read_to_string(&config_path).unwrap(), @r"
+        endpoint: https://api.example.com/graphql
+        headers:
+          Authorization: Bearer new_token
+        ");
+    }
+
+    #[test]
+    fn config_file_store_round_trips_the_refresh_token() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(
+            &config_path,
+            r#"
+endpoint: "https://api.example.com/graphql"
+token_refresh:
+  provider:
+    type: refresh_token
+    token_endpoint: https://auth.example.com/token
+    refresh_token: first_refresh_token
+"#,
+        )
+        .unwrap();
+        let store = ConfigFileTokenStore::new(&config_path);
+
+        let stored = store.load_refresh_token().unwrap().unwrap();
+        assert_eq!(stored.refresh_token, "first_refresh_token");
+        assert!(stored.derives_from("first_refresh_token"));
+        store
+            .save_refresh_token(&RotatedRefreshToken::new(
+                "second_refresh_token",
+                "first_refresh_token",
+            ))
+            .unwrap();
+
+        insta::assert_snapshot!(fs::read_to_string(&config_path).unwrap(), @r"
+        endpoint: https://api.example.com/graphql
+        token_refresh:
+          provider:
+            type: refresh_token
+            token_endpoint: https://auth.example.com/token
+            refresh_token: second_refresh_token
+        ");
+        assert!(matches!(
+            ConfigFileTokenStore::new(temp_dir.path().join("missing.yaml"))
+                .save_refresh_token(&RotatedRefreshToken::new("token", "token")),
+            Err(TokenStoreError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn config_file_store_requires_a_mapping() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(&config_path, "headers: [Authorization]\n").unwrap();
+
+        assert!(matches!(
+            ConfigFileTokenStore::new(&config_path).save("new_token"),
+            Err(TokenStoreError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            ConfigFileTokenStore::new(&config_path)
+                .save_refresh_token(&RotatedRefreshToken::new("new", "old")),
+            Err(TokenStoreError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            ConfigFileTokenStore::new(temp_dir.path().join("missing.yaml")).load(),
+            Err(TokenStoreError::Io(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_keep_their_permissions() {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(&config_path, "headers: {}\n").unwrap();
+        fs::set_permissions(&config_path, fs::Permissions::from_mode(0o640)).unwrap();
+        let token_path = temp_dir.path().join("token");
+
+        ConfigFileTokenStore::new(&config_path)
+            .save("new_token")
+            .unwrap();
+        SidecarTokenStore::new(&token_path)
+            .save("new_token")
+            .unwrap();
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&config_path), 0o640);
+        assert_eq!(mode(&token_path), 0o600);
+    }
+
+    #[test]
+    fn sidecar_store_round_trips_the_token() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SidecarTokenStore::new(temp_dir.path().join("token"));
+
+        assert_eq!(store.load().unwrap(), None);
+        store.save("first_token").unwrap();
+        store.save("second_token").unwrap();
+        assert_eq!(store.load().unwrap().as_deref(), Some("second_token"));
+
+        assert_eq!(store.load_refresh_token().unwrap(), None);
+        let rotated = RotatedRefreshToken::new("rotated_refresh_token", "refresh_token");
+        store.save_refresh_token(&rotated).unwrap();
+        assert_eq!(store.load_refresh_token().unwrap(), Some(rotated));
+        assert!(
+            fs::read_to_string(temp_dir.path().join("token.refresh"))
+                .unwrap()
+                .starts_with(r#"{"refresh_token":"rotated_refresh_token","configured":""#)
+        );
+        assert_eq!(store.load().unwrap().as_deref(), Some("second_token"));
+    }
+
+    #[test]
+    fn memory_store_round_trips_the_token() {
+        let store = MemoryTokenStore::default();
+
+        assert_eq!(store.load().unwrap(), None);
+        store.save("new_token").unwrap();
+        assert_eq!(store.load().unwrap().as_deref(), Some("new_token"));
+        assert_eq!(store.load_refresh_token().unwrap(), None);
+        let rotated = RotatedRefreshToken::new("rotated_refresh_token", "refresh_token");
+        store.save_refresh_token(&rotated).unwrap();
+        assert_eq!(store.load_refresh_token().unwrap(), Some(rotated));
+    }
+
+    #[test]
+    fn rotated_refresh_tokens_remember_the_configured_one() {
+        let rotated = RotatedRefreshToken::new("rotated_refresh_token", "refresh_token");
+
+        assert!(rotated.derives_from("refresh_token"));
+        assert!(!rotated.derives_from("new_refresh_token"));
+        assert!(!rotated.configured.contains("refresh_token"));
+    }
+}
@@ -0,0 +1,378 @@
+//! Upstream HTTP client
+//!
+//! Requests to GraphQL endpoints and the token refresh endpoint share a single pooled HTTP
+//! client, built once at startup from the `upstream` config section, so connections and TLS
+//! sessions are reused across tool calls. Failed queries are retried, and endpoints that
+//! keep failing are cut off by a circuit breaker.
+
+use std::{path::PathBuf, time::Duration};
+
+use reqwest::{Certificate, Client, Identity, Proxy, header::HeaderMap};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tracing::debug;
+use url::Url;
+
+use crate::errors::ServerError;
+use crate::generated::telemetry::TelemetryMetric;
+use crate::meter;
+
+pub mod circuit_breaker;
+pub mod retry;
+pub mod websocket;
+
+use circuit_breaker::{CircuitBreaker, CircuitBreakerConfig};
+use retry::RetryConfig;
+use websocket::WebSocketConnector;
+
+/// Configuration for the HTTP client used to reach upstream services
+///
+/// Defaults are read from the `REQWEST_TIMEOUT`, `REQWEST_CONNECT_TIMEOUT`,
+/// `REQWEST_USER_AGENT`, `REQWEST_SSL_VERIFY` and `REQWEST_SSL_VERIFY_HOSTNAME` environment
+/// variables when they are set.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct UpstreamConfig {
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// The timeout for each request, from connecting until the response is read (default: 30s)
+    pub timeout: Duration,
+
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// The timeout for establishing a connection, including WebSocket connections for
+    /// subscriptions (default: 10s)
+    pub connect_timeout: Duration,
+
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// How long idle connections are kept in the pool (default: 90s)
+    pub pool_idle_timeout: Duration,
+
+    /// The maximum number of idle connections kept per host (default: unlimited)
+    pub pool_max_idle_per_host: Option<usize>,
+
+    /// The user agent sent with every request (default: curl/8.4.0)
+    pub user_agent: String,
+
+    /// Use HTTP/2 without negotiating it first, for endpoints that only accept HTTP/2
+    /// (default: false)
+    pub http2_prior_knowledge: bool,
+
+    /// A proxy to send all requests through. The `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY`
+    /// environment variables are used when unset.
+    pub proxy: Option<Url>,
+
+    /// TLS configuration
+    pub tls: UpstreamTlsConfig,
+
+    /// Retry policy for failed queries
+    pub retry: RetryConfig,
+
+    /// Circuit breaker for endpoints that keep failing
+    pub circuit_breaker: CircuitBreakerConfig,
+}
+
+/// TLS configuration for upstream requests
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct UpstreamTlsConfig {
+    /// Path to a PEM bundle of certificate authorities to trust, in addition to the
+    /// system roots
+    pub ca_bundle: Option<PathBuf>,
+
+    /// Path to a PEM client certificate to present for mutual TLS. Requires `client_key`.
+    pub client_cert: Option<PathBuf>,
+
+    /// Path to the PEM private key of the client certificate
+    pub client_key: Option<PathBuf>,
+
+    /// Accept invalid certificates. Only use this for local development (default: false)
+    pub danger_accept_invalid_certs: bool,
+
+    /// Accept certificates that don't match the hostname. Only use this for local
+    /// development (default: false)
+    pub danger_accept_invalid_hostnames: bool,
+}
+
+impl Default for UpstreamConfig {
+    fn default() -> Self {
+        let env_secs = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .map(Duration::from_secs)
+        };
+        let env_disabled = |name: &str| std::env::var(name).is_ok_and(|s| s == "false");
+        Self {
+            timeout: env_secs("REQWEST_TIMEOUT").unwrap_or(Duration::from_secs(30)),
+            connect_timeout: env_secs("REQWEST_CONNECT_TIMEOUT").unwrap_or(Duration::from_secs(10)),
+            pool_idle_timeout: Duration::from_secs(90),
+            pool_max_idle_per_host: None,
+            user_agent: std::env::var("REQWEST_USER_AGENT")
+                .unwrap_or_else(|_| "curl/8.4.0".to_string()),
+            http2_prior_knowledge: false,
+            proxy: None,
+            tls: UpstreamTlsConfig {
+                danger_accept_invalid_certs: env_disabled("REQWEST_SSL_VERIFY"),
+                danger_accept_invalid_hostnames: env_disabled("REQWEST_SSL_VERIFY_HOSTNAME"),
+                ..Default::default()
+            },
+            retry: RetryConfig::default(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+        }
+    }
+}
+
+impl UpstreamConfig {
+    /// Build the shared HTTP client
+    pub fn build_client(&self) -> Result<Client, ServerError> {
+        let mut builder = Client::builder()
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .user_agent(&self.user_agent)
+            .danger_accept_invalid_certs(self.tls.danger_accept_invalid_certs)
+            .danger_accept_invalid_hostnames(self.tls.danger_accept_invalid_hostnames);
+
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy.as_str())?);
+        }
+        if let Some(ca_bundle) = &self.tls.ca_bundle {
+            for certificate in Certificate::from_pem_bundle(&std::fs::read(ca_bundle)?)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        match (&self.tls.client_cert, &self.tls.client_key) {
+            (Some(cert), Some(key)) => {
+                let mut pem = std::fs::read(cert)?;
+                pem.push(b'\n');
+                pem.extend(std::fs::read(key)?);
+                builder = builder.identity(Identity::from_pem(&pem)?);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(ServerError::UpstreamClient(
+                    "client_cert and client_key must be set together".to_string(),
+                ));
+            }
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+/// An error sending a request upstream
+#[derive(Debug, thiserror::Error)]
+pub enum UpstreamError {
+    #[error("Failed to send GraphQL request: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("Failed to send GraphQL request: the circuit for {0} is open after repeated failures")]
+    CircuitOpen(Url),
+}
+
+/// The shared HTTP client, with the retry policy and circuit breaker for upstream requests
+#[derive(Debug, Clone)]
+pub struct UpstreamClient {
+    client: Client,
+    websocket: WebSocketConnector,
+    retry: RetryConfig,
+    circuit_breaker: CircuitBreaker,
+}
+
+impl UpstreamClient {
+    pub fn new(config: &UpstreamConfig) -> Result<Self, ServerError> {
+        Ok(Self {
+            client: config.build_client()?,
+            websocket: WebSocketConnector::new(config)?,
+            retry: config.retry.clone(),
+            circuit_breaker: CircuitBreaker::new(config.circuit_breaker.clone()),
+        })
+    }
+
+    /// The underlying HTTP client
+    pub fn http(&self) -> &Client {
+        &self.client
+    }
+
+    /// The connector for WebSocket connections, configured like the HTTP client
+    pub fn websocket(&self) -> &WebSocketConnector {
+        &self.websocket
+    }
+
+    /// The circuit breaker shared by all requests made with this client
+    pub fn circuit_breaker(&self) -> &CircuitBreaker {
+        &self.circuit_breaker
+    }
+
+    /// Post a JSON body to an endpoint. Transient failures are retried if the request is
+    /// idempotent, and count towards opening the circuit of the endpoint.
+    pub(crate) async fn post(
+        &self,
+        endpoint: &Url,
+        headers: &HeaderMap,
+        body: String,
+        idempotent: bool,
+    ) -> Result<reqwest::Response, UpstreamError> {
+        let mut attempt = 0;
+        loop {
+            if !self.circuit_breaker.allows(endpoint) {
+                return Err(UpstreamError::CircuitOpen(endpoint.clone()));
+            }
+
+            let result = self
+                .client
+                .post(endpoint.as_str())
+                .headers(headers.clone())
+                .header("Content-Type", "application/json")
+                .body(body.clone())
+                .send()
+                .await;
+            let failed = match &result {
+                Ok(response) => self.retry.is_retryable_status(response.status()),
+                Err(error) => RetryConfig::is_retryable_error(error),
+            };
+            self.circuit_breaker.record(endpoint, !failed);
+
+            if !failed || !idempotent || attempt >= self.retry.max_retries {
+                return Ok(result?);
+            }
+            let backoff = self.retry.backoff(attempt);
+            debug!("Retrying request to {endpoint} in {backoff:?}");
+            meter::METER
+                .u64_counter(TelemetryMetric::UpstreamRetryCount.as_str())
+                .build()
+                .add(1, &[]);
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+}
+
+impl Default for UpstreamClient {
+    fn default() -> Self {
+        Self {
+            client: Client::new(),
+            websocket: WebSocketConnector::default(),
+            retry: RetryConfig::default(),
+            circuit_breaker: CircuitBreaker::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_builds_the_default_client() {
+        UpstreamConfig::default().build_client().unwrap();
+    }
+
+    #[test]
+    fn it_requires_a_key_for_client_certificates() {
+        let config = UpstreamConfig {
+            tls: UpstreamTlsConfig {
+                client_cert: Some(PathBuf::from("client.pem")),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            config.build_client().unwrap_err().to_string(),
+            "Invalid upstream HTTP client configuration: client_cert and client_key must be set together"
+        );
+    }
+
+    #[test]
+    fn it_parses_durations() {
+        let config: UpstreamConfig = serde_json::from_value(
+            serde_json::json!({ "timeout": "5s", "http2_prior_knowledge": true }),
+        )
+        .unwrap();
+
+        assert_eq!(config.timeout, Duration::from_secs(5));
+        assert!(config.http2_prior_knowledge);
+    }
+
+    fn client(max_retries: u32, failure_threshold: u32) -> UpstreamClient {
+        UpstreamClient::new(&UpstreamConfig {
+            retry: RetryConfig {
+                max_retries,
+                initial_backoff: Duration::from_millis(1),
+                ..Default::default()
+            },
+            circuit_breaker: CircuitBreakerConfig {
+                enabled: true,
+                failure_threshold,
+                open_duration: Duration::from_secs(30),
+            },
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_retries_idempotent_requests() {
+        let mut server = mockito::Server::new_async().await;
+        let failure = server
+            .mock("POST", "/")
+            .with_status(503)
+            .expect(3)
+            .create_async()
+            .await;
+        let endpoint = Url::parse(&server.url()).unwrap();
+        let client = client(1, 10);
+
+        let response = client
+            .post(&endpoint, &HeaderMap::new(), "{}".to_string(), true)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 503);
+
+        let response = client
+            .post(&endpoint, &HeaderMap::new(), "{}".to_string(), false)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 503);
+        failure.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn it_fails_fast_when_the_circuit_is_open() {
+        let mut server = mockito::Server::new_async().await;
+        let failure = server
+            .mock("POST", "/")
+            .with_status(502)
+            .expect(2)
+            .create_async()
+            .await;
+        let endpoint = Url::parse(&server.url()).unwrap();
+        let client = client(5, 2);
+
+        // Retries stop once the circuit opens
+        let error = client
+            .post(&endpoint, &HeaderMap::new(), "{}".to_string(), true)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, UpstreamError::CircuitOpen(_)));
+        assert!(client.circuit_breaker().is_open());
+
+        let error = client
+            .post(&endpoint, &HeaderMap::new(), "{}".to_string(), false)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, UpstreamError::CircuitOpen(_)));
+        failure.assert_async().await;
+    }
+}
@@ -0,0 +1,210 @@
+//! Circuit breaker for upstream requests
+//!
+//! After a number of consecutive failed requests to an endpoint, the circuit opens and
+//! requests to the endpoint fail immediately instead of waiting on an upstream that is down.
+//! Once the open duration has passed, a single trial request is let through: the circuit
+//! closes if it succeeds and opens again if it fails.
+//!
+//! While any circuit is open, the server reports itself as unready through the health check.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tracing::warn;
+use url::Url;
+
+use crate::generated::telemetry::TelemetryMetric;
+use crate::meter;
+
+/// Configuration for the upstream circuit breaker
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    /// Whether to fail fast when an endpoint is persistently failing (default: false)
+    pub enabled: bool,
+
+    /// The number of consecutive failed requests that opens the circuit (default: 5)
+    pub failure_threshold: u32,
+
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// How long the circuit stays open before a trial request is let through (default: 30s)
+    pub open_duration: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    trial_started_at: Option<Instant>,
+}
+
+/// Tracks the failures of each upstream endpoint, shared by all sessions
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    config: Arc<CircuitBreakerConfig>,
+    circuits: Arc<Mutex<HashMap<Url, Circuit>>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            circuits: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Whether a request to the endpoint may be sent
+    pub(crate) fn allows(&self, endpoint: &Url) -> bool {
+        if !self.config.enabled {
+            return true;
+        }
+        let Ok(mut circuits) = self.circuits.lock() else {
+            return true;
+        };
+        let Some(circuit) = circuits.get_mut(endpoint) else {
+            return true;
+        };
+        match circuit.opened_at {
+            None => true,
+            Some(opened_at) if opened_at.elapsed() < self.config.open_duration => false,
+            // Another trial is in flight, unless it was abandoned without an outcome
+            Some(_)
+                if circuit
+                    .trial_started_at
+                    .is_some_and(|started_at| started_at.elapsed() < self.config.open_duration) =>
+            {
+                false
+            }
+            Some(_) => {
+                circuit.trial_started_at = Some(Instant::now());
+                true
+            }
+        }
+    }
+
+    /// Record the outcome of a request to the endpoint
+    pub(crate) fn record(&self, endpoint: &Url, success: bool) {
+        if !self.config.enabled {
+            return;
+        }
+        let Ok(mut circuits) = self.circuits.lock() else {
+            return;
+        };
+        if success {
+            circuits.remove(endpoint);
+            return;
+        }
+
+        let circuit = circuits.entry(endpoint.clone()).or_default();
+        circuit.consecutive_failures = circuit.consecutive_failures.saturating_add(1);
+        circuit.trial_started_at = None;
+        if circuit.opened_at.is_some()
+            || circuit.consecutive_failures >= self.config.failure_threshold
+        {
+            if circuit.opened_at.is_none() {
+                warn!(
+                    "Opening the circuit for {endpoint} after {} consecutive failures",
+                    circuit.consecutive_failures
+                );
+                meter::METER
+                    .u64_counter(TelemetryMetric::UpstreamCircuitBreakerOpenCount.as_str())
+                    .build()
+                    .add(1, &[]);
+            }
+            circuit.opened_at = Some(Instant::now());
+        }
+    }
+
+    /// Whether the circuit of any endpoint is open
+    pub fn is_open(&self) -> bool {
+        self.circuits.lock().is_ok_and(|circuits| {
+            circuits.values().any(|circuit| {
+                circuit
+                    .opened_at
+                    .is_some_and(|opened_at| opened_at.elapsed() < self.config.open_duration)
+            })
+        })
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(CircuitBreakerConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(open_duration: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            enabled: true,
+            failure_threshold: 2,
+            open_duration,
+        })
+    }
+
+    fn endpoint() -> Url {
+        Url::parse("http://localhost:4000/graphql").unwrap()
+    }
+
+    #[test]
+    fn it_opens_after_consecutive_failures() {
+        let breaker = breaker(Duration::from_secs(30));
+
+        breaker.record(&endpoint(), false);
+        breaker.record(&endpoint(), true);
+        breaker.record(&endpoint(), false);
+        assert!(breaker.allows(&endpoint()));
+        assert!(!breaker.is_open());
+
+        breaker.record(&endpoint(), false);
+        assert!(!breaker.allows(&endpoint()));
+        assert!(breaker.is_open());
+        assert!(breaker.allows(&Url::parse("http://localhost:4001/graphql").unwrap()));
+    }
+
+    #[test]
+    fn it_lets_a_single_trial_request_through_after_the_open_duration() {
+        let breaker = breaker(Duration::from_millis(50));
+        breaker.record(&endpoint(), false);
+        breaker.record(&endpoint(), false);
+        std::thread::sleep(Duration::from_millis(60));
+
+        assert!(!breaker.is_open());
+        assert!(breaker.allows(&endpoint()));
+        assert!(!breaker.allows(&endpoint()));
+
+        breaker.record(&endpoint(), true);
+        assert!(breaker.allows(&endpoint()));
+    }
+
+    #[test]
+    fn it_does_nothing_when_disabled() {
+        let breaker = CircuitBreaker::default();
+        for _ in 0..10 {
+            breaker.record(&endpoint(), false);
+        }
+
+        assert!(breaker.allows(&endpoint()));
+        assert!(!breaker.is_open());
+    }
+}
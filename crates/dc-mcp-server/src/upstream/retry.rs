@@ -0,0 +1,103 @@
+//! Retry policy for upstream requests
+//!
+//! Only idempotent operations (queries) are retried. Retries back off exponentially, with
+//! jitter so that clients that failed together don't retry together.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use reqwest::StatusCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Configuration for retrying failed upstream requests
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// The maximum number of times a failed query is retried (default: 0)
+    pub max_retries: u32,
+
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// The delay before the first retry, doubled for each following retry (default: 100ms)
+    pub initial_backoff: Duration,
+
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// The maximum delay between retries (default: 2s)
+    pub max_backoff: Duration,
+
+    /// The HTTP status codes that are retried, in addition to connection errors and timeouts
+    /// (default: [502, 503, 504])
+    pub status_codes: Vec<u16>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+            status_codes: vec![502, 503, 504],
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Whether a response with this status is a transient failure
+    pub(crate) fn is_retryable_status(&self, status: StatusCode) -> bool {
+        self.status_codes.contains(&status.as_u16())
+    }
+
+    /// Whether a request error is a transient failure
+    pub(crate) fn is_retryable_error(error: &reqwest::Error) -> bool {
+        error.is_connect() || error.is_timeout() || error.is_request()
+    }
+
+    /// The delay before a retry, where `attempt` is the number of retries already made. The
+    /// delay is picked at random between half and all of the exponential backoff.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff);
+        let jitter = RandomState::new().build_hasher().finish() % 1000;
+        backoff / 2 + (backoff / 2).mul_f64(jitter as f64 / 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_backs_off_exponentially_up_to_the_maximum() {
+        let config = RetryConfig {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(300),
+            ..Default::default()
+        };
+
+        for (attempt, expected) in [(0, 100), (1, 200), (2, 300), (10, 300), (40, 300)] {
+            let backoff = config.backoff(attempt);
+            assert!(backoff <= Duration::from_millis(expected), "{backoff:?}");
+            assert!(
+                backoff >= Duration::from_millis(expected / 2),
+                "{backoff:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn it_retries_configured_status_codes() {
+        let config = RetryConfig::default();
+
+        assert!(config.is_retryable_status(StatusCode::BAD_GATEWAY));
+        assert!(!config.is_retryable_status(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(!config.is_retryable_status(StatusCode::OK));
+    }
+}
@@ -0,0 +1,384 @@
+//! WebSocket connections to upstream services
+//!
+//! Subscriptions over graphql-ws don't go through the shared HTTP client, so their connections
+//! are opened with a connector built from the same `upstream` config section: the TLS
+//! configuration, the proxy and the connect timeout apply to them as well. Only HTTP proxies,
+//! which are sent a `CONNECT` request for the WebSocket endpoint, are supported.
+
+use std::{io, sync::Arc, time::Duration};
+
+use base64::{Engine as _, prelude::BASE64_STANDARD};
+use http::Uri;
+use percent_encoding::percent_decode_str;
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{CryptoProvider, WebPkiSupportedAlgorithms};
+use rustls::pki_types::pem::PemObject as _;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{
+    CertificateError, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::{self, handshake::client::Request};
+use tokio_tungstenite::{
+    Connector, MaybeTlsStream, WebSocketStream, client_async_tls_with_config,
+    connect_async_tls_with_config,
+};
+use url::Url;
+
+use super::{UpstreamConfig, UpstreamTlsConfig};
+use crate::errors::ServerError;
+
+/// An open WebSocket connection
+pub type WebSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// The most bytes read from a proxy before the end of its response headers
+const MAX_PROXY_RESPONSE_BYTES: usize = 8 * 1024;
+
+/// Opens WebSocket connections to upstream services
+#[derive(Debug, Clone, Default)]
+pub struct WebSocketConnector {
+    /// The TLS configuration, when the system roots alone aren't enough
+    tls: Option<Arc<ClientConfig>>,
+    connect_timeout: Option<Duration>,
+    proxy: Option<Url>,
+}
+
+impl WebSocketConnector {
+    pub fn new(config: &UpstreamConfig) -> Result<Self, ServerError> {
+        Ok(Self {
+            tls: tls_config(&config.tls)?,
+            connect_timeout: Some(config.connect_timeout),
+            proxy: config.proxy.clone(),
+        })
+    }
+
+    /// Open a connection and complete the WebSocket handshake, through the proxy if one is
+    /// configured
+    pub async fn connect(&self, request: Request) -> Result<WebSocket, tungstenite::Error> {
+        let connector = self.tls.clone().map(Connector::Rustls);
+        let connect = async {
+            match &self.proxy {
+                Some(proxy) => {
+                    let stream = tunnel(proxy, request.uri()).await?;
+                    client_async_tls_with_config(request, stream, None, connector).await
+                }
+                None => connect_async_tls_with_config(request, None, false, connector).await,
+            }
+        };
+
+        let (socket, _) = match self.connect_timeout {
+            Some(connect_timeout) => tokio::time::timeout(connect_timeout, connect)
+                .await
+                .map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("connection timed out after {connect_timeout:?}"),
+                    )
+                })??,
+            None => connect.await?,
+        };
+        Ok(socket)
+    }
+}
+
+/// Build the TLS configuration for WebSocket connections. None is returned when the
+/// configuration doesn't change anything, so tokio-tungstenite's default, which trusts the
+/// system roots, is used.
+fn tls_config(tls: &UpstreamTlsConfig) -> Result<Option<Arc<ClientConfig>>, ServerError> {
+    let lenient = tls.danger_accept_invalid_certs || tls.danger_accept_invalid_hostnames;
+    if !lenient && tls.ca_bundle.is_none() && tls.client_cert.is_none() && tls.client_key.is_none()
+    {
+        return Ok(None);
+    }
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?;
+    let builder = if tls.danger_accept_invalid_certs {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(LenientVerifier {
+                webpki: None,
+                algorithms: provider.signature_verification_algorithms,
+            }))
+    } else {
+        let webpki = webpki_verifier(tls, &provider)?;
+        if tls.danger_accept_invalid_hostnames {
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(LenientVerifier {
+                    webpki: Some(webpki),
+                    algorithms: provider.signature_verification_algorithms,
+                }))
+        } else {
+            builder.with_webpki_verifier(webpki)
+        }
+    };
+
+    let config = match (&tls.client_cert, &tls.client_key) {
+        (Some(cert), Some(key)) => {
+            let certs = CertificateDer::pem_file_iter(cert)
+                .map_err(tls_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(tls_error)?;
+            let key = PrivateKeyDer::from_pem_file(key).map_err(tls_error)?;
+            builder
+                .with_client_auth_cert(certs, key)
+                .map_err(tls_error)?
+        }
+        (None, None) => builder.with_no_client_auth(),
+        _ => {
+            return Err(ServerError::UpstreamClient(
+                "client_cert and client_key must be set together".to_string(),
+            ));
+        }
+    };
+    Ok(Some(Arc::new(config)))
+}
+
+/// A verifier trusting the system roots and the configured certificate authorities
+fn webpki_verifier(
+    tls: &UpstreamTlsConfig,
+    provider: &Arc<CryptoProvider>,
+) -> Result<Arc<WebPkiServerVerifier>, ServerError> {
+    let mut roots = RootCertStore::empty();
+    roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+    if let Some(ca_bundle) = &tls.ca_bundle {
+        for certificate in CertificateDer::pem_file_iter(ca_bundle).map_err(tls_error)? {
+            roots
+                .add(certificate.map_err(tls_error)?)
+                .map_err(tls_error)?;
+        }
+    }
+    WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+        .build()
+        .map_err(tls_error)
+}
+
+fn tls_error(error: impl std::fmt::Display) -> ServerError {
+    ServerError::UpstreamClient(format!("invalid TLS configuration: {error}"))
+}
+
+/// Accepts invalid certificates, or only certificates for other hostnames when a verifier of
+/// the certificate chain is given. Handshake signatures are still verified.
+#[derive(Debug)]
+struct LenientVerifier {
+    webpki: Option<Arc<WebPkiServerVerifier>>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for LenientVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let Some(webpki) = &self.webpki else {
+            return Ok(ServerCertVerified::assertion());
+        };
+        match webpki.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+        {
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. },
+            )) => Ok(ServerCertVerified::assertion()),
+            result => result,
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+/// Open a tunnel to the host of a WebSocket endpoint through an HTTP proxy
+async fn tunnel(proxy: &Url, endpoint: &Uri) -> Result<TcpStream, tungstenite::Error> {
+    if proxy.scheme() != "http" {
+        return Err(proxy_error(format!(
+            "{} proxies are not supported for WebSocket connections",
+            proxy.scheme()
+        )));
+    }
+    let (Some(proxy_host), Some(proxy_port)) = (proxy.host_str(), proxy.port_or_known_default())
+    else {
+        return Err(proxy_error("the proxy URL has no host"));
+    };
+    let Some(host) = endpoint.host() else {
+        return Err(tungstenite::Error::Url(
+            tungstenite::error::UrlError::NoHostName,
+        ));
+    };
+    let port = endpoint
+        .port_u16()
+        .unwrap_or(if endpoint.scheme_str() == Some("wss") {
+            443
+        } else {
+            80
+        });
+
+    let mut stream = TcpStream::connect((proxy_host, proxy_port)).await?;
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if !proxy.username().is_empty() {
+        let credentials = format!(
+            "{}:{}",
+            percent_decode_str(proxy.username()).decode_utf8_lossy(),
+            percent_decode_str(proxy.password().unwrap_or_default()).decode_utf8_lossy()
+        );
+        request.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            BASE64_STANDARD.encode(credentials)
+        ));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read the response headers a byte at a time, so nothing sent through the tunnel is
+    // consumed
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_PROXY_RESPONSE_BYTES {
+            return Err(proxy_error("the proxy response headers are too large"));
+        }
+        response.push(stream.read_u8().await?);
+    }
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    match status_line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(stream),
+        _ => Err(proxy_error(format!(
+            "the proxy refused to connect: {status_line}"
+        ))),
+    }
+}
+
+fn proxy_error(message: impl Into<String>) -> tungstenite::Error {
+    tungstenite::Error::Io(io::Error::other(message.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt as _;
+    use tokio::io::AsyncBufReadExt as _;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest as _;
+
+    use super::*;
+
+    #[test]
+    fn it_only_builds_a_tls_config_when_needed() {
+        assert!(tls_config(&UpstreamTlsConfig::default()).unwrap().is_none());
+        assert!(
+            tls_config(&UpstreamTlsConfig {
+                danger_accept_invalid_certs: true,
+                ..Default::default()
+            })
+            .unwrap()
+            .is_some()
+        );
+        assert!(
+            tls_config(&UpstreamTlsConfig {
+                client_cert: Some("cert.pem".into()),
+                ..Default::default()
+            })
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn it_times_out_connecting() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+        let connector = WebSocketConnector {
+            connect_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+
+        let error = connector
+            .connect(endpoint.into_client_request().unwrap())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("timed out"), "{error}");
+        drop(listener);
+    }
+
+    #[tokio::test]
+    async fn it_connects_through_the_proxy() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = Url::parse(&format!(
+            "http://user:p%40ss@{}",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+
+        // The proxy serves the WebSocket itself once the tunnel is open
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = tokio::io::BufReader::new(stream);
+            let mut request = Vec::new();
+            loop {
+                let mut line = String::new();
+                stream.read_line(&mut line).await.unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                request.push(line);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                .await
+                .unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream.into_inner())
+                .await
+                .unwrap();
+            socket.close(None).await.unwrap();
+            request
+        });
+
+        let connector = WebSocketConnector {
+            proxy: Some(proxy),
+            ..Default::default()
+        };
+        let mut socket = connector
+            .connect(
+                "ws://api.example.com/graphql"
+                    .into_client_request()
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        while socket.next().await.is_some() {}
+
+        let request = server.await.unwrap();
+        assert_eq!(
+            request,
+            [
+                "CONNECT api.example.com:80 HTTP/1.1\r\n",
+                "Host: api.example.com:80\r\n",
+                "Proxy-Authorization: Basic dXNlcjpwQHNz\r\n",
+            ]
+        );
+    }
+}